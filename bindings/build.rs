@@ -10,7 +10,7 @@ winrt::build!(
       windows::storage::streams::{
         DataWriter, IDataWriterFactory, IBuffer
       }
-      windows::ui::xaml::{UIElement, RoutedEventHandler, Thickness, Visibility}
+      windows::ui::xaml::{UIElement, RoutedEventHandler, Thickness, Visibility, ElementTheme}
       windows::ui::xaml::controls::{
         Button, IButtonFactory, 
         IRelativePanelFactory, RelativePanel, 
@@ -28,7 +28,8 @@ winrt::build!(
         GridUnitType,
         GridLength,
         IGridStatics,
-        ProgressRing
+        ProgressRing,
+        ToolTipService
       }
       windows::ui::xaml::media::imaging::{
         SoftwareBitmapSource
@@ -0,0 +1,282 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+
+use crate::conf::{BrowserFields, ConfigReadLater, ReadLaterFormat};
+use crate::data::HistoryEntity;
+use crate::error::{BSError, BSResult};
+use crate::os::sys_browsers::Browser;
+use crate::os::ActiveWindowInfo;
+use crate::ui::{Image, ListItem};
+
+/// What the selector can do with the URLs instead of opening them. The actions are listed after the
+/// browsers and work the same: the config rules match them by name or by their `action:` path,
+/// and the statistics learn and predict them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    CopyUrls,
+    /// Shows the QR code of the first URL, to open it on a phone
+    QrCode,
+    ReadLater,
+    PrintUrls,
+    /// Lists the links opened lately, to open one again in another browser
+    History,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::CopyUrls,
+        Action::QrCode,
+        Action::ReadLater,
+        Action::PrintUrls,
+        Action::History,
+    ];
+
+    /// The name the config rules match, it stays English whatever the language of the list.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::CopyUrls => "Copy URL",
+            Action::QrCode => "Show QR code",
+            Action::ReadLater => "Read later",
+            Action::PrintUrls => "Print URL",
+            Action::History => "Recent links",
+        }
+    }
+
+    /// Stands for the executable path of a browser, in the config rules and the statistics.
+    pub fn path(&self) -> &'static str {
+        match self {
+            Action::CopyUrls => "action:copy",
+            Action::QrCode => "action:qr-code",
+            Action::ReadLater => "action:read-later",
+            Action::PrintUrls => "action:print",
+            Action::History => "action:history",
+        }
+    }
+
+    /// The id of the translated name, the description is the one with `-description` appended.
+    fn message_id(&self) -> &'static str {
+        match self {
+            Action::CopyUrls => "action-copy",
+            Action::QrCode => "action-qr-code",
+            Action::ReadLater => "action-read-later",
+            Action::PrintUrls => "action-print",
+            Action::History => "action-history",
+        }
+    }
+
+    pub fn get_hash(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.path().hash(&mut hasher);
+        hasher.finish().to_string()
+    }
+
+    /// The entry showing the action in the selector list.
+    pub fn list_item(&self) -> ListItem<Target> {
+        ListItem {
+            title: crate::i18n::text(self.message_id()),
+            subtitle: crate::i18n::text(&format!("{}-description", self.message_id())),
+            image: Image::default(),
+            uuid: self.get_hash(),
+            mnemonic: None,
+            state: std::rc::Rc::new(Target::Action(*self)),
+        }
+    }
+
+    /// Does the action with the `urls` opened from `source`. The QR code and the history are shown
+    /// by the selector itself, see [UserInterface::set_qr_code](crate::ui::UserInterface::set_qr_code)
+    /// and [UserInterface::set_history](crate::ui::UserInterface::set_history).
+    pub fn run(&self, urls: &[String], source: &ActiveWindowInfo, read_later: &ConfigReadLater) -> BSResult<()> {
+        match self {
+            Action::CopyUrls => crate::os::util::copy_to_clipboard(&urls.join("\n")),
+            Action::QrCode | Action::History => Ok(()),
+            Action::ReadLater => append_to_read_later(urls, source, read_later),
+            Action::PrintUrls => {
+                crate::os::attach_parent_console();
+                for url in urls {
+                    println!("{}", url);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Appends the `urls` to the read-later file with when they were saved and the app they came from.
+fn append_to_read_later(urls: &[String], source: &ActiveWindowInfo, settings: &ConfigReadLater) -> BSResult<()> {
+    let path = settings.file_path()?;
+    let saved_at = chrono::Local::now();
+    let source_name = source
+        .exe_path
+        .as_ref()
+        .and_then(|exe_path| exe_path.file_stem())
+        .map(|name| name.to_string_lossy().to_string());
+
+    let text: String = urls
+        .iter()
+        .map(|url| match settings.format {
+            ReadLaterFormat::Markdown => {
+                let from = source_name
+                    .as_ref()
+                    .map(|name| format!(" from {}", markdown_text(name)))
+                    .unwrap_or_default();
+                format!("- {} (saved {}{})\n", markdown_link(url), saved_at.format("%Y-%m-%d %H:%M"), from)
+            }
+            ReadLaterFormat::Jsonl => {
+                let entry = serde_json::json!({
+                    "url": url,
+                    "saved_at": saved_at.to_rfc3339(),
+                    "source": source.exe_path,
+                });
+                format!("{}\n", entry)
+            }
+        })
+        .collect();
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| BSError::io(&path, err))?;
+    file.write_all(text.as_bytes()).map_err(|err| BSError::io(&path, err))
+}
+
+/// `url` as a Markdown autolink. The link ends at the first `>` and can't hold spaces, so those
+/// are percent-encoded.
+fn markdown_link(url: &str) -> String {
+    let escaped: String = url
+        .chars()
+        .map(|c| match c {
+            ' ' => "%20".to_string(),
+            '<' => "%3C".to_string(),
+            '>' => "%3E".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    format!("<{}>", escaped)
+}
+
+/// `text` with the characters Markdown would take for formatting escaped by a backslash.
+fn markdown_text(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+/// What a list item stands for.
+// the targets are shared behind an Rc by the list items, they are not moved around
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum Target {
+    Browser(Browser),
+    Action(Action),
+    /// A link of the history, to be opened again
+    History(HistoryEntity),
+}
+
+impl Target {
+    pub fn get_hash(&self) -> String {
+        match self {
+            Target::Browser(browser) => browser.get_hash(),
+            Target::Action(action) => action.get_hash(),
+            Target::History(entry) => format!("history:{}", entry.id.unwrap_or_default()),
+        }
+    }
+
+    /// The executable path of the browser, or the `action:` path of the action. For a link of the
+    /// history, the path of the browser it was opened in.
+    pub fn path(&self) -> &str {
+        match self {
+            Target::Browser(browser) => &browser.exe_path,
+            Target::Action(action) => action.path(),
+            Target::History(entry) => entry.path.as_deref().unwrap_or_default(),
+        }
+    }
+
+    /// The attributes the config rules match on, actions only have a name and a path.
+    pub fn match_fields(&self) -> BrowserFields<'_> {
+        match self {
+            Target::Browser(browser) => browser.match_fields(),
+            Target::Action(action) => BrowserFields {
+                name: action.name(),
+                path: action.path(),
+                ..Default::default()
+            },
+            Target::History(entry) => BrowserFields {
+                path: entry.path.as_deref().unwrap_or_default(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn read_later(format: ReadLaterFormat, extension: &str) -> (ConfigReadLater, PathBuf) {
+        let path = std::env::temp_dir().join(format!("browsor-read-later-{}.{}", std::process::id(), extension));
+        let _ = std::fs::remove_file(&path);
+        let settings = ConfigReadLater {
+            path: path.to_string_lossy().to_string(),
+            format,
+        };
+        (settings, path)
+    }
+
+    fn source(exe_path: &str) -> ActiveWindowInfo {
+        ActiveWindowInfo {
+            window_name: None,
+            exe_path: Some(PathBuf::from(exe_path)),
+        }
+    }
+
+    #[test]
+    fn markdown_appends_escaped_entries() {
+        let (settings, path) = read_later(ReadLaterFormat::Markdown, "md");
+
+        append_to_read_later(&["https://example.com/a".to_string()], &source("/usr/bin/mail"), &settings).unwrap();
+        append_to_read_later(
+            &["https://example.com/search?q=<a b>".to_string()],
+            &source("/usr/bin/my_*chat*"),
+            &settings,
+        )
+        .unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("- <https://example.com/a> (saved "));
+        assert!(lines[0].ends_with(" from mail)"));
+        assert!(lines[1].starts_with("- <https://example.com/search?q=%3Ca%20b%3E> (saved "));
+        assert!(lines[1].ends_with(r" from my\_\*chat\*)"));
+    }
+
+    #[test]
+    fn jsonl_appends_escaped_entries() {
+        let (settings, path) = read_later(ReadLaterFormat::Jsonl, "jsonl");
+
+        append_to_read_later(&["https://example.com/a".to_string()], &source("/usr/bin/mail"), &settings).unwrap();
+        append_to_read_later(
+            &["https://example.com/\"quoted\"\n".to_string()],
+            &source("/usr/bin/\"chat\""),
+            &settings,
+        )
+        .unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let entries: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["url"], "https://example.com/a");
+        assert_eq!(entries[0]["source"], "/usr/bin/mail");
+        assert_eq!(entries[1]["url"], "https://example.com/\"quoted\"\n");
+        assert_eq!(entries[1]["source"], "/usr/bin/\"chat\"");
+        assert!(entries[1]["saved_at"].is_string());
+    }
+}
@@ -21,6 +21,6 @@ fn main() {
     let alice_exists = stmt.exists(rusqlite::params_from_iter([alice])).unwrap();
     let bob_exists = stmt.exists(rusqlite::params_from_iter([bob])).unwrap();
 
-    assert_eq!(alice_exists, true); // panics, but shouldn't
-    assert_eq!(bob_exists, false); // same, should be false but it yields true
+    assert!(alice_exists); // panics, but shouldn't
+    assert!(!bob_exists); // same, should be false but it yields true
 }
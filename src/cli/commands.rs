@@ -0,0 +1,479 @@
+use serde::Serialize;
+use std::path::Path;
+
+use super::{Command, ConfigCommand, HistoryCommand, OpenOptions, StatsCommand, USAGE};
+use crate::conf;
+use crate::data;
+use crate::error::BSError;
+use crate::log_error;
+use crate::os::sys_browsers::{self, Browser};
+
+/// Runs the commands that work without the selector window, returns the process exit code.
+pub fn run(command: Command, config_path: Option<&Path>) -> i32 {
+    crate::os::attach_parent_console();
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            0
+        }
+        Command::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            0
+        }
+        Command::Open(options) if options.dry_run => super::dry_run::trace(&options, config_path),
+        Command::Open(options) => open(&options, config_path),
+        Command::Daemon => unreachable!("The daemon runs the selector UI"),
+        Command::List { json: false } => list(config_path),
+        Command::List { json: true } => list_json(config_path),
+        Command::Register => register(),
+        Command::Unregister => unregister(),
+        Command::Config(ConfigCommand::Check) => config_check(config_path),
+        Command::Config(ConfigCommand::Path) => config_path_command(config_path),
+        Command::Config(ConfigCommand::Print) => config_print(config_path),
+        Command::Config(ConfigCommand::Upgrade { write }) => config_upgrade(config_path, write),
+        Command::Stats(StatsCommand::Summary) => stats_summary(config_path),
+        Command::Stats(StatsCommand::Path) => {
+            println!("{}", data::default_db_path().display());
+            0
+        }
+        Command::Stats(StatsCommand::Clear) => stats_clear(),
+        Command::History(HistoryCommand::List { json }) => history(json),
+        Command::History(HistoryCommand::Reopen { id, browser }) => history_reopen(id, &browser),
+    }
+}
+
+/// Reads the config the same way the selector does: on errors they are printed and the defaults are used.
+pub(super) fn read_config(config_path: Option<&Path>) -> conf::Config {
+    let config_result = conf::read_config(config_path);
+    for diagnostic in config_result.diagnostics.iter() {
+        eprintln!("{}", diagnostic);
+    }
+    if !config_result.diagnostics.is_empty() {
+        eprintln!("The config was ignored because of the errors above.");
+    }
+
+    config_result.config
+}
+
+pub(super) fn read_browsers() -> Option<Vec<Browser>> {
+    match sys_browsers::read_system_browsers_sync() {
+        Ok(browsers) => Some(browsers),
+        Err(err) => {
+            log_error("Cannot read the installed browsers", &err);
+            None
+        }
+    }
+}
+
+/// Finds the browser the user means by `name`: same name ignoring case, the executable path,
+/// or else the first browser with a name containing it.
+pub(super) fn find_browser<'a>(browsers: &'a [Browser], name: &str) -> Option<&'a Browser> {
+    let lowercase_name = name.to_lowercase();
+    browsers
+        .iter()
+        .find(|browser| browser.name.to_lowercase() == lowercase_name)
+        .or_else(|| browsers.iter().find(|browser| browser.exe_path == name))
+        .or_else(|| {
+            browsers
+                .iter()
+                .find(|browser| browser.name.to_lowercase().contains(&lowercase_name))
+        })
+}
+
+/// `open` with `--browser` or `--no-ui`. A browser given by name is used even when the config
+/// hides it, `--no-ui` picks the first browser of the list as it would be shown.
+/// Opening without the selector is not recorded in the statistics as no choice was made.
+fn open(options: &OpenOptions, config_path: Option<&Path>) -> i32 {
+    let config = read_config(config_path);
+    let urls = if options.urls.is_empty() {
+        vec![config.default_url.clone()]
+    } else {
+        options.urls.clone()
+    };
+    let Some(browsers) = read_browsers() else { return 1; };
+    let visible = config.visible_items(&browsers, Browser::match_fields);
+
+    let browser = match options.browser.as_deref() {
+        Some(name) => find_browser(&browsers, name),
+        None => visible.first().copied(),
+    };
+    let Some(browser) = browser else {
+        eprintln!(
+            "No browser found{}. Run `list` to see the available browsers.",
+            options.browser.as_deref().map(|name| format!(" for {}", name)).unwrap_or_default()
+        );
+        return 1;
+    };
+
+    match crate::os::util::spawn_browser_process(&browser.exe_path, browser.arguments.clone(), &urls) {
+        Ok(_) => 0,
+        Err(err) => {
+            log_error("Cannot open the browser", &err);
+            1
+        }
+    }
+}
+
+/// `list`: prints the browsers in the order the selector shows them, without the hidden ones.
+fn list(config_path: Option<&Path>) -> i32 {
+    let config = read_config(config_path);
+    let Some(browsers) = read_browsers() else { return 1; };
+    for browser in config.visible_items(&browsers, Browser::match_fields) {
+        println!("{}\t{}", browser.name, browser.exe_path);
+    }
+
+    0
+}
+
+/// A browser as printed by `list --json`.
+#[derive(Serialize)]
+struct ListedBrowser<'a> {
+    #[serde(flatten)]
+    browser: &'a Browser,
+    /// Identifies the browser in the statistics
+    hash: String,
+    hidden: bool,
+    hidden_reason: Option<String>,
+}
+
+/// `list --json`: prints all the browsers as a JSON array, the ones shown in the selector first
+/// and in the same order, followed by the hidden ones.
+fn list_json(config_path: Option<&Path>) -> i32 {
+    let config = read_config(config_path);
+    let Some(browsers) = read_browsers() else { return 1; };
+    let visible = config.visible_items(&browsers, Browser::match_fields);
+    let hidden = browsers
+        .iter()
+        .filter(|browser| !visible.iter().any(|visible_browser| std::ptr::eq(*visible_browser, *browser)));
+    let listed: Vec<ListedBrowser> = visible
+        .iter()
+        .copied()
+        .chain(hidden)
+        .map(|browser| {
+            let hidden_reason = config.hide_reason(&browser.match_fields());
+            ListedBrowser {
+                browser,
+                hash: browser.get_hash(),
+                hidden: hidden_reason.is_some(),
+                hidden_reason,
+            }
+        })
+        .collect();
+
+    match serde_json::to_string_pretty(&listed) {
+        Ok(json) => {
+            println!("{}", json);
+            0
+        }
+        Err(err) => {
+            log_error("Cannot print the browsers", &BSError::new(&err.to_string()));
+            1
+        }
+    }
+}
+
+fn register() -> i32 {
+    let exe_path = match std::env::current_exe() {
+        Ok(exe_path) => exe_path,
+        Err(err) => {
+            log_error("Cannot find the program path", &BSError::from(err));
+            return 1;
+        }
+    };
+
+    match crate::os::registration::register(&exe_path) {
+        Ok(_) => {
+            println!(
+                "Registered {} as a browser for the current user. {}",
+                exe_path.display(),
+                crate::os::registration::SET_DEFAULT_HINT
+            );
+            0
+        }
+        Err(err) => {
+            log_error("Registration failed", &err);
+            1
+        }
+    }
+}
+
+fn unregister() -> i32 {
+    match crate::os::registration::unregister() {
+        Ok(_) => {
+            println!("Removed the registration for the current user.");
+            0
+        }
+        Err(err) => {
+            log_error("Removing the registration failed", &err);
+            1
+        }
+    }
+}
+
+/// `config check`: prints the problems found in the config files, the exit code is 1 when there are any.
+fn config_check(config_path: Option<&Path>) -> i32 {
+    let config_result = conf::read_config(config_path);
+    for layer in config_result.layers.iter() {
+        println!("Found config ({}): {}", layer.source, layer.path.display());
+    }
+
+    if config_result.diagnostics.is_empty() {
+        println!("OK");
+        return 0;
+    }
+
+    for diagnostic in config_result.diagnostics.iter() {
+        println!("{}", diagnostic);
+    }
+    1
+}
+
+/// `config path`: prints where config files are looked for, highest priority first.
+fn config_path_command(config_path: Option<&Path>) -> i32 {
+    for layer in conf::get_config_search_paths(config_path) {
+        let state = if layer.path.is_file() { "found" } else { "not found" };
+        println!("{} ({}, {})", layer.path.display(), layer.source, state);
+    }
+
+    0
+}
+
+/// `config print`: prints the config in effect. Fails when the config has errors rather than
+/// printing the defaults the selector would fall back to.
+fn config_print(config_path: Option<&Path>) -> i32 {
+    let config_result = conf::read_config(config_path);
+    if !config_result.diagnostics.is_empty() {
+        for diagnostic in config_result.diagnostics.iter() {
+            eprintln!("{}", diagnostic);
+        }
+        return 1;
+    }
+
+    match serde_yaml::to_string(&config_result.config) {
+        Ok(text) => {
+            print!("{}", text);
+            0
+        }
+        Err(err) => {
+            log_error("Cannot print the config", &BSError::from(err));
+            1
+        }
+    }
+}
+
+/// `config upgrade [--write]`: upgrades the config files found to the current config version.
+/// Prints the upgraded files unless `write` is set in which case the files are replaced, keeping
+/// a copy of the original next to them.
+fn config_upgrade(config_path: Option<&Path>, write: bool) -> i32 {
+    let mut exit_code = 0;
+    for layer in conf::get_config_search_paths(config_path) {
+        let Ok(text) = std::fs::read_to_string(&layer.path) else { continue; };
+        let version = serde_yaml::from_str::<serde_json::Value>(&text)
+            .map(|document| conf::upgrade::document_version(&document))
+            .unwrap_or(conf::upgrade::CURRENT_CONFIG_VERSION);
+        if version == conf::upgrade::CURRENT_CONFIG_VERSION {
+            println!("{}: already at version {}", layer.path.display(), version);
+            continue;
+        }
+
+        let (upgraded, comments_kept) = match conf::upgrade::upgrade_text(&text) {
+            Ok(result) => result,
+            Err(message) => {
+                log_error("Cannot upgrade the config", &BSError::config(Some(&layer.path), &message));
+                exit_code = 1;
+                continue;
+            }
+        };
+        if !comments_kept {
+            println!("{}: comments could not be kept while upgrading", layer.path.display());
+        }
+
+        if !write {
+            println!("# {} upgraded from version {}", layer.path.display(), version);
+            println!("{}", upgraded);
+            continue;
+        }
+
+        let backup_path = layer.path.with_extension(format!("yml.v{}.bak", version));
+        let written = std::fs::copy(&layer.path, &backup_path)
+            .and_then(|_| std::fs::write(&layer.path, upgraded));
+        match written {
+            Ok(_) => println!(
+                "{}: upgraded from version {} to {}, original saved as {}",
+                layer.path.display(),
+                version,
+                conf::upgrade::CURRENT_CONFIG_VERSION,
+                backup_path.display()
+            ),
+            Err(err) => {
+                log_error("Failed writing the upgraded config", &BSError::io(&layer.path, err));
+                exit_code = 1;
+            }
+        }
+    }
+
+    exit_code
+}
+
+/// `stats`: how many times each browser was chosen, most chosen first.
+fn stats_summary(config_path: Option<&Path>) -> i32 {
+    let db_path = data::default_db_path();
+    if !db_path.is_file() {
+        println!("No statistics recorded.");
+        if !read_config(config_path).privacy.statistics {
+            println!("Statistics are off, enable `privacy.statistics` in the config to record them.");
+        }
+        return 0;
+    }
+
+    let mut repo = data::StatisticsRepository::new();
+    repo.set_db_path(&db_path);
+    let counts = repo.open_conn().and_then(|mut conn| {
+        data::migrations::migrate(&mut conn)?;
+        repo.choice_counts(&conn)
+    });
+    match counts {
+        Ok(counts) => {
+            for entry in counts.iter() {
+                println!(
+                    "{:>6}  {}  {}",
+                    entry.count.unwrap_or_default(),
+                    entry.last_date.as_deref().unwrap_or_default(),
+                    entry.path.as_deref().unwrap_or_default()
+                );
+            }
+            0
+        }
+        Err(err) => {
+            log_error("Cannot read the statistics", &err);
+            1
+        }
+    }
+}
+
+/// `stats clear`: deletes the statistics database, including the SQLite WAL files.
+fn stats_clear() -> i32 {
+    let db_path = data::default_db_path();
+    let mut exit_code = 0;
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file_name = db_path.clone().into_os_string();
+        file_name.push(suffix);
+        let path = Path::new(&file_name);
+        match std::fs::remove_file(path) {
+            Ok(_) => println!("Deleted {}", path.display()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => {
+                log_error("Cannot delete the statistics", &BSError::io(path, err));
+                exit_code = 1;
+            }
+        }
+    }
+
+    exit_code
+}
+
+/// Opens the statistics database for the history commands, `None` with the reason printed when
+/// nothing was recorded.
+fn open_statistics() -> Option<(data::StatisticsRepository, rusqlite::Connection)> {
+    let db_path = data::default_db_path();
+    if !db_path.is_file() {
+        println!("No history recorded, it is kept with the statistics when `privacy.statistics` is on.");
+        return None;
+    }
+
+    let mut repo = data::StatisticsRepository::new();
+    repo.set_db_path(&db_path);
+    let conn = repo.open_conn().and_then(|mut conn| {
+        data::migrations::migrate(&mut conn)?;
+        Ok(conn)
+    });
+    match conn {
+        Ok(conn) => Some((repo, conn)),
+        Err(err) => {
+            log_error("Cannot read the statistics", &err);
+            None
+        }
+    }
+}
+
+/// `history [--json]`: the last choices, latest first, with the ids `history reopen` takes.
+fn history(json: bool) -> i32 {
+    let Some((mut repo, conn)) = open_statistics() else { return 0; };
+    let entries = match repo.history(&conn, data::HISTORY_LENGTH) {
+        Ok(entries) => entries,
+        Err(err) => {
+            log_error("Cannot read the history", &err);
+            return 1;
+        }
+    };
+
+    if json {
+        return match serde_json::to_string_pretty(&entries) {
+            Ok(json) => {
+                println!("{}", json);
+                0
+            }
+            Err(err) => {
+                log_error("Cannot print the history", &BSError::new(&err.to_string()));
+                1
+            }
+        };
+    }
+    for entry in entries.iter() {
+        let browser = entry.path.as_deref().map(Path::new).and_then(Path::file_stem);
+        println!(
+            "{:>6}  {}  {}  {}  {}",
+            entry.id.unwrap_or_default(),
+            entry.local_date().unwrap_or_default(),
+            browser.map(|name| name.to_string_lossy()).unwrap_or_default(),
+            entry.source_name().unwrap_or_else(|| String::from("-")),
+            entry.url.as_deref().unwrap_or_default()
+        );
+    }
+
+    0
+}
+
+/// `history reopen <id> --browser <name>`: opens the URL of the entry in the browser and records
+/// it as the browser the choice should have been.
+fn history_reopen(id: usize, browser_name: &str) -> i32 {
+    let Some((mut repo, mut conn)) = open_statistics() else { return 1; };
+    let entry = match repo.history_entry(&conn, id) {
+        Ok(entry) => entry,
+        Err(err) => {
+            log_error("Cannot read the history", &err);
+            return 1;
+        }
+    };
+    let Some(url) = entry.and_then(|entry| entry.url) else {
+        eprintln!("No history entry {}. Run `history` to see the entries.", id);
+        return 1;
+    };
+
+    let Some(browsers) = read_browsers() else { return 1; };
+    let Some(browser) = find_browser(&browsers, browser_name) else {
+        eprintln!("No browser found for {}. Run `list` to see the available browsers.", browser_name);
+        return 1;
+    };
+    if let Err(err) = crate::os::util::spawn_browser_process(&browser.exe_path, browser.arguments.clone(), &[url]) {
+        log_error("Cannot open the browser", &err);
+        return 1;
+    }
+
+    // the browser may not be known to the statistics yet if the selector didn't list it since
+    let selection = data::SelectionEntity {
+        id: None,
+        path_hash: Some(browser.get_hash()),
+        path: Some(browser.exe_path.clone()),
+    };
+    let corrected = repo
+        .update_selections(&conn, vec![selection])
+        .and_then(|_| repo.correct_choice(&mut conn, id, &browser.get_hash()));
+    match corrected {
+        Ok(_) => 0,
+        Err(err) => {
+            log_error("Cannot record the correction in the statistics", &err);
+            1
+        }
+    }
+}
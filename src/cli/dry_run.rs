@@ -0,0 +1,136 @@
+use std::path::Path;
+use std::time::Duration;
+
+use super::OpenOptions;
+use crate::data::{self, CancellationToken, StatisticsRepository};
+use crate::error::BSResult;
+use crate::os::sys_browsers::Browser;
+
+/// `open --dry-run`: goes through the same steps as opening a URL and prints what each one
+/// decided, without launching the browser or writing statistics.
+pub fn trace(options: &OpenOptions, config_path: Option<&Path>) -> i32 {
+    let config = super::commands::read_config(config_path);
+    let urls = if options.urls.is_empty() {
+        println!("url: {} (default_url from the config)", config.default_url);
+        vec![config.default_url.clone()]
+    } else {
+        for url in options.urls.iter() {
+            println!("url: {}", url);
+        }
+        if options.urls.len() > 1 {
+            println!("batch: all the URLs open in the one browser chosen, the prediction is made for the first one");
+        }
+        options.urls.clone()
+    };
+
+    let Some(browsers) = super::commands::read_browsers() else { return 1; };
+    let visible = config.visible_items(&browsers, Browser::match_fields);
+    println!("rules:");
+    for browser in browsers.iter() {
+        let fields = browser.match_fields();
+        let outcome = match config.hide_reason(&fields) {
+            Some(reason) => format!("hidden, {}", reason),
+            None => {
+                let position = visible
+                    .iter()
+                    .position(|visible_browser| std::ptr::eq(*visible_browser, browser))
+                    .unwrap_or_default();
+                match config.order_reason(&fields) {
+                    Some(reason) => format!("shown at {}, {}", position + 1, reason),
+                    None => format!("shown at {}", position + 1),
+                }
+            }
+        };
+        println!("  {} ({}): {}", browser.name, browser.exe_path, outcome);
+    }
+
+    let predicted = if config.privacy.statistics {
+        match predict(&urls[0], &browsers) {
+            Ok(predicted) => {
+                println!("prediction:");
+                for browser in predicted.iter() {
+                    println!("  {} ({})", browser.name, browser.exe_path);
+                }
+                predicted
+            }
+            Err(err) => {
+                println!("prediction: none, {}", err.report());
+                Vec::new()
+            }
+        }
+    } else {
+        println!("prediction: off, `privacy.statistics` is not enabled");
+        Vec::new()
+    };
+
+    let browser = match options.browser.as_deref() {
+        Some(name) => {
+            let browser = super::commands::find_browser(&browsers, name);
+            if let Some(browser) = browser {
+                println!("choice: {}, given by --browser {}", browser.name, name);
+            }
+            browser
+        }
+        None if options.no_ui => {
+            let browser = visible.first().copied();
+            if let Some(browser) = browser {
+                println!("choice: {}, first in the list (--no-ui)", browser.name);
+            }
+            browser
+        }
+        None => {
+            let browser = predicted.first().copied().or_else(|| visible.first().copied());
+            if let Some(browser) = browser {
+                println!("choice: made in the selector, {} is assumed below", browser.name);
+            }
+            browser
+        }
+    };
+    let Some(browser) = browser else {
+        println!("choice: none, no browser matches");
+        return 1;
+    };
+
+    let arguments = crate::os::util::browser_command_arguments(browser.arguments.clone(), &urls);
+    let command_line = std::iter::once(&browser.exe_path)
+        .chain(arguments.iter())
+        .map(|arg| quote_argument(arg))
+        .collect::<Vec<String>>()
+        .join(" ");
+    println!("command: {}", command_line);
+
+    0
+}
+
+/// Runs the prediction on the statistics database, read only. The app the link came from is not
+/// known when running from the command line, so that factor never matches.
+fn predict<'a>(url: &str, browsers: &'a [Browser]) -> BSResult<Vec<&'a Browser>> {
+    let db_path = data::default_db_path();
+    if !db_path.is_file() {
+        return Err("no statistics recorded".into());
+    }
+
+    let mut repo = StatisticsRepository::new();
+    repo.set_db_path(&db_path);
+    let conn = repo.open_conn_read_only()?;
+    let token = CancellationToken::with_timeout(Duration::from_millis(crate::PREDICTION_TIMEOUT_MS));
+    let selections = repo.predict(&conn, None, url, &token)?;
+
+    Ok(selections
+        .iter()
+        .filter_map(|selection| {
+            browsers
+                .iter()
+                .find(|browser| selection.path_hash.as_deref() == Some(browser.get_hash().as_str()))
+        })
+        .collect())
+}
+
+/// Quotes the argument the way it would need to be typed in a terminal to get the same command.
+fn quote_argument(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(char::is_whitespace) {
+        format!("\"{}\"", arg)
+    } else {
+        String::from(arg)
+    }
+}
@@ -0,0 +1,351 @@
+pub mod commands;
+mod dry_run;
+
+use std::path::PathBuf;
+
+use crate::ui::Frontend;
+
+pub const USAGE: &str = "Usage:
+  browsor [<url>...]                 Shows the browser selector, the URLs all open in the chosen browser
+  browsor open <url>... [options]    Same as above, with options:
+      --browser <name>               Opens in the browser with the given name, without showing the selector
+      --no-ui                        Opens in the first browser of the list, without showing the selector
+      --dry-run                      Prints how the browser is chosen and the command that would
+                                     open it, without opening it
+      --ui <gui|tui>                 Shows the selector in a window or in the terminal. The terminal
+                                     is used by default when there is no display, ie. over SSH
+  browsor --daemon                   Keeps running in the background with the selector hidden, later
+                                     invocations hand their URLs over to it and exit right away
+  browsor list [--json]              Lists the browsers, after applying the config rules. With --json
+                                     all browsers are listed, with the reason the hidden ones are hidden
+  browsor register                   Registers as a browser for the current user
+  browsor unregister                 Removes the registration made by `register`
+  browsor config check               Validates the config files
+  browsor config path                Lists the places config files are looked for
+  browsor config print               Prints the config in effect, after merging all the config files
+  browsor config upgrade [--write]   Upgrades the config files to the current config version
+  browsor stats [summary]            Shows how often each browser was chosen
+  browsor stats path                 Prints the location of the statistics database
+  browsor stats clear                Deletes the statistics database
+  browsor history [--json]           Lists the URLs opened last, with the browser, the app they
+                                     came from and when
+  browsor history reopen <id> --browser <name>
+                                     Opens the URL of the history entry in another browser, the
+                                     statistics learn it as the browser it should have been
+
+Global options:
+  --config <path>                    Reads this config file before any other
+  -h, --help                         Prints this help
+  -V, --version                      Prints the version";
+
+#[derive(Debug, Default)]
+pub struct OpenOptions {
+    pub urls: Vec<String>,
+    /// Name of the browser to open the URLs in, skipping the selector
+    pub browser: Option<String>,
+    pub no_ui: bool,
+    pub dry_run: bool,
+    /// The frontend to show the selector with, detected when not given
+    pub ui: Option<Frontend>,
+}
+
+#[derive(Debug)]
+pub enum ConfigCommand {
+    Check,
+    Path,
+    Print,
+    Upgrade { write: bool },
+}
+
+#[derive(Debug)]
+pub enum StatsCommand {
+    Summary,
+    Path,
+    Clear,
+}
+
+#[derive(Debug)]
+pub enum HistoryCommand {
+    List { json: bool },
+    /// Opens the entry with the `id` in the `browser`, by name
+    Reopen { id: usize, browser: String },
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Open(OpenOptions),
+    Daemon,
+    List { json: bool },
+    Register,
+    Unregister,
+    Config(ConfigCommand),
+    Stats(StatsCommand),
+    History(HistoryCommand),
+    Version,
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Cli {
+    pub config_path: Option<PathBuf>,
+    pub command: Command,
+}
+
+/// Parses the command line `args`, without the program name.
+///
+/// The OS starts us as the URL handler with the URL as the first argument, so anything that is not
+/// a subcommand is an `open`. Handlers of other apps may be configured with extra flags, in that
+/// implicit form unknown flags are skipped instead of being taken for URLs. Returns the error
+/// message for invalid command lines.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+    let mut config_path = None;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                let path = args.next().ok_or("--config needs a path.")?;
+                config_path = Some(PathBuf::from(path));
+            }
+            "--" => {
+                // everything after is a URL, even if it looks like one of our options
+                rest.push(arg);
+                rest.extend(args.by_ref());
+            }
+            _ => rest.push(arg),
+        }
+    }
+
+    let command = match rest.first().map(String::as_str) {
+        Some("-h" | "--help" | "help") => Command::Help,
+        Some("-V" | "--version") => Command::Version,
+        Some("--daemon") => {
+            parse_flags("--daemon", &rest[1..], &[])?;
+            Command::Daemon
+        }
+        Some("open") => Command::Open(parse_open(&rest[1..], true)?),
+        Some("list") => {
+            let flags = parse_flags("list", &rest[1..], &["--json"])?;
+            Command::List {
+                json: flags.contains(&"--json"),
+            }
+        }
+        Some("register") => {
+            parse_flags("register", &rest[1..], &[])?;
+            Command::Register
+        }
+        Some("unregister") => {
+            parse_flags("unregister", &rest[1..], &[])?;
+            Command::Unregister
+        }
+        Some("config") => Command::Config(parse_config(&rest[1..])?),
+        Some("stats") => Command::Stats(parse_stats(&rest[1..])?),
+        Some("history") => Command::History(parse_history(&rest[1..])?),
+        _ => Command::Open(parse_open(&rest, false)?),
+    };
+
+    Ok(Cli {
+        config_path,
+        command,
+    })
+}
+
+fn parse_open(args: &[String], strict: bool) -> Result<OpenOptions, String> {
+    let mut options = OpenOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--browser" => {
+                let name = args.next().ok_or("--browser needs a browser name.")?;
+                options.browser = Some(name.clone());
+            }
+            "--ui" => {
+                let name = args.next().ok_or("--ui needs gui or tui.")?;
+                let frontend = Frontend::from_name(name)
+                    .ok_or_else(|| format!("Unknown UI {}, expected gui or tui.", name))?;
+                options.ui = Some(frontend);
+            }
+            "--no-ui" => options.no_ui = true,
+            "--dry-run" => options.dry_run = true,
+            "--" => options.urls.extend(args.by_ref().cloned()),
+            flag if flag.starts_with('-') && strict => {
+                return Err(format!("Unknown option {} for open.", flag));
+            }
+            // passed by a handler, not meant for us. Its value goes with it
+            flag if flag.starts_with('-') => {
+                let has_value = !flag.contains('=')
+                    && args.as_slice().first().is_some_and(|value| !value.starts_with('-') && !looks_like_url(value));
+                if has_value {
+                    args.next();
+                }
+            }
+            url => options.urls.push(String::from(url)),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Whether an argument following an unknown option is a URL rather than the option value, ie.
+/// `-url https://example.com` against `--profile-directory Default`. Domains and file paths count
+/// as URLs, browsers open them too.
+fn looks_like_url(arg: &str) -> bool {
+    arg.contains(['.', ':', '/', '\\'])
+}
+
+fn parse_config(args: &[String]) -> Result<ConfigCommand, String> {
+    let rest = args.get(1..).unwrap_or_default();
+    match args.first().map(String::as_str) {
+        Some("check") => parse_flags("config check", rest, &[]).map(|_| ConfigCommand::Check),
+        Some("path") => parse_flags("config path", rest, &[]).map(|_| ConfigCommand::Path),
+        Some("print") => parse_flags("config print", rest, &[]).map(|_| ConfigCommand::Print),
+        Some("upgrade") => parse_flags("config upgrade", rest, &["--write"]).map(|flags| {
+            ConfigCommand::Upgrade {
+                write: flags.contains(&"--write"),
+            }
+        }),
+        Some(other) => Err(format!("Unknown config command {}.", other)),
+        None => Err(String::from("The config command needs one of: check, path, print, upgrade.")),
+    }
+}
+
+fn parse_stats(args: &[String]) -> Result<StatsCommand, String> {
+    let rest = args.get(1..).unwrap_or_default();
+    match args.first().map(String::as_str) {
+        None => Ok(StatsCommand::Summary),
+        Some("summary") => parse_flags("stats summary", rest, &[]).map(|_| StatsCommand::Summary),
+        Some("path") => parse_flags("stats path", rest, &[]).map(|_| StatsCommand::Path),
+        Some("clear") => parse_flags("stats clear", rest, &[]).map(|_| StatsCommand::Clear),
+        Some(other) => Err(format!("Unknown stats command {}.", other)),
+    }
+}
+
+fn parse_history(args: &[String]) -> Result<HistoryCommand, String> {
+    match args.first().map(String::as_str) {
+        Some("reopen") => {
+            let id = args.get(1).ok_or("history reopen needs the id of the entry.")?;
+            let id = id.parse().map_err(|_| format!("Invalid history entry id {}.", id))?;
+            let browser = match args.get(2..).unwrap_or_default() {
+                [flag, name] if flag == "--browser" => name.clone(),
+                [] => return Err(String::from("history reopen needs --browser <name>.")),
+                [arg, ..] => return Err(format!("Unexpected argument {} for history reopen.", arg)),
+            };
+            Ok(HistoryCommand::Reopen { id, browser })
+        }
+        _ => parse_flags("history", args, &["--json"]).map(|flags| HistoryCommand::List {
+            json: flags.contains(&"--json"),
+        }),
+    }
+}
+
+/// Checks that `args` are only flags from `known` and returns the ones given.
+fn parse_flags<'a>(command: &str, args: &'a [String], known: &[&str]) -> Result<Vec<&'a str>, String> {
+    args.iter()
+        .map(|arg| {
+            if known.contains(&arg.as_str()) {
+                Ok(arg.as_str())
+            } else {
+                Err(format!("Unexpected argument {} for {}.", arg, command))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Cli, String> {
+        parse(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    fn open_options(args: &[&str]) -> OpenOptions {
+        match parse_args(args).unwrap().command {
+            Command::Open(options) => options,
+            command => panic!("Expected open, got {:?}", command),
+        }
+    }
+
+    #[test]
+    fn urls_alone_open_the_selector() {
+        let options = open_options(&["https://a.example", "https://b.example"]);
+
+        assert_eq!(options.urls, ["https://a.example", "https://b.example"]);
+        assert!(options.browser.is_none() && !options.no_ui && !options.dry_run);
+    }
+
+    #[test]
+    fn open_takes_its_options() {
+        let options = open_options(&["open", "https://a.example", "--browser", "firefox", "--dry-run", "--ui", "tui"]);
+
+        assert_eq!(options.urls, ["https://a.example"]);
+        assert_eq!(options.browser.as_deref(), Some("firefox"));
+        assert!(options.dry_run);
+        assert_eq!(options.ui, Some(Frontend::Tui));
+    }
+
+    #[test]
+    fn open_rejects_unknown_options() {
+        assert!(parse_args(&["open", "--bogus", "https://a.example"]).is_err());
+        assert!(parse_args(&["open", "--ui", "web"]).is_err());
+        assert!(parse_args(&["open", "--browser"]).is_err());
+    }
+
+    #[test]
+    fn unknown_options_are_skipped_with_their_value_without_a_command() {
+        let options = open_options(&["--profile-directory", "Default", "https://a.example"]);
+        assert_eq!(options.urls, ["https://a.example"]);
+
+        let options = open_options(&["-osint", "-url", "https://a.example", "--flag=value"]);
+        assert_eq!(options.urls, ["https://a.example"]);
+    }
+
+    #[test]
+    fn urls_after_double_dash_are_taken_as_is() {
+        let options = open_options(&["--", "--config", "https://a.example"]);
+
+        assert_eq!(options.urls, ["--config", "https://a.example"]);
+    }
+
+    #[test]
+    fn config_path_is_global() {
+        let cli = parse_args(&["list", "--config", "/tmp/config.yml"]).unwrap();
+
+        assert_eq!(cli.config_path, Some(PathBuf::from("/tmp/config.yml")));
+        assert!(matches!(cli.command, Command::List { json: false }));
+        assert!(parse_args(&["--config"]).is_err());
+    }
+
+    #[test]
+    fn subcommands_are_parsed() {
+        assert!(matches!(parse_args(&["--daemon"]).unwrap().command, Command::Daemon));
+        assert!(matches!(parse_args(&["list", "--json"]).unwrap().command, Command::List { json: true }));
+        assert!(matches!(
+            parse_args(&["config", "upgrade", "--write"]).unwrap().command,
+            Command::Config(ConfigCommand::Upgrade { write: true })
+        ));
+        assert!(matches!(parse_args(&["stats"]).unwrap().command, Command::Stats(StatsCommand::Summary)));
+        assert!(matches!(
+            parse_args(&["history", "--json"]).unwrap().command,
+            Command::History(HistoryCommand::List { json: true })
+        ));
+        assert!(matches!(parse_args(&["-h"]).unwrap().command, Command::Help));
+    }
+
+    #[test]
+    fn history_reopen_needs_an_id_and_a_browser() {
+        let command = parse_args(&["history", "reopen", "42", "--browser", "firefox"]).unwrap().command;
+        assert!(matches!(command, Command::History(HistoryCommand::Reopen { id: 42, ref browser }) if browser == "firefox"));
+
+        assert!(parse_args(&["history", "reopen", "x", "--browser", "firefox"]).is_err());
+        assert!(parse_args(&["history", "reopen", "42"]).is_err());
+    }
+
+    #[test]
+    fn subcommands_reject_unexpected_arguments() {
+        assert!(parse_args(&["list", "--all"]).is_err());
+        assert!(parse_args(&["register", "now"]).is_err());
+        assert!(parse_args(&["config", "bogus"]).is_err());
+        assert!(parse_args(&["stats", "bogus"]).is_err());
+    }
+}
@@ -0,0 +1,996 @@
+pub mod upgrade;
+pub mod watcher;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::error::{BSError, BSResult};
+
+/// The browser attributes the config rules can match on.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BrowserFields<'a> {
+    pub name: &'a str,
+    pub path: &'a str,
+    pub company: &'a str,
+    pub version: &'a str,
+    pub description: &'a str,
+}
+
+/// A rule selecting browsers, used by `hide`, `only` and `order`. The browser matches when any
+/// of the defined fields matches, undefined (empty) fields are ignored.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ConfigBrowserMatch {
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub company: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// The patterns compiled once the config is validated when `regex` is on, by field name
+    #[serde(skip)]
+    compiled: Vec<(&'static str, regex::Regex)>,
+}
+
+impl ConfigBrowserMatch {
+    /// The defined patterns with the name of the field they apply to.
+    fn patterns<'a>(&'a self) -> impl Iterator<Item = (&'static str, &'a str)> + 'a {
+        [
+            ("name", self.name.as_str()),
+            ("path", self.path.as_str()),
+            ("company", self.company.as_str()),
+            ("version", self.version.as_str()),
+            ("description", self.description.as_str()),
+        ]
+        .into_iter()
+        .filter(|(_, pattern)| !pattern.is_empty())
+    }
+
+    fn pattern_matches(&self, field: &str, pattern: &str, value: &str) -> bool {
+        if self.regex {
+            // invalid expressions are reported by validate_config, they never match
+            self.compiled
+                .iter()
+                .find(|(compiled_field, _)| *compiled_field == field)
+                .is_some_and(|(_, regex)| regex.is_match(value))
+        } else if self.case_insensitive {
+            value.to_lowercase().contains(&pattern.to_lowercase())
+        } else {
+            value.contains(pattern)
+        }
+    }
+
+    pub fn matches(&self, browser: &BrowserFields) -> bool {
+        self.matching_field(browser).is_some()
+    }
+
+    /// Name of the first field of the rule that matches the browser.
+    pub fn matching_field(&self, browser: &BrowserFields) -> Option<&'static str> {
+        self.patterns()
+            .find(|(field, pattern)| {
+                let value = match *field {
+                    "name" => browser.name,
+                    "path" => browser.path,
+                    "company" => browser.company,
+                    "version" => browser.version,
+                    _ => browser.description,
+                };
+                self.pattern_matches(field, pattern, value)
+            })
+            .map(|(field, _)| field)
+    }
+
+    /// Compiles the regular expressions of this rule when `regex` is on. Returns the errors of those
+    /// that don't compile, by field name.
+    fn compile(&mut self) -> Vec<(&'static str, String)> {
+        if !self.regex {
+            return Vec::new();
+        }
+
+        let mut errors = Vec::new();
+        let compiled = self
+            .patterns()
+            .filter_map(|(field, pattern)| {
+                let built = regex::RegexBuilder::new(pattern)
+                    .case_insensitive(self.case_insensitive)
+                    .build();
+                match built {
+                    Ok(regex) => Some((field, regex)),
+                    Err(err) => {
+                        errors.push((field, err.to_string()));
+                        None
+                    }
+                }
+            })
+            .collect();
+        self.compiled = compiled;
+
+        errors
+    }
+}
+
+/// A letter that opens the browsers matching `browser` when pressed with Alt in the selector.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConfigShortcut {
+    pub key: char,
+    pub browser: ConfigBrowserMatch,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ConfigPrivacy {
+    #[serde(default)]
+    pub statistics: bool,
+}
+
+/// How the "Read later" action writes the URLs.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadLaterFormat {
+    /// A list item per URL, to read as is
+    #[default]
+    Markdown,
+    /// A JSON object per line, for scripts
+    Jsonl,
+}
+
+/// Where the "Read later" action appends the URLs.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ConfigReadLater {
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub format: ReadLaterFormat,
+}
+
+impl ConfigReadLater {
+    /// The configured file, `read-later.md` or `read-later.jsonl` in the user data directory by default.
+    pub fn file_path(&self) -> BSResult<PathBuf> {
+        if !self.path.is_empty() {
+            return Ok(PathBuf::from(&self.path));
+        }
+
+        let file_name = match self.format {
+            ReadLaterFormat::Markdown => "read-later.md",
+            ReadLaterFormat::Jsonl => "read-later.jsonl",
+        };
+        Ok(crate::os::util::get_user_data_dir()?.join(file_name))
+    }
+}
+
+/// Light or dark colors for the selector.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    /// As the OS is set
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// How much space the list items take.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListDensity {
+    Compact,
+    #[default]
+    Comfortable,
+}
+
+/// How the selector looks, every frontend applies what it can, see
+/// [UserInterface::set_theme](crate::ui::UserInterface::set_theme).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConfigTheme {
+    #[serde(default)]
+    pub mode: ThemeMode,
+    /// As `#RRGGBB`, the OS accent color when empty
+    #[serde(default)]
+    pub accent: String,
+    /// In points, the frontend picks when not set
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    #[serde(default)]
+    pub density: ListDensity,
+    /// The version, architecture and company under the browser names
+    #[serde(default = "default_true")]
+    pub subtitles: bool,
+    #[serde(default = "default_true")]
+    pub icons: bool,
+    /// Of the window, in logical pixels
+    #[serde(default = "default_window_width")]
+    pub width: u32,
+    /// The window is this tall, the list scrolls when it doesn't fit
+    #[serde(default = "default_window_max_height")]
+    pub max_height: u32,
+}
+
+impl ConfigTheme {
+    /// The configured accent color as red, green and blue.
+    pub fn accent_rgb(&self) -> Option<[u8; 3]> {
+        let hex = self.accent.strip_prefix('#')?;
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+}
+
+impl Default for ConfigTheme {
+    fn default() -> Self {
+        ConfigTheme {
+            mode: Default::default(),
+            accent: Default::default(),
+            font_size: None,
+            density: Default::default(),
+            subtitles: true,
+            icons: true,
+            width: default_window_width(),
+            max_height: default_window_max_height(),
+        }
+    }
+}
+
+/// Where the selector window opens.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowPlacement {
+    /// Centered on the monitor showing the mouse cursor
+    #[default]
+    Cursor,
+    /// Centered on the primary monitor
+    Center,
+    /// Where it was last closed, with the size it had, for each monitor setup
+    Last,
+    /// With its top left corner at `x`, `y` on the desktop, in physical pixels
+    Fixed { x: i32, y: i32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ConfigWindow {
+    #[serde(default)]
+    pub placement: WindowPlacement,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_window_width() -> u32 {
+    400
+}
+
+fn default_window_max_height() -> u32 {
+    400
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Config {
+    pub version: i16,
+
+    #[serde(default)]
+    pub default_url: String,
+
+    #[serde(default)]
+    pub privacy: ConfigPrivacy,
+
+    #[serde(default)]
+    pub hide: Vec<ConfigBrowserMatch>,
+
+    #[serde(default)]
+    pub only: Vec<ConfigBrowserMatch>,
+
+    #[serde(default)]
+    pub order: Vec<ConfigBrowserMatch>,
+
+    #[serde(default)]
+    pub shortcuts: Vec<ConfigShortcut>,
+
+    #[serde(default)]
+    pub read_later: ConfigReadLater,
+
+    #[serde(default)]
+    pub theme: ConfigTheme,
+
+    #[serde(default)]
+    pub window: ConfigWindow,
+}
+
+impl Config {
+    /// A browser is hidden when it matches a `hide` rule, or when there is an `only` allow-list
+    /// and it matches none of its rules.
+    pub fn browser_is_hidden(&self, browser: &BrowserFields) -> bool {
+        self.hide_reason(browser).is_some()
+    }
+
+    /// Why the browser is hidden, pointing to the rules that hide it as in the config diagnostics.
+    pub fn hide_reason(&self, browser: &BrowserFields) -> Option<String> {
+        if let Some(pointer) = Self::first_match("hide", &self.hide, browser) {
+            return Some(format!("matches {}", pointer));
+        }
+        if !self.only.is_empty() && !self.only.iter().any(|rule| rule.matches(browser)) {
+            return Some(String::from("matches no rule in /only"));
+        }
+
+        None
+    }
+
+    /// Position of the browser in the list: the index of the first `order` rule it matches,
+    /// browsers matching no rule come after all the others.
+    pub fn browser_order_rank(&self, browser: &BrowserFields) -> usize {
+        self.order
+            .iter()
+            .position(|rule| rule.matches(browser))
+            .unwrap_or(self.order.len())
+    }
+
+    /// Why the browser is moved up the list, if an `order` rule matches it.
+    pub fn order_reason(&self, browser: &BrowserFields) -> Option<String> {
+        Self::first_match("order", &self.order, browser).map(|pointer| format!("pinned by {}", pointer))
+    }
+
+    /// The letter of the first `shortcuts` entry matching the browser. Browsers without one get
+    /// a letter from their name.
+    pub fn browser_mnemonic(&self, browser: &BrowserFields) -> Option<char> {
+        self.shortcuts
+            .iter()
+            .find(|shortcut| shortcut.browser.matches(browser))
+            .map(|shortcut| shortcut.key)
+    }
+
+    /// Pointer to the field of the first rule in `rules` that matches, ie. `/hide/1/name`.
+    fn first_match(list_name: &str, rules: &[ConfigBrowserMatch], browser: &BrowserFields) -> Option<String> {
+        rules.iter().enumerate().find_map(|(index, rule)| {
+            rule.matching_field(browser)
+                .map(|field| format!("/{}/{}/{}", list_name, index, field))
+        })
+    }
+
+    /// Keeps the `items` not hidden by the rules, sorted by the `order` rules. The sort is stable,
+    /// the items not pinned by the config keep the order they were found in.
+    pub fn visible_items<'a, T>(&self, items: &'a [T], fields: impl Fn(&'a T) -> BrowserFields<'a>) -> Vec<&'a T> {
+        let mut visible: Vec<&'a T> = items
+            .iter()
+            .filter(|item| !self.browser_is_hidden(&fields(item)))
+            .collect();
+        visible.sort_by_key(|item| self.browser_order_rank(&fields(item)));
+
+        visible
+    }
+
+    /// Compiles the regular expressions of the rules. Returns the locations and messages of those
+    /// that don't compile.
+    fn compile_rules(&mut self) -> Vec<(String, String)> {
+        let shortcut_rules = self.shortcuts.iter_mut().enumerate().map(|(index, shortcut)| {
+            (format!("/shortcuts/{}/browser", index), &mut shortcut.browser)
+        });
+
+        [("hide", &mut self.hide), ("only", &mut self.only), ("order", &mut self.order)]
+            .into_iter()
+            .flat_map(|(list_name, rules)| {
+                rules
+                    .iter_mut()
+                    .enumerate()
+                    .map(move |(index, rule)| (format!("/{}/{}", list_name, index), rule))
+            })
+            .chain(shortcut_rules)
+            .flat_map(|(rule_pointer, rule)| {
+                rule.compile()
+                    .into_iter()
+                    .map(move |(field, message)| (format!("{}/{}", rule_pointer, field), message))
+            })
+            .collect()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: upgrade::CURRENT_CONFIG_VERSION as i16,
+            hide: Default::default(),
+            only: Default::default(),
+            order: Default::default(),
+            shortcuts: Default::default(),
+            read_later: Default::default(),
+            theme: Default::default(),
+            window: Default::default(),
+            default_url: String::from("about:home"),
+            privacy: Default::default(),
+        }
+    }
+}
+
+/// Environment variable pointing to a config file, takes priority over the standard locations.
+pub const CONFIG_ENV_VAR: &str = "BROWSOR_CONFIG";
+const CONFIG_FILE_NAME: &str = "config.yml";
+
+/// A problem found in a config file, pointing to where it is both in the YAML text
+/// (`line`, `column`, 1 based) and in the config structure (`pointer`, as JSON pointer).
+#[derive(Debug, Clone)]
+pub struct ConfigDiagnostic {
+    pub path: Option<PathBuf>,
+    pub pointer: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = self.path.as_ref() {
+            write!(f, "{}:", path.display())?;
+        }
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{}:{}:", line, column)?;
+        }
+        if !self.pointer.is_empty() {
+            write!(f, " {}:", self.pointer)?;
+        }
+
+        write!(f, " {}", self.message)
+    }
+}
+
+/// Where a config file was found, in order of priority.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigSource {
+    /// `--config <path>` command line argument
+    Argument,
+    /// [CONFIG_ENV_VAR] environment variable
+    Environment,
+    User,
+    System,
+    /// Next to the executable, as installed by the setup script
+    Executable,
+}
+
+impl std::fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConfigSource::Argument => "--config argument",
+            ConfigSource::Environment => CONFIG_ENV_VAR,
+            ConfigSource::User => "user",
+            ConfigSource::System => "system",
+            ConfigSource::Executable => "executable directory",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigLayer {
+    pub path: PathBuf,
+    pub source: ConfigSource,
+}
+
+/// Outcome of reading the config files. The `config` is always usable, when the files have errors
+/// it holds the defaults and the errors are listed in `diagnostics`.
+pub struct ConfigReadResult {
+    pub config: Config,
+    /// The files that were found and merged, highest priority first
+    pub layers: Vec<ConfigLayer>,
+    pub diagnostics: Vec<ConfigDiagnostic>,
+}
+
+/// Lists the places a config file is looked for, highest priority first.
+pub fn get_config_search_paths(argument_path: Option<&Path>) -> Vec<ConfigLayer> {
+    let mut layers = Vec::new();
+    if let Some(path) = argument_path {
+        layers.push(ConfigLayer {
+            path: PathBuf::from(path),
+            source: ConfigSource::Argument,
+        });
+    }
+    if let Some(path) = std::env::var_os(CONFIG_ENV_VAR).filter(|path| !path.is_empty()) {
+        layers.push(ConfigLayer {
+            path: PathBuf::from(path),
+            source: ConfigSource::Environment,
+        });
+    }
+    if let Ok(dir) = crate::os::util::get_user_config_dir() {
+        layers.push(ConfigLayer {
+            path: dir.join(CONFIG_FILE_NAME),
+            source: ConfigSource::User,
+        });
+    }
+    if let Ok(dir) = crate::os::util::get_system_config_dir() {
+        layers.push(ConfigLayer {
+            path: dir.join(CONFIG_FILE_NAME),
+            source: ConfigSource::System,
+        });
+    }
+    if let Ok(mut path) = std::env::current_exe() {
+        path.set_file_name(CONFIG_FILE_NAME);
+        layers.push(ConfigLayer {
+            path,
+            source: ConfigSource::Executable,
+        });
+    }
+
+    layers
+}
+
+/// Reads all the config files found in the search paths and merges them so that settings
+/// from a higher priority file override the same settings from lower priority ones, ie. user
+/// settings override system wide settings. Mappings are merged key by key, anything else is replaced.
+///
+/// When any of the files has errors, all are ignored and the defaults are used.
+pub fn read_config(argument_path: Option<&Path>) -> ConfigReadResult {
+    let mut layers = Vec::new();
+    let mut documents = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for layer in get_config_search_paths(argument_path) {
+        match read_config_file(&layer.path) {
+            Ok(None) if layer.source == ConfigSource::Argument => diagnostics.push(ConfigDiagnostic {
+                path: Some(layer.path.clone()),
+                pointer: String::new(),
+                line: None,
+                column: None,
+                message: String::from("File not found or empty."),
+            }),
+            Ok(None) => continue,
+            Ok(Some(contents)) => match parse_layer(&contents) {
+                Ok(document) => documents.push(document),
+                Err(layer_diagnostics) => {
+                    diagnostics.extend(layer_diagnostics.into_iter().map(|mut diagnostic| {
+                        diagnostic.path = Some(layer.path.clone());
+                        diagnostic
+                    }));
+                }
+            },
+            Err(err) => diagnostics.push(ConfigDiagnostic {
+                path: Some(layer.path.clone()),
+                pointer: String::new(),
+                line: None,
+                column: None,
+                message: err.report(),
+            }),
+        }
+        layers.push(layer);
+    }
+
+    if !diagnostics.is_empty() || documents.is_empty() {
+        return ConfigReadResult {
+            config: Config::default(),
+            layers,
+            diagnostics,
+        };
+    }
+
+    let merged = documents
+        .iter()
+        .rev()
+        .fold(serde_json::Value::Null, |mut merged, (document, _)| {
+            merge_values(&mut merged, document.clone());
+            merged
+        });
+
+    // report a problem at the location of the highest priority file that sets it
+    let locate = |pointer: &str| {
+        layers
+            .iter()
+            .zip(documents.iter())
+            .find_map(|(layer, (_, locations))| {
+                locations
+                    .get(pointer)
+                    .map(|location| (layer.path.clone(), *location))
+            })
+    };
+
+    let config = match validate_config(merged) {
+        Ok(config) => config,
+        Err(mut errors) => {
+            for diagnostic in errors.iter_mut() {
+                if let Some((path, (line, column))) = locate(&diagnostic.pointer) {
+                    diagnostic.path = Some(path);
+                    diagnostic.line = Some(line);
+                    diagnostic.column = Some(column);
+                }
+            }
+            diagnostics = errors;
+            Config::default()
+        }
+    };
+
+    ConfigReadResult {
+        config,
+        layers,
+        diagnostics,
+    }
+}
+
+/// Returns the contents of the config file at `path` or `None` when there is
+/// no file or it is empty, in which case the defaults apply.
+fn read_config_file(path: &Path) -> BSResult<Option<String>> {
+    let mut file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(BSError::io(path, err)),
+    };
+
+    let mut config_contents = String::new();
+    match file.read_to_string(&mut config_contents) {
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(config_contents)),
+        Err(err) => Err(BSError::io(path, err)),
+    }
+}
+
+fn parse_yaml(contents: &str) -> Result<serde_json::Value, ConfigDiagnostic> {
+    serde_yaml::from_str(contents).map_err(|err| {
+        let location = err.location();
+        ConfigDiagnostic {
+            path: None,
+            pointer: String::new(),
+            line: location.as_ref().map(|loc| loc.line()),
+            column: location.as_ref().map(|loc| loc.column()),
+            message: err.to_string(),
+        }
+    })
+}
+
+/// A config file brought to the current version, with the line and column of its values by JSON pointer.
+type ParsedLayer = (serde_json::Value, HashMap<String, (usize, usize)>);
+
+/// Parses a config file and brings it to the current config version. Files of older versions
+/// are validated against the schema of their version first so the upgrade can rely on their shape.
+fn parse_layer(contents: &str) -> Result<ParsedLayer, Vec<ConfigDiagnostic>> {
+    let mut document = parse_yaml(contents).map_err(|diagnostic| vec![diagnostic])?;
+    let locations = yaml_pointer_locations(contents);
+    let diagnostic_at = |pointer: String, message: String| {
+        let location = locations.get(&pointer);
+        ConfigDiagnostic {
+            line: location.map(|(line, _)| *line),
+            column: location.map(|(_, column)| *column),
+            pointer,
+            message,
+            path: None,
+        }
+    };
+
+    let version = upgrade::document_version(&document);
+    if version < upgrade::CURRENT_CONFIG_VERSION {
+        upgrade::validate_with_version_schema(&document, version).map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|(pointer, message)| diagnostic_at(pointer, message))
+                .collect::<Vec<_>>()
+        })?;
+    }
+
+    upgrade::upgrade_document(&mut document)
+        .map_err(|message| vec![diagnostic_at(String::from("/version"), message)])?;
+
+    Ok((document, locations))
+}
+
+/// Recursively merges `overlay` into `base`, keys of `overlay` win.
+fn merge_values(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    use serde_json::Value;
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                merge_values(base_map.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Validates the config document against the current config schema. Collects all the schema
+/// violations instead of stopping at the first one, the returned diagnostics have no location.
+pub fn validate_config(config_value: serde_json::Value) -> Result<Config, Vec<ConfigDiagnostic>> {
+    upgrade::validate_with_version_schema(&config_value, upgrade::CURRENT_CONFIG_VERSION).map_err(
+        |errors| {
+            errors
+                .into_iter()
+                .map(|(pointer, message)| ConfigDiagnostic {
+                    path: None,
+                    pointer,
+                    line: None,
+                    column: None,
+                    message,
+                })
+                .collect::<Vec<_>>()
+        },
+    )?;
+
+    let mut config: Config = serde_json::from_value(config_value).map_err(|err| {
+        vec![ConfigDiagnostic {
+            path: None,
+            pointer: String::new(),
+            line: None,
+            column: None,
+            message: err.to_string(),
+        }]
+    })?;
+
+    // the schema can't tell if a regular expression compiles, they are compiled once here
+    let regex_diagnostics: Vec<ConfigDiagnostic> = config
+        .compile_rules()
+        .into_iter()
+        .map(|(pointer, message)| ConfigDiagnostic {
+            path: None,
+            pointer,
+            line: None,
+            column: None,
+            message,
+        })
+        .collect();
+    if !regex_diagnostics.is_empty() {
+        return Err(regex_diagnostics);
+    }
+
+    Ok(config)
+}
+
+/// Writes the `diagnostics` in a log file in the user data directory and returns the log path.
+pub fn write_diagnostics_log(diagnostics: &[ConfigDiagnostic]) -> BSResult<PathBuf> {
+    let log_path = crate::os::util::get_user_data_dir()?.join("config-errors.log");
+    let mut text = format!(
+        "[{}] Ignored the config because of the errors below, using defaults instead.\n",
+        chrono::Local::now().to_rfc3339(),
+    );
+    for diagnostic in diagnostics {
+        text.push_str(&format!("{}\n", diagnostic));
+    }
+
+    std::fs::write(&log_path, text).map_err(|err| BSError::io(&log_path, err))?;
+    Ok(log_path)
+}
+
+/// Maps the JSON pointer of every node in the YAML `contents` to its line and column.
+/// Values in mappings point to their key as that is where the user would look.
+///
+/// serde_yaml does not keep positions once the document is parsed, so we walk the parser events instead.
+fn yaml_pointer_locations(contents: &str) -> HashMap<String, (usize, usize)> {
+    use yaml_rust::parser::{Event, Parser};
+
+    enum Frame {
+        Mapping { pointer: String, key: Option<String> },
+        Sequence { pointer: String, index: usize },
+    }
+
+    let mut locations = HashMap::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut parser = Parser::new(contents.chars());
+
+    while let Ok((event, marker)) = parser.next() {
+        let location = (marker.line(), marker.col() + 1);
+        // determine the pointer of the node the event starts, None when it is a mapping key
+        let node_pointer = match &event {
+            Event::Scalar(..) | Event::Alias(_) | Event::MappingStart(_) | Event::SequenceStart(_) => {
+                match stack.last_mut() {
+                    None => Some(String::new()),
+                    Some(Frame::Sequence { pointer, index }) => {
+                        *index += 1;
+                        Some(format!("{}/{}", pointer, *index - 1))
+                    }
+                    Some(Frame::Mapping { pointer, key }) => match key.take() {
+                        Some(key) => Some(format!("{}/{}", pointer, key)),
+                        None => {
+                            if let Event::Scalar(value, ..) = &event {
+                                let escaped = value.replace('~', "~0").replace('/', "~1");
+                                locations.insert(format!("{}/{}", pointer, escaped), location);
+                                *key = Some(escaped);
+                            }
+                            None
+                        }
+                    },
+                }
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                stack.pop();
+                None
+            }
+            Event::StreamEnd => break,
+            _ => None,
+        };
+
+        if let Some(pointer) = node_pointer {
+            locations.entry(pointer.clone()).or_insert(location);
+            match event {
+                Event::MappingStart(_) => stack.push(Frame::Mapping { pointer, key: None }),
+                Event::SequenceStart(_) => stack.push(Frame::Sequence { pointer, index: 0 }),
+                _ => (),
+            }
+        }
+    }
+
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_values, validate_config, yaml_pointer_locations, BrowserFields};
+    use serde_json::json;
+
+    fn firefox() -> BrowserFields<'static> {
+        BrowserFields {
+            name: "Firefox Developer Edition",
+            path: "/usr/bin/firefox",
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn regex_rules_are_compiled_when_validated() {
+        let config = validate_config(json!({
+            "version": 2,
+            "hide": [{ "name": "^firefox", "regex": true, "case_insensitive": true }],
+        }))
+        .unwrap();
+
+        assert!(config.browser_is_hidden(&firefox()));
+    }
+
+    #[test]
+    fn invalid_regex_rules_are_reported_at_load() {
+        let errors = validate_config(json!({
+            "version": 2,
+            "order": [{ "path": "/usr/bin/(firefox", "regex": true }],
+        }))
+        .err()
+        .unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "/order/0/path");
+    }
+
+    #[test]
+    fn merge_overlays_nested_keys() {
+        let mut base = json!({ "version": 2, "privacy": { "statistics": true, "other": 1 } });
+        merge_values(&mut base, json!({ "privacy": { "statistics": false } }));
+
+        assert_eq!(base, json!({ "version": 2, "privacy": { "statistics": false, "other": 1 } }));
+    }
+
+    #[test]
+    fn merge_replaces_lists_and_scalars() {
+        let mut base = json!({ "hide": [{ "name": "Edge" }], "default_url": "https://a.example" });
+        merge_values(&mut base, json!({ "hide": [{ "name": "Opera" }], "default_url": "https://b.example" }));
+
+        assert_eq!(base, json!({ "hide": [{ "name": "Opera" }], "default_url": "https://b.example" }));
+    }
+
+    #[test]
+    fn merge_into_nothing_takes_the_overlay() {
+        let mut base = serde_json::Value::Null;
+        merge_values(&mut base, json!({ "version": 2 }));
+
+        assert_eq!(base, json!({ "version": 2 }));
+    }
+
+    #[test]
+    fn merge_lets_an_object_replace_a_scalar() {
+        let mut base = json!({ "theme": "dark" });
+        merge_values(&mut base, json!({ "theme": { "mode": "light" } }));
+
+        assert_eq!(base, json!({ "theme": { "mode": "light" } }));
+    }
+
+    /// Name, company and version of the browsers the rule tests run on.
+    const BROWSERS: [(&str, &str, &str); 4] = [
+        ("Firefox", "Mozilla Corporation", "118.0.1"),
+        ("Google Chrome", "Google LLC", "117.0.5938"),
+        ("Microsoft Edge", "Microsoft Corporation", "116.0.1938"),
+        ("Opera", "Opera Software", "102.0.4880"),
+    ];
+
+    fn browser_fields(browser: &(&'static str, &'static str, &'static str)) -> BrowserFields<'static> {
+        let &(name, company, version) = browser;
+        BrowserFields {
+            name,
+            company,
+            version,
+            ..Default::default()
+        }
+    }
+
+    fn visible_names(config: serde_json::Value) -> Vec<&'static str> {
+        let config = validate_config(config).unwrap();
+        config
+            .visible_items(&BROWSERS, browser_fields)
+            .into_iter()
+            .map(|(name, ..)| *name)
+            .collect()
+    }
+
+    #[test]
+    fn every_hide_rule_applies() {
+        let names = visible_names(json!({
+            "version": 2,
+            "hide": [{ "name": "Edge" }, { "name": "Opera" }],
+        }));
+
+        assert_eq!(names, ["Firefox", "Google Chrome"]);
+    }
+
+    #[test]
+    fn only_keeps_the_allowed_browsers() {
+        let names = visible_names(json!({
+            "version": 2,
+            "only": [{ "name": "Opera" }, { "name": "Firefox" }],
+        }));
+
+        assert_eq!(names, ["Firefox", "Opera"]);
+    }
+
+    #[test]
+    fn order_pins_browsers_and_keeps_the_others_in_place() {
+        let names = visible_names(json!({
+            "version": 2,
+            "order": [{ "name": "Opera" }, { "name": "Edge" }],
+        }));
+
+        assert_eq!(names, ["Opera", "Microsoft Edge", "Firefox", "Google Chrome"]);
+    }
+
+    #[test]
+    fn case_insensitive_plain_rules_ignore_the_case() {
+        let config = validate_config(json!({
+            "version": 2,
+            "hide": [{ "name": "FIREFOX", "case_insensitive": true }],
+            "order": [{ "name": "OPERA" }],
+        }))
+        .unwrap();
+
+        assert!(config.browser_is_hidden(&browser_fields(&BROWSERS[0])));
+        // without case_insensitive the case has to match, Opera is not pinned
+        assert_eq!(config.browser_order_rank(&browser_fields(&BROWSERS[3])), 1);
+    }
+
+    #[test]
+    fn rules_match_the_version_and_company() {
+        let config = validate_config(json!({
+            "version": 2,
+            "hide": [{ "company": "Microsoft" }, { "version": "^117\\.", "regex": true }],
+        }))
+        .unwrap();
+
+        assert_eq!(config.hide_reason(&browser_fields(&BROWSERS[2])).as_deref(), Some("matches /hide/0/company"));
+        assert_eq!(config.hide_reason(&browser_fields(&BROWSERS[1])).as_deref(), Some("matches /hide/1/version"));
+        assert!(!config.browser_is_hidden(&browser_fields(&BROWSERS[0])));
+    }
+
+    const LOCATED_YAML: &str = "version: 2
+privacy:
+  statistics: false
+hide:
+  - name: Edge
+  - name: Opera
+    path: /usr/bin/opera
+";
+
+    #[test]
+    fn locations_of_nested_map_keys() {
+        let locations = yaml_pointer_locations(LOCATED_YAML);
+
+        assert_eq!(locations["/version"], (1, 1));
+        assert_eq!(locations["/privacy/statistics"], (3, 3));
+    }
+
+    #[test]
+    fn locations_of_sequence_items() {
+        let locations = yaml_pointer_locations(LOCATED_YAML);
+
+        assert_eq!(locations["/hide/0/name"], (5, 5));
+        assert_eq!(locations["/hide/1/name"], (6, 5));
+        assert_eq!(locations["/hide/1/path"], (7, 5));
+    }
+
+    #[test]
+    fn location_of_a_key_with_a_nested_block_is_the_key() {
+        let locations = yaml_pointer_locations(LOCATED_YAML);
+
+        assert_eq!(locations["/privacy"], (2, 1));
+        assert_eq!(locations["/hide"], (4, 1));
+    }
+}
@@ -0,0 +1,233 @@
+use jsonschema::{Draft, JSONSchema};
+use serde_json::Value;
+
+/// The config version this build reads. Older config files are upgraded on the fly
+/// (see [upgrade_document]), newer ones are rejected.
+pub const CURRENT_CONFIG_VERSION: i64 = 2;
+
+/// Schema of every config version, index 0 is version 1.
+const SCHEMAS: &[&str] = &[
+    include_str!("../data/conf.schema.v1.yaml"),
+    include_str!("../data/conf.schema.v2.yaml"),
+];
+
+/// A step upgrading a config document from version `from` to `from + 1`.
+struct Upgrade {
+    from: i64,
+    /// Transforms the parsed document, always succeeds on a document valid for the `from` version schema.
+    document: fn(&mut Value),
+    /// Same transformation made on the YAML text so that comments and formatting survive,
+    /// returns `None` when the text is not laid out in a way the step knows how to edit.
+    text: fn(&str) -> Option<String>,
+}
+
+// Think about how older files get to the new shape when adding versions
+const UPGRADES: &[Upgrade] = &[Upgrade {
+    from: 1,
+    document: upgrade_v1_document,
+    text: upgrade_v1_text,
+}];
+
+/// v2 groups the settings about data the program keeps under `privacy`,
+/// `statistics: bool` becomes `privacy: { statistics: bool }`.
+fn upgrade_v1_document(document: &mut Value) {
+    if let Value::Object(map) = document {
+        if let Some(statistics) = map.remove("statistics") {
+            let mut privacy = serde_json::Map::new();
+            privacy.insert(String::from("statistics"), statistics);
+            map.insert(String::from("privacy"), Value::Object(privacy));
+        }
+    }
+}
+
+fn upgrade_v1_text(text: &str) -> Option<String> {
+    if top_level_key_lines(text, "privacy").next().is_some() {
+        return None;
+    }
+
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let statistics_lines: Vec<usize> = top_level_key_lines(text, "statistics").collect();
+    match statistics_lines.as_slice() {
+        [] => (),
+        [index] => {
+            let value = lines[*index]["statistics:".len()..].trim_start().to_string();
+            if value.is_empty() {
+                return None; // value on the following lines, not something we expect for a boolean
+            }
+            lines[*index] = format!("privacy:\n  statistics: {}", value);
+        }
+        _ => return None,
+    }
+
+    Some(lines.join("\n") + "\n")
+}
+
+/// Indexes of the lines where the top level `key` is defined.
+fn top_level_key_lines<'a>(text: &'a str, key: &'a str) -> impl Iterator<Item = usize> + 'a {
+    text.lines()
+        .enumerate()
+        .filter(move |(_, line)| {
+            line.strip_prefix(key)
+                .is_some_and(|rest| rest.starts_with(':'))
+        })
+        .map(|(index, _)| index)
+}
+
+/// Sets the top level `version` value in the YAML `text`, keeping any trailing comment.
+fn set_text_version(text: &str, version: i64) -> Option<String> {
+    let index = top_level_key_lines(text, "version").next()?;
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let comment = lines[index]
+        .find(" #")
+        .map(|position| lines[index][position..].to_string())
+        .unwrap_or_default();
+    lines[index] = format!("version: {}{}", version, comment);
+
+    Some(lines.join("\n") + "\n")
+}
+
+/// Reads the `version` of the document, files without it are considered to be current
+/// as only files that were never upgraded can lack it (ie. partial config layers). The v1 schema
+/// allows any number, `1.0` is version 1.
+pub fn document_version(document: &Value) -> i64 {
+    document
+        .get("version")
+        .and_then(|version| {
+            version.as_i64().or_else(|| {
+                version
+                    .as_f64()
+                    .filter(|version| version.fract() == 0.0)
+                    .map(|version| version as i64)
+            })
+        })
+        .unwrap_or(CURRENT_CONFIG_VERSION)
+}
+
+/// Validates `document` against the schema of the given config `version`.
+pub fn validate_with_version_schema(document: &Value, version: i64) -> Result<(), Vec<(String, String)>> {
+    let schema_yaml = SCHEMAS
+        .get((version - 1) as usize)
+        .ok_or_else(|| vec![(String::from("/version"), format!("Unknown config version {}.", version))])?;
+    let schema = serde_yaml::from_str(schema_yaml).unwrap_or_default();
+    let compiled_schema = JSONSchema::options()
+        .with_draft(Draft::Draft7)
+        .compile(&schema)
+        .expect("Invalid config schema."); // the schemas are bundled, a failure here is a bug
+
+    let result = compiled_schema.validate(document);
+    result.map_err(|errors| {
+        errors
+            .map(|error| (error.instance_path.to_string(), error.to_string()))
+            .collect()
+    })
+}
+
+/// Brings a config document of any older version to [CURRENT_CONFIG_VERSION] and sets its
+/// `version`, also when it had none so that the merged layers always have one.
+/// Returns the version the document had, errors when the version is newer than what this build knows.
+pub fn upgrade_document(document: &mut Value) -> Result<i64, String> {
+    let version = document_version(document);
+    if version > CURRENT_CONFIG_VERSION {
+        return Err(format!(
+            "Config version {} is newer than version {} supported by this build of {}. Update the program or lower the config version.",
+            version,
+            CURRENT_CONFIG_VERSION,
+            env!("CARGO_PKG_NAME"),
+        ));
+    }
+
+    for upgrade in UPGRADES.iter().filter(|upgrade| upgrade.from >= version) {
+        (upgrade.document)(document);
+    }
+    if let Value::Object(map) = document {
+        map.insert(String::from("version"), Value::from(CURRENT_CONFIG_VERSION));
+    }
+
+    Ok(version)
+}
+
+/// Upgrades the YAML `text` of a config file to [CURRENT_CONFIG_VERSION]. Edits the text in place
+/// to keep comments, when that is not possible for a step the upgraded document is serialized
+/// again and comments are lost. Returns the new text and whether comments were kept.
+pub fn upgrade_text(text: &str) -> Result<(String, bool), String> {
+    let original: Value = serde_yaml::from_str(text).map_err(|err| err.to_string())?;
+    let mut expected = original.clone();
+    let version = upgrade_document(&mut expected)?;
+
+    let edited = UPGRADES
+        .iter()
+        .filter(|upgrade| upgrade.from >= version)
+        .try_fold(String::from(text), |text, upgrade| {
+            set_text_version(&(upgrade.text)(&text)?, upgrade.from + 1)
+        });
+
+    // only trust the text edit if it means the same thing as the document upgrade
+    match edited {
+        Some(edited)
+            if serde_yaml::from_str::<Value>(&edited).is_ok_and(|value| value == expected) =>
+        {
+            Ok((edited, true))
+        }
+        _ => serde_yaml::to_string(&expected)
+            .map(|text| (text, false))
+            .map_err(|err| err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn upgrade_text_keeps_comments() {
+        let text = "# mine\nversion: 1 # old\nstatistics: false # no tracking\n";
+
+        let (upgraded, comments_kept) = upgrade_text(text).unwrap();
+
+        assert!(comments_kept);
+        assert_eq!(upgraded, "# mine\nversion: 2 # old\nprivacy:\n  statistics: false # no tracking\n");
+    }
+
+    #[test]
+    fn upgrade_text_serializes_layouts_it_cant_edit() {
+        let (upgraded, comments_kept) = upgrade_text("{ version: 1, statistics: true } # flow").unwrap();
+
+        assert!(!comments_kept);
+        let upgraded: Value = serde_yaml::from_str(&upgraded).unwrap();
+        assert_eq!(upgraded, json!({ "version": 2, "privacy": { "statistics": true } }));
+    }
+
+    #[test]
+    fn upgrade_text_takes_a_float_version() {
+        let (upgraded, _) = upgrade_text("version: 1.0\nstatistics: true\n").unwrap();
+
+        assert_eq!(upgraded, "version: 2\nprivacy:\n  statistics: true\n");
+    }
+
+    #[test]
+    fn upgrade_text_rejects_newer_versions() {
+        assert!(upgrade_text("version: 3\n").is_err());
+    }
+
+    #[test]
+    fn upgrade_text_rejects_invalid_yaml() {
+        assert!(upgrade_text("version: [1\n").is_err());
+    }
+
+    #[test]
+    fn document_version_reads_integers_and_whole_floats() {
+        assert_eq!(document_version(&json!({ "version": 1 })), 1);
+        assert_eq!(document_version(&json!({ "version": 1.0 })), 1);
+        assert_eq!(document_version(&json!({ "version": 1.5 })), CURRENT_CONFIG_VERSION);
+        assert_eq!(document_version(&json!({})), CURRENT_CONFIG_VERSION);
+    }
+
+    #[test]
+    fn upgrade_document_sets_the_version_of_layers_without_one() {
+        let mut document = json!({ "privacy": { "statistics": false } });
+
+        assert_eq!(upgrade_document(&mut document), Ok(CURRENT_CONFIG_VERSION));
+        assert_eq!(document["version"], json!(CURRENT_CONFIG_VERSION));
+    }
+}
@@ -0,0 +1,143 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use super::ConfigLayer;
+use crate::error::{BSError, BSResult};
+
+/// Editors often save with several writes (truncate, write, rename), wait for them to settle.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Watches the config files for changes, including files that don't exist yet such as a user
+/// config created after the program started. While the directory of a file doesn't exist either,
+/// its nearest existing ancestor is watched until the directory is created. Uses the OS native
+/// notifications (inotify on Linux, ReadDirectoryChangesW on Windows) through [notify].
+///
+/// It does not reload by itself, [`poll`](ConfigWatcher::poll) is meant to be called from the
+/// event loop, the same way [crate::data::thread_worker::ThreadWorker::tick] is.
+pub struct ConfigWatcher {
+    // kept alive for as long as we want notifications
+    watcher: RecommendedWatcher,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    files: Vec<PathBuf>,
+    /// The directories watched, see [watched_dirs]
+    dirs: Vec<PathBuf>,
+    last_change: Option<Instant>,
+}
+
+impl ConfigWatcher {
+    pub fn new(layers: &[ConfigLayer]) -> BSResult<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(Self::to_bs_error)?;
+
+        let files: Vec<PathBuf> = layers.iter().map(|layer| layer.path.clone()).collect();
+        let dirs = watched_dirs(&files);
+        for dir in dirs.iter() {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(Self::to_bs_error)?;
+        }
+
+        Ok(Self {
+            watcher,
+            receiver,
+            files,
+            dirs,
+            last_change: None,
+        })
+    }
+
+    /// Returns true once after a config file has changed and no other change came for a short while.
+    pub fn poll(&mut self) -> bool {
+        let mut dirs_changed = false;
+        while let Ok(event) = self.receiver.try_recv() {
+            let Ok(event) = event else { continue };
+            if event.paths.iter().any(|path| self.files.contains(path)) {
+                self.last_change = Some(Instant::now());
+            }
+            // a directory on the way to a config file was created or removed
+            dirs_changed |= event.paths.iter().any(|path| {
+                !self.files.contains(path) && self.files.iter().any(|file| file.starts_with(path))
+            });
+        }
+        // the file may have been written to the new directory before it was watched
+        if dirs_changed && self.rearm() {
+            self.last_change = Some(Instant::now());
+        }
+
+        match self.last_change {
+            Some(last_change) if last_change.elapsed() >= DEBOUNCE => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the watches to the directories there are now. Returns true when they moved.
+    fn rearm(&mut self) -> bool {
+        let dirs = watched_dirs(&self.files);
+        if dirs == self.dirs {
+            return false;
+        }
+
+        for dir in self.dirs.iter().filter(|dir| !dirs.contains(dir)) {
+            // fails when the directory is gone, the watch went with it
+            let _ = self.watcher.unwatch(dir);
+        }
+        for dir in dirs.iter().filter(|dir| !self.dirs.contains(dir)) {
+            if let Err(err) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                crate::log_error("Config changes may be missed", &Self::to_bs_error(err));
+            }
+        }
+        self.dirs = dirs;
+
+        true
+    }
+
+    fn to_bs_error(err: notify::Error) -> BSError {
+        BSError::Platform {
+            msg: String::from("Cannot watch the config files for changes"),
+            source: Some(Box::new(err)),
+        }
+    }
+}
+
+/// The directories to watch for the config `files`: their own directory, as files that are replaced
+/// on save or created later would otherwise be missed, or the nearest ancestor that exists while
+/// it doesn't.
+fn watched_dirs(files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|file| file.ancestors().skip(1).find(|dir| dir.is_dir()))
+        .map(PathBuf::from)
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::watched_dirs;
+
+    #[test]
+    fn missing_directories_are_watched_from_the_nearest_ancestor() {
+        let root = std::env::temp_dir().join(format!("browsor-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("existing")).unwrap();
+        let files = [
+            root.join("existing").join("config.yml"),
+            root.join("missing").join("nested").join("config.yml"),
+        ];
+
+        let dirs = watched_dirs(&files);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(dirs, vec![root.clone(), root.join("existing")]);
+    }
+}
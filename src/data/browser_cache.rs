@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::error::{BSError, BSResult};
+use crate::os::sys_browsers::Browser;
+use crate::os::IconPixels;
+
+const CACHE_FILE_NAME: &str = "browsers.cache.json";
+const ICONS_DIR_NAME: &str = "browser-icons";
+
+/// Bump on any change to the cached data, caches of other versions are ignored and rebuilt.
+const CACHE_VERSION: u32 = 2;
+
+/// The browsers found by the last discovery, so that the next run can show them without reading
+/// the executables again. Each icon is kept as a PNG file next to the cache file.
+#[derive(Serialize, Deserialize)]
+pub struct BrowserCache {
+    version: u32,
+    /// Fingerprint of what the registry listed, see [sources_fingerprint]
+    sources: String,
+    browsers: Vec<CachedBrowser>,
+}
+
+/// A browser as it was when cached, it is only reused while its executable stays the same.
+#[derive(Serialize, Deserialize)]
+struct CachedBrowser {
+    browser: Browser,
+    /// `None` when the executable couldn't be read, ie. it is gone but still registered. The entry
+    /// stays current for as long as that lasts
+    exe: Option<ExeStamp>,
+    /// File name of the icon in the icons dir, `None` when the icon couldn't be read
+    icon: Option<String>,
+}
+
+/// What tells that an executable changed.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+struct ExeStamp {
+    modified: SystemTime,
+    size: u64,
+}
+
+impl ExeStamp {
+    fn read(exe_path: &str) -> Option<Self> {
+        let metadata = std::fs::metadata(exe_path).ok()?;
+        Some(ExeStamp {
+            modified: metadata.modified().ok()?,
+            size: metadata.len(),
+        })
+    }
+}
+
+impl BrowserCache {
+    pub fn default_path() -> BSResult<PathBuf> {
+        Ok(crate::os::util::get_user_data_dir()?.join(CACHE_FILE_NAME))
+    }
+
+    /// Reads the cache, `None` when there is none yet or it was written by another version.
+    pub fn read(path: &Path) -> BSResult<Option<Self>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(BSError::io(path, err)),
+        };
+        let cache: BrowserCache = serde_json::from_str(&text)
+            .map_err(|err| BSError::new(&format!("Invalid browser cache {}: {}", path.display(), err)))?;
+
+        Ok(Some(cache).filter(|cache| cache.version == CACHE_VERSION))
+    }
+
+    /// The cached browsers with their icons, in the order they were discovered.
+    pub fn browsers(&self, path: &Path) -> Vec<(Browser, Option<IconPixels>)> {
+        self.browsers
+            .iter()
+            .map(|entry| (entry.browser.clone(), entry.read_icon(path)))
+            .collect()
+    }
+
+    /// True when the registry lists the same `browsers` as when the cache was written
+    /// and none of their executables changed since.
+    pub fn is_valid_for(&self, browsers: &[Browser]) -> bool {
+        self.sources == sources_fingerprint(browsers)
+            && self.browsers.len() == browsers.len()
+            && self.browsers.iter().all(|entry| entry.is_current())
+    }
+
+    /// The cached details and icon of the browser at `exe_path`, if its executable didn't change.
+    pub fn lookup(&self, path: &Path, exe_path: &str) -> Option<(Browser, Option<IconPixels>)> {
+        self.browsers
+            .iter()
+            .find(|entry| entry.browser.exe_path == exe_path && entry.is_current())
+            .map(|entry| (entry.browser.clone(), entry.read_icon(path)))
+    }
+
+    /// Replaces the cache at `path` with the given browsers. `sources` are the browsers as listed by
+    /// the registry, before their details were read.
+    ///
+    /// The icons and then the cache file are written aside and renamed into place, another instance
+    /// reading the cache meanwhile finds the previous one. Icons of browsers that are gone go with it.
+    pub fn write(path: &Path, sources: &[Browser], browsers: &[(Browser, Option<IconPixels>)]) -> BSResult<()> {
+        let new_icons_dir = path.with_file_name(format!("{}.new-{}", ICONS_DIR_NAME, std::process::id()));
+        std::fs::create_dir_all(&new_icons_dir).map_err(|err| BSError::io(&new_icons_dir, err))?;
+        let entries = match write_icons(&new_icons_dir, browsers) {
+            Ok(entries) => entries,
+            Err(err) => {
+                let _ = std::fs::remove_dir_all(&new_icons_dir);
+                return Err(err);
+            }
+        };
+        replace_dir(&new_icons_dir, &icons_dir(path))?;
+
+        let cache = BrowserCache {
+            version: CACHE_VERSION,
+            sources: sources_fingerprint(sources),
+            browsers: entries,
+        };
+        let text = serde_json::to_string_pretty(&cache)
+            .map_err(|err| BSError::new(&format!("Cannot write the browser cache: {}", err)))?;
+        let new_path = path.with_extension(format!("json.new-{}", std::process::id()));
+        std::fs::write(&new_path, text).map_err(|err| BSError::io(&new_path, err))?;
+        std::fs::rename(&new_path, path).map_err(|err| BSError::io(path, err))
+    }
+}
+
+impl CachedBrowser {
+    fn is_current(&self) -> bool {
+        ExeStamp::read(&self.browser.exe_path) == self.exe
+    }
+
+    fn read_icon(&self, cache_path: &Path) -> Option<IconPixels> {
+        let icon_path = icons_dir(cache_path).join(self.icon.as_ref()?);
+        read_png(&icon_path)
+            .map_err(|err| crate::log_error(&format!("Cannot read the cached icon {}", icon_path.display()), &err))
+            .ok()
+    }
+}
+
+/// Identifies what the registry lists: a change to any browser entry, including one being added or
+/// removed, gives another fingerprint. It is a 64 bit FNV-1a hash, unlike the std hashers it stays
+/// the same across Rust releases.
+pub fn sources_fingerprint(browsers: &[Browser]) -> String {
+    let fields = browsers.iter().flat_map(|browser| {
+        [&browser.exe_path, &browser.name, &browser.icon]
+            .into_iter()
+            .chain(browser.arguments.iter())
+            .map(String::as_str)
+            // ends each browser, so that an argument can't pass for the next browser path
+            .chain(std::iter::once("\n"))
+    });
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for field in fields {
+        // the length keeps `ab`, `c` apart from `a`, `bc`
+        let length = (field.len() as u64).to_le_bytes();
+        for byte in length.iter().chain(field.as_bytes()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+fn icons_dir(cache_path: &Path) -> PathBuf {
+    cache_path.with_file_name(ICONS_DIR_NAME)
+}
+
+/// Writes the icons of the `browsers` in `icons_dir` and returns their cache entries.
+fn write_icons(icons_dir: &Path, browsers: &[(Browser, Option<IconPixels>)]) -> BSResult<Vec<CachedBrowser>> {
+    browsers
+        .iter()
+        .map(|(browser, icon)| {
+            let icon = match icon {
+                Some(pixels) => {
+                    let file_name = format!("{}.png", browser.get_hash());
+                    write_png(&icons_dir.join(&file_name), pixels)?;
+                    Some(file_name)
+                }
+                None => None,
+            };
+
+            Ok(CachedBrowser {
+                browser: browser.clone(),
+                exe: ExeStamp::read(&browser.exe_path),
+                icon,
+            })
+        })
+        .collect()
+}
+
+/// Moves the directory `from` to `to`, replacing what was there.
+fn replace_dir(from: &Path, to: &Path) -> BSResult<()> {
+    let old = to.with_file_name(format!("{}.old-{}", ICONS_DIR_NAME, std::process::id()));
+    if to.is_dir() {
+        std::fs::rename(to, &old).map_err(|err| BSError::io(to, err))?;
+    }
+    std::fs::rename(from, to).map_err(|err| BSError::io(to, err))?;
+    if old.is_dir() {
+        std::fs::remove_dir_all(&old).map_err(|err| BSError::io(&old, err))?;
+    }
+
+    Ok(())
+}
+
+fn write_png(path: &Path, pixels: &IconPixels) -> BSResult<()> {
+    let file = std::fs::File::create(path).map_err(|err| BSError::io(path, err))?;
+    let mut encoder = png::Encoder::new(
+        std::io::BufWriter::new(file),
+        pixels.width as u32,
+        pixels.height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer
+        .write_image_data(&swap_red_blue(&pixels.bgra))
+        .map_err(png_error)
+}
+
+fn read_png(path: &Path) -> BSResult<IconPixels> {
+    let file = std::fs::File::open(path).map_err(|err| BSError::io(path, err))?;
+    let mut reader = png::Decoder::new(std::io::BufReader::new(file))
+        .read_info()
+        .map_err(png_error)?;
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut rgba).map_err(png_error)?;
+    if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+        return Err(BSError::new("Unexpected pixel format, the cached icons are 8 bit RGBA."));
+    }
+    rgba.truncate(info.buffer_size());
+
+    Ok(IconPixels {
+        width: info.width as i32,
+        height: info.height as i32,
+        bgra: swap_red_blue(&rgba),
+    })
+}
+
+/// BGRA to RGBA and back.
+fn swap_red_blue(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+        .collect()
+}
+
+fn png_error(err: impl std::fmt::Display) -> BSError {
+    BSError::new(&format!("Cannot convert the icon: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sources_fingerprint, BrowserCache};
+    use crate::os::sys_browsers::Browser;
+
+    fn browser(exe_path: &str, arguments: &[&str]) -> Browser {
+        Browser {
+            exe_path: exe_path.to_string(),
+            arguments: arguments.iter().map(|arg| arg.to_string()).collect(),
+            name: "Browser".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        assert_eq!(sources_fingerprint(&[]), "cbf29ce484222325");
+        let browsers = [browser("/usr/bin/firefox", &["-P", "work"])];
+        assert_eq!(sources_fingerprint(&browsers), sources_fingerprint(&browsers.clone()));
+    }
+
+    #[test]
+    fn fingerprint_tells_fields_apart() {
+        let one = sources_fingerprint(&[browser("/usr/bin/ab", &["c"])]);
+        assert_ne!(one, sources_fingerprint(&[browser("/usr/bin/a", &["bc"])]));
+        assert_ne!(one, sources_fingerprint(&[browser("/usr/bin/ab", &[])]));
+        assert_ne!(
+            sources_fingerprint(&[browser("/usr/bin/a", &["/usr/bin/b"])]),
+            sources_fingerprint(&[browser("/usr/bin/a", &[]), browser("/usr/bin/b", &[])])
+        );
+    }
+
+    #[test]
+    fn browsers_with_a_missing_executable_keep_the_cache_valid() {
+        let dir = std::env::temp_dir().join(format!("browsor-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("browsers.cache.json");
+        let exe_path = std::env::current_exe().unwrap().to_string_lossy().to_string();
+        let sources = [browser(&exe_path, &[]), browser("/missing/browser", &[])];
+        let browsers: Vec<_> = sources.iter().map(|browser| (browser.clone(), None)).collect();
+
+        BrowserCache::write(&path, &sources, &browsers).unwrap();
+        // writing again replaces the icons dir that is there
+        BrowserCache::write(&path, &sources, &browsers).unwrap();
+        let cache = BrowserCache::read(&path).unwrap().unwrap();
+        let entries: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(cache.is_valid_for(&sources));
+        assert!(cache.lookup(&path, "/missing/browser").is_some());
+        assert!(!cache.is_valid_for(&sources[..1]));
+        assert_eq!(entries.len(), 2, "{:?}", entries);
+    }
+}
@@ -8,7 +8,7 @@ CREATE TABLE migrations (
 );
 "#;
 
-const MIGRATIONS: &'static [&str] = &[
+const MIGRATIONS: &[&str] = &[
   r#"  
     CREATE TABLE selections (
       `id` INTEGER PRIMARY KEY,
@@ -34,6 +34,15 @@ const MIGRATIONS: &'static [&str] = &[
     CREATE INDEX sel_browser_hash ON selections (path);
     CREATE INDEX sel_history_tld ON selections_history (tld);
     CREATE INDEX sel_date ON selections_history (date);
+  "#,
+  // a choice re-opened in another browser from the history, the predictor leaves it out
+  r#"
+    ALTER TABLE selections_history ADD COLUMN `corrected_by` INTEGER REFERENCES selections_history(id);
+  "#,
+  // the indexes after the first one of the third migration, it only ran its first statement
+  r#"
+    CREATE INDEX IF NOT EXISTS sel_history_tld ON selections_history (tld);
+    CREATE INDEX IF NOT EXISTS sel_date ON selections_history (date);
   "#, // Think about downgrade paths when adding migrations
 ];
 
@@ -48,18 +57,18 @@ pub fn migrate(conn: &mut rusqlite::Connection) -> BSResult<()> {
   let last_migration_index: isize = conn
     .prepare("SELECT idx FROM migrations ORDER BY idx DESC LIMIT 1")?
     .query_row([], |row| row.get::<_, i64>(0))
-    .unwrap_or_else(|_| -1)
+    .unwrap_or(-1)
     .try_into()
     .expect("Failed reading last migration from DB");
   
-  let mut migration_index = if last_migration_index < 0 { 0 } else { last_migration_index };
   let already_migrated = last_migration_index + 1;
+  let mut migration_index = already_migrated;
   MIGRATIONS
-    .into_iter()
+    .iter()
     .skip(already_migrated as usize)
     .try_for_each(|migration| -> BSResult<()> {
       let tx = conn.transaction()?;
-      tx.execute(migration, [])?;
+      tx.execute_batch(migration)?;
       tx.execute("INSERT INTO migrations (idx) VALUES (?)", [migration_index])?;
       tx.commit()?;
       
@@ -68,4 +77,38 @@ pub fn migrate(conn: &mut rusqlite::Connection) -> BSResult<()> {
     })?;
 
   Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+  use super::{migrate, MIGRATIONS};
+
+  fn index_names(conn: &rusqlite::Connection) -> Vec<String> {
+    let mut statement = conn
+      .prepare("SELECT name FROM sqlite_schema WHERE type = 'index' AND name LIKE 'sel_%' ORDER BY name")
+      .unwrap();
+    let names = statement.query_map([], |row| row.get(0)).unwrap();
+    names.map(Result::unwrap).collect()
+  }
+
+  #[test]
+  fn every_statement_of_a_migration_runs() {
+    let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    migrate(&mut conn).unwrap();
+
+    assert_eq!(index_names(&conn), ["sel_browser_hash", "sel_date", "sel_history_tld"]);
+    let last_index: usize = conn
+      .query_row("SELECT MAX(idx) FROM migrations", [], |row| row.get(0))
+      .unwrap();
+    assert_eq!(last_index, MIGRATIONS.len() - 1);
+  }
+
+  #[test]
+  fn migrating_again_changes_nothing() {
+    let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    migrate(&mut conn).unwrap();
+    migrate(&mut conn).unwrap();
+
+    let count: usize = conn.query_row("SELECT COUNT(*) FROM migrations", [], |row| row.get(0)).unwrap();
+    assert_eq!(count, MIGRATIONS.len());
+  }
+}
@@ -1,15 +1,42 @@
+pub mod browser_cache;
 pub mod migrations;
 pub mod statistics_repository;
 pub mod thread_worker;
+pub mod window_geometry;
 
-pub use self::statistics_repository::{SelectionEntity, StatisticsRepository};
-use self::thread_worker::ThreadWorker;
-use crate::error::BSResult;
+pub use self::statistics_repository::{HistoryEntity, SelectionEntity, StatisticsRepository};
+pub use self::thread_worker::CancellationToken;
+use self::thread_worker::{TaskInterrupted, ThreadWorker};
+use crate::error::{BSError, BSResult};
+use rusqlite::Connection;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// The worker thread keeps one connection open for its lifetime, every task receives it.
+/// Opening can fail, in which case each task reports the failure through its result callback.
+type WorkerState = BSResult<Connection>;
+
+const DB_FILE_NAME: &str = "statistics.sqlite";
+/// How many of the last choices the history lists
+pub const HISTORY_LENGTH: usize = 50;
+
+/// Where the statistics database is kept, the user data directory or next to the executable
+/// when the user data directory is not available.
+pub fn default_db_path() -> PathBuf {
+    match crate::os::util::get_user_data_dir() {
+        Ok(data_dir) => data_dir.join(DB_FILE_NAME),
+        Err(err) => {
+            crate::log_error("Cannot use the user data directory for statistics", &err);
+            let mut exe_dir_path = std::env::current_exe().unwrap_or_default();
+            exe_dir_path.set_file_name(DB_FILE_NAME);
+            exe_dir_path
+        }
+    }
+}
 
 pub struct Statistics {
     repo: StatisticsRepository,
-    worker: Option<ThreadWorker>,
+    worker: Option<ThreadWorker<WorkerState>>,
 }
 
 impl Statistics {
@@ -25,12 +52,8 @@ impl Statistics {
     }
 
     pub fn migrate_async(&mut self, result_cb: impl Fn(Box<BSResult<()>>) + 'static) {
-        let db_path = self.repo.get_db_path();
-        self.get_worker().run_async(
-            move || {
-                let mut conn = rusqlite::Connection::open(db_path).expect(format!("Unable to open DB.").as_str());
-                migrations::migrate(&mut conn)
-            },
+        self.get_worker().run_async_with_state(
+            move |state| migrations::migrate(Self::connection(state)?),
             Self::unwrap_result_callback(result_cb),
         );
     }
@@ -41,8 +64,8 @@ impl Statistics {
         result_cb: impl Fn(Box<BSResult<()>>) + 'static,
     ) {
         let mut repo_clone = self.repo.clone();
-        self.get_worker().run_async(
-            move || repo_clone.update_selections(list),
+        self.get_worker().run_async_with_state(
+            move |state| repo_clone.update_selections(Self::connection(state)?, list),
             Self::unwrap_result_callback(result_cb),
         );
     }
@@ -59,29 +82,68 @@ impl Statistics {
         let url_s = String::from(url);
         let browser_path_hash_str = String::from(browser_path_hash);
         let browser_path_str = String::from(browser_path);
-        self.get_worker().run_async(
-            move || {
-                repo_clone.save_choice(source, &url_s, &browser_path_hash_str, &browser_path_str)
+        self.get_worker().run_async_with_state(
+            move |state| {
+                repo_clone.save_choice(
+                    Self::connection(state)?,
+                    source,
+                    &url_s,
+                    &browser_path_hash_str,
+                    &browser_path_str,
+                )
             },
             Self::unwrap_result_callback(result_cb),
         );
     }
 
-    
+    /// The last `limit` choices, latest first, see [StatisticsRepository::history].
+    pub fn history(&mut self, limit: usize, result_cb: impl Fn(Box<BSResult<Vec<HistoryEntity>>>) + 'static) {
+        let mut repo_clone = self.repo.clone();
+        self.get_worker().run_async_with_state(
+            move |state| repo_clone.history(Self::connection(state)?, limit),
+            Self::unwrap_result_callback(result_cb),
+        );
+    }
+
+    /// Records the choice `history_id` as made for another browser, see [StatisticsRepository::correct_choice].
+    pub fn correct_choice(
+        &mut self,
+        history_id: usize,
+        browser_path_hash: &str,
+        result_cb: impl Fn(Box<BSResult<()>>) + 'static,
+    ) {
+        let mut repo_clone = self.repo.clone();
+        let browser_path_hash_str = String::from(browser_path_hash);
+        self.get_worker().run_async_with_state(
+            move |state| repo_clone.correct_choice(Self::connection(state)?, history_id, &browser_path_hash_str),
+            Self::unwrap_result_callback(result_cb),
+        );
+    }
+
+    /// Queues the prediction and returns the token that can cancel it. When `deadline` passes
+    /// before the prediction is done, `result_cb` receives an error instead.
     pub fn predict(
         &mut self,
         source: Option<PathBuf>,
         url: &str,
+        deadline: Option<Instant>,
         result_cb: impl Fn(Box<BSResult<Vec<SelectionEntity>>>) + 'static,
-    ) {
+    ) -> CancellationToken {
         let mut repo_clone = self.repo.clone();
         let url_s = String::from(url);
-        self.get_worker().run_async(
-            move || {
-                repo_clone.predict(source, &url_s)
+        let token = match deadline {
+            Some(deadline) => CancellationToken::with_deadline(deadline),
+            None => CancellationToken::new(),
+        };
+        self.get_worker().run_async_with_token(
+            move |state, token| {
+                repo_clone.predict(Self::connection(state)?, source, &url_s, token)
             },
             Self::unwrap_result_callback(result_cb),
+            token.clone(),
         );
+
+        token
     }
 
     pub fn tick(&mut self) -> bool {
@@ -89,31 +151,38 @@ impl Statistics {
     }
 
     fn unwrap_result_callback<T: 'static>(
-        result_cb: impl Fn(Box<T>) + 'static,
+        result_cb: impl Fn(Box<BSResult<T>>) + 'static,
     ) -> impl Fn(Box<dyn std::any::Any + Send>) + 'static {
         move |incoming: Box<dyn std::any::Any + Send>| {
-            if let Ok(res) = incoming.downcast::<T>() {
-                result_cb(res);
-            } else {
-                panic!("Type mismatch for result from worker.");
+            match incoming.downcast::<BSResult<T>>() {
+                Ok(res) => result_cb(res),
+                Err(incoming) => match incoming.downcast::<TaskInterrupted>() {
+                    Ok(interrupted) => result_cb(Box::new(Err(BSError::from(*interrupted)))),
+                    Err(_) => panic!("Type mismatch for result from worker."),
+                },
             }
         }
     }
 
-    fn get_worker(&mut self) -> &mut ThreadWorker {
+    fn connection(state: &mut WorkerState) -> BSResult<&mut Connection> {
+        state.as_mut().map_err(|err| {
+            BSError::new(&format!("Statistics database is not available. {}", err))
+        })
+    }
+
+    /// The worker is created on first use so [`set_db_path`](Statistics::set_db_path) must be called
+    /// before queueing any task.
+    fn get_worker(&mut self) -> &mut ThreadWorker<WorkerState> {
         if self.worker.is_none() {
-            self.worker = Some(ThreadWorker::new(|| {}));
+            let repo = self.repo.clone();
+            self.worker = Some(ThreadWorker::new(move || repo.open_conn()));
         }
 
         self.worker.as_mut().unwrap()
     }
 
     pub fn is_finished(&self) -> bool {
-        if self.worker.is_some() {
-            self.worker.as_ref().unwrap().is_finished()
-        } else {
-            true
-        }
+        self.worker.as_ref().is_none_or(|worker| worker.is_finished())
     }
 
     pub fn stop(&mut self) {
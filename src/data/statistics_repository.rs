@@ -1,7 +1,8 @@
+use super::thread_worker::CancellationToken;
 use crate::error::{BSError, BSResult};
 use chrono::prelude::*;
 use chrono::Datelike;
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_rusqlite::{columns_from_statement, from_row_with_columns};
 use std::collections::BTreeMap;
@@ -10,6 +11,14 @@ use url::Url;
 
 static TLD_SUFFIXES: &str = include_str!("../../assets/data/tld-suffixes.dat");
 
+/// How long a write waits on a lock held by another Browsor instance before giving up with `SQLITE_BUSY`
+const BUSY_TIMEOUT_MS: u64 = 5_000;
+
+/// The choices with the browser they were made for, as [HistoryEntity], to complete with a `WHERE`.
+const HISTORY_QUERY: &str = r#"SELECT h.id AS id, h.url AS url, h.src AS src, s.path AS path, s.path_hash AS path_hash, h.date AS date
+    FROM selections_history h
+    INNER JOIN selections s ON s.id = h.selection_id"#;
+
 #[derive(Deserialize, Serialize)]
 pub struct StatisticsEntity {
     pub id: Option<usize>,
@@ -30,6 +39,45 @@ pub struct SelectionEntity {
     pub path: Option<String>,
 }
 
+/// How many times a browser was chosen, across all the recorded history.
+#[derive(Deserialize, Serialize)]
+pub struct ChoiceCountEntity {
+    pub path: Option<String>,
+    pub count: Option<usize>,
+    pub last_date: Option<String>,
+}
+
+/// A choice as the history lists it, with the browser or action it was made for.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HistoryEntity {
+    pub id: Option<usize>,
+    pub url: Option<String>,
+    /// The app the URL was opened from
+    pub src: Option<String>,
+    pub path: Option<String>,
+    pub path_hash: Option<String>,
+    /// In UTC, as SQLite writes `CURRENT_TIMESTAMP`
+    pub date: Option<String>,
+}
+
+impl HistoryEntity {
+    /// When the choice was made in local time, ie. `2024-03-01 14:05`.
+    pub fn local_date(&self) -> Option<String> {
+        let utc = NaiveDateTime::parse_from_str(self.date.as_deref()?, "%Y-%m-%d %H:%M:%S").ok()?;
+        let local = Utc.from_utc_datetime(&utc).with_timezone(&Local);
+
+        Some(local.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    /// The name of the app the URL was opened from, its executable without the extension.
+    pub fn source_name(&self) -> Option<String> {
+        let src = self.src.as_deref().filter(|src| !src.is_empty())?;
+        Path::new(src)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+    }
+}
+
 #[derive(Clone)]
 pub struct StatisticsRepository {
     db_path: Option<PathBuf>,
@@ -44,20 +92,32 @@ impl StatisticsRepository {
         self.db_path = Some(PathBuf::from(db_file));
     }
 
-    pub fn get_db_path(&self) -> PathBuf {
-        if let Some(path) = self.db_path.clone() {
-            return path;
-        }
+    /// Opens the statistics database and configures the connection for being shared between
+    /// concurrently running instances: WAL journaling so readers don't block the writer, a busy
+    /// timeout instead of failing right away on a locked DB and enforced foreign keys.
+    pub fn open_conn(&self) -> BSResult<Connection> {
+        let conn = Connection::open(self.db_path.as_ref().expect("DB Path should be set"))?;
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        conn.busy_timeout(std::time::Duration::from_millis(BUSY_TIMEOUT_MS))?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+
+        Ok(conn)
+    }
+
+    /// Opens the database for reading only, nothing is written to it, not even the journal mode.
+    pub fn open_conn_read_only(&self) -> BSResult<Connection> {
+        let db_path = self.db_path.as_ref().expect("DB Path should be set");
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        conn.busy_timeout(std::time::Duration::from_millis(BUSY_TIMEOUT_MS))?;
 
-        Default::default()
+        Ok(conn)
     }
 
     pub fn select<T: DeserializeOwned + Serialize>(
         &mut self,
         table: &str,
-        con: Option<Connection>,
+        con: &Connection,
     ) -> BSResult<Vec<T>> {
-        let con = self.open_conn(con)?;
         let query = format!("SELECT * FROM {}", table);
         let mut stmt = con.prepare(&query)?;
         let cols = columns_from_statement(&stmt);
@@ -67,11 +127,11 @@ impl StatisticsRepository {
         Ok(list)
     }
 
-    pub fn select_by_params<'a, T: DeserializeOwned + Serialize>(
+    pub fn select_by_params<T: DeserializeOwned + Serialize>(
         &mut self,
         table: &str,
         params_entity: &T,
-        existing_conn: Option<Connection>,
+        conn: &Connection,
     ) -> BSResult<Vec<T>> {
         if let serde_json::Value::Object(record) = serde_json::to_value(params_entity).unwrap() {
             let keys: Vec<_> = record
@@ -85,7 +145,6 @@ impl StatisticsRepository {
                 .collect::<Vec<String>>()
                 .join(",");
             let query = format!(r#"SELECT * FROM {table} WHERE {sql_keys}"#);
-            let conn = self.open_conn(existing_conn)?;
             let mut stmt = conn.prepare(&query)?;
             let cols = columns_from_statement(&stmt);
 
@@ -113,23 +172,26 @@ impl StatisticsRepository {
     pub fn get_selection_from_browser(
         &mut self,
         browser_path_hash: &str,
-        existing_conn: Option<Connection>,
+        conn: &Connection,
     ) -> BSResult<Option<SelectionEntity>> {
         let params = SelectionEntity {
             id: None,
             path_hash: Some(browser_path_hash.into()),
             path: None,
         };
-        let mut result = self.select_by_params("selections", &params, existing_conn)?;
-        if result.len() > 0 {
+        let mut result = self.select_by_params("selections", &params, conn)?;
+        if !result.is_empty() {
             Ok(Some(result.remove(0)))
         } else {
             Ok(None)
         }
     }
 
-    pub fn update_selections(&mut self, mut list: Vec<SelectionEntity>) -> BSResult<()> {
-        let conn = self.open_conn(None)?;
+    pub fn update_selections(
+        &mut self,
+        conn: &Connection,
+        mut list: Vec<SelectionEntity>,
+    ) -> BSResult<()> {
         let query =
             "INSERT INTO selections (path_hash, path) VALUES(?1, ?2) ON CONFLICT(path) DO UPDATE SET path_hash=?1;";
         let mut stmt = conn.prepare(query)?;
@@ -143,13 +205,13 @@ impl StatisticsRepository {
 
     pub fn save_choice(
         &mut self,
+        conn: &Connection,
         source: Option<PathBuf>,
         url: &str,
         browser_path_hash: &str,
         _browser_path: &str,
     ) -> BSResult<()> {
-        let conn = self.open_conn(None)?;
-        let selection_opt = self.get_selection_from_browser(browser_path_hash, None)?;
+        let selection_opt = self.get_selection_from_browser(browser_path_hash, conn)?;
 
         if let Some(selection) = selection_opt {
             let query = r#"INSERT INTO selections_history (url, src, tld, weekday, hour, selection_id)
@@ -176,12 +238,14 @@ impl StatisticsRepository {
 
     pub fn predict(
         &mut self,
+        conn: &Connection,
         source: Option<PathBuf>,
         url: &str,
+        token: &CancellationToken,
     ) -> BSResult<Vec<SelectionEntity>> {
         // TODO: quick and dirty MVP POC, refactor asap
-        let choices: Vec<SelectionEntity> = self.select("selections", None)?;
-        if choices.len() == 0 {
+        let choices: Vec<SelectionEntity> = self.select("selections", conn)?;
+        if choices.is_empty() {
             bail!("Selections table is empty.");
         }
         let src_path = source.unwrap_or_default();
@@ -201,11 +265,10 @@ impl StatisticsRepository {
                     map.insert(item.id.unwrap(), (0, 0.0));
                     map
                 });
-        let con = self.open_conn(None)?;
         let query = r#"SELECT id, url, src, tld, weekday, hour, selection_id, date, count
             FROM selections_history
-            WHERE date > ?"#;
-        let mut stmt = con.prepare(&query)?;
+            WHERE date > ? AND corrected_by IS NULL"#;
+        let mut stmt = conn.prepare(query)?;
         let cols = columns_from_statement(&stmt);
         let rows = stmt.query_and_then(params!(max_age), |row| {
             from_row_with_columns::<StatisticsEntity>(row, &cols)
@@ -214,6 +277,10 @@ impl StatisticsRepository {
         let weights = [0.5, 3.0, 2.0, 2.0, 3.0];
 
         for entry in rows {
+            if token.should_stop() {
+                bail!("Prediction interrupted.");
+            }
+
             if let Ok(stat_entity) = entry {
                 let (mut choice_count, mut choice_score) =
                     choice_map.get(&stat_entity.selection_id.unwrap()).unwrap();
@@ -251,7 +318,7 @@ impl StatisticsRepository {
 
                 if let Some(entity_tld) = stat_entity.tld {
                     let factor_score = if entity_tld == dns_tld { 1.0 * weights[4] } else { 0.0 };
-                    entry_sum = entry_sum + factor_score;
+                    entry_sum += factor_score;
                 }
 
                 
@@ -270,15 +337,13 @@ impl StatisticsRepository {
             |mut map, (choice_id, (choice_count, choice_score))| {
                 let final_score = (choice_score * 10.0) + choice_count as f64;
                 map.insert(final_score as i64 , choice_id);
-
-                println!("Choice: {}, count: {}, score: {}, final score: {}", choice_id, choice_count, choice_score, final_score);
                 map
             },
         );
 
         let mut choices_sorted = choices_scores
-            .iter()
-            .map(|(_, choice_id)| -> SelectionEntity {
+            .values()
+            .map(|choice_id| -> SelectionEntity {
                 let choice = choices
                     .iter()
                     .find(|item| item.id.unwrap() == *choice_id)
@@ -292,12 +357,70 @@ impl StatisticsRepository {
         Ok(choices_sorted)
     }
 
-    fn open_conn(&self, exiting_conn: Option<Connection>) -> Result<Connection, rusqlite::Error> {
-        if exiting_conn.is_some() {
-            return Ok(exiting_conn.unwrap());
-        } else {
-            Connection::open(&self.db_path.as_ref().expect("DB Path should be set"))
+    pub fn choice_counts(&mut self, conn: &Connection) -> BSResult<Vec<ChoiceCountEntity>> {
+        let query = r#"SELECT s.path AS path, SUM(h.count) AS count, MAX(h.date) AS last_date
+            FROM selections s
+            INNER JOIN selections_history h ON h.selection_id = s.id
+            GROUP BY s.id
+            ORDER BY count DESC;"#;
+        let mut stmt = conn.prepare(query)?;
+        let cols = columns_from_statement(&stmt);
+        let res = stmt.query_and_then((), |row| from_row_with_columns::<ChoiceCountEntity>(row, &cols))?;
+
+        let list: Vec<ChoiceCountEntity> = res.filter_map(|item| item.ok()).collect();
+        Ok(list)
+    }
+
+    /// The last `limit` choices, latest first. Choices corrected since are left out, their
+    /// correction is listed instead.
+    pub fn history(&mut self, conn: &Connection, limit: usize) -> BSResult<Vec<HistoryEntity>> {
+        let query = format!(
+            "{} WHERE h.corrected_by IS NULL ORDER BY h.date DESC, h.id DESC LIMIT ?;",
+            HISTORY_QUERY
+        );
+        let mut stmt = conn.prepare(&query)?;
+        let cols = columns_from_statement(&stmt);
+        let res = stmt.query_and_then([limit], |row| from_row_with_columns::<HistoryEntity>(row, &cols))?;
+
+        let list: Vec<HistoryEntity> = res.filter_map(|item| item.ok()).collect();
+        Ok(list)
+    }
+
+    /// The choice `history_id` as the history lists it, `None` when there is none or it was corrected since.
+    pub fn history_entry(&mut self, conn: &Connection, history_id: usize) -> BSResult<Option<HistoryEntity>> {
+        let query = format!("{} WHERE h.id = ? AND h.corrected_by IS NULL;", HISTORY_QUERY);
+        let mut stmt = conn.prepare(&query)?;
+        let cols = columns_from_statement(&stmt);
+        let mut res = stmt.query_and_then([history_id], |row| from_row_with_columns::<HistoryEntity>(row, &cols))?;
+
+        Ok(res.next().and_then(|item| item.ok()))
+    }
+
+    /// Records that the choice `history_id` should have been the browser with `browser_path_hash`,
+    /// as when it is re-opened there from the history. The correction keeps the source and time of
+    /// the original choice so that the prediction learns it for the same circumstances, the
+    /// original choice no longer counts.
+    pub fn correct_choice(&mut self, conn: &mut Connection, history_id: usize, browser_path_hash: &str) -> BSResult<()> {
+        let Some(selection) = self.get_selection_from_browser(browser_path_hash, conn)? else {
+            bail!("Couldn't find selection by path_hash {} to correct the choice.", browser_path_hash);
+        };
+
+        let tx = conn.transaction()?;
+        let inserted = tx.execute(
+            r#"INSERT INTO selections_history (url, src, tld, weekday, hour, date, selection_id)
+            SELECT url, src, tld, weekday, hour, date, ? FROM selections_history WHERE id = ?;"#,
+            (selection.id.unwrap(), history_id),
+        )?;
+        if inserted == 0 {
+            bail!("Couldn't find the choice {} to correct.", history_id);
         }
+        tx.execute(
+            "UPDATE selections_history SET corrected_by = ? WHERE id = ?;",
+            (tx.last_insert_rowid(), history_id),
+        )?;
+        tx.commit()?;
+
+        Ok(())
     }
 
     fn find_tld_from_url(url: &str) -> BSResult<String> {
@@ -305,26 +428,22 @@ impl StatisticsRepository {
         // TODO: Potentially expensive computationally, optimize
         use url::Host::Domain;
 
-        let url_res = Url::parse(url); // TODO: Don't panic on invalid URLs
-        if let Err(err) = url_res {
-            return Err(BSError::from(err.to_string().as_str()))
-        }
-
-        let url = url_res.unwrap();
-        let hostname: &str = if let Some(Domain(host)) = url.host() {
+        let parsed_url = Url::parse(url).map_err(|err| BSError::url(url, err))?;
+        let hostname: &str = if let Some(Domain(host)) = parsed_url.host() {
             host
         } else {
             // TODO Handle no host separately
-            return Ok(String::from(url.host_str().unwrap()));
+            return parsed_url.host_str().map(String::from).ok_or(BSError::Url {
+                url: String::from(url),
+                source: None,
+            });
         };
         let hostname_parts: Vec<&str> = hostname.split('.').collect();
         let mut part_idx = hostname_parts.len() - 1;
         let mut needle = String::from(hostname_parts[part_idx]);
         while let Some(needle_index) = TLD_SUFFIXES.find(&needle) {
-            if needle_index > 0 {
-                if TLD_SUFFIXES.chars().nth(needle_index - 1).unwrap() != '\n' {
-                    break;
-                }
+            if needle_index > 0 && TLD_SUFFIXES.chars().nth(needle_index - 1).unwrap() != '\n' {
+                break;
             }
             part_idx -= 1;
             needle = format!("{}.{}", hostname_parts[part_idx], needle);
@@ -333,8 +452,94 @@ impl StatisticsRepository {
         let tld_parts: Vec<&str> = hostname_parts
             .iter()
             .skip(part_idx)
-            .map(|item| *item)
+            .copied()
             .collect();
         Ok(tld_parts.join("."))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/page";
+
+    /// An in-memory DB with the browsers `a` and `b` and a choice of `a` for [URL], as the history
+    /// entry returned with its id. The choice is dated back so that a correction can't pass for it.
+    fn with_a_choice() -> (StatisticsRepository, Connection, usize) {
+        let mut repository = StatisticsRepository::new();
+        let mut conn = Connection::open_in_memory().unwrap();
+        super::super::migrations::migrate(&mut conn).unwrap();
+        let selections = ["a", "b"]
+            .iter()
+            .map(|name| SelectionEntity {
+                id: None,
+                path_hash: Some(format!("hash-{}", name)),
+                path: Some(format!("/usr/bin/{}", name)),
+            })
+            .collect();
+        repository.update_selections(&conn, selections).unwrap();
+
+        repository
+            .save_choice(&conn, Some(PathBuf::from("/usr/bin/mail")), URL, "hash-a", "/usr/bin/a")
+            .unwrap();
+        let history_id = conn.last_insert_rowid() as usize;
+        conn.execute(
+            "UPDATE selections_history SET date = '2024-03-01 12:00:00', hour = 3 WHERE id = ?;",
+            [history_id],
+        )
+        .unwrap();
+
+        (repository, conn, history_id)
+    }
+
+    #[test]
+    fn a_correction_replaces_the_choice_in_the_history() {
+        let (mut repository, mut conn, history_id) = with_a_choice();
+        repository.correct_choice(&mut conn, history_id, "hash-b").unwrap();
+
+        let history = repository.history(&conn, 10).unwrap();
+        assert_eq!(history.len(), 1);
+        let correction = &history[0];
+        assert_ne!(correction.id, Some(history_id));
+        assert_eq!(correction.url.as_deref(), Some(URL));
+        assert_eq!(correction.path_hash.as_deref(), Some("hash-b"));
+        assert_eq!(correction.src.as_deref(), Some("/usr/bin/mail"));
+        assert_eq!(correction.date.as_deref(), Some("2024-03-01 12:00:00"));
+        let hour: u16 = conn
+            .query_row("SELECT hour FROM selections_history WHERE id = ?;", [correction.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(hour, 3);
+
+        assert!(repository.history_entry(&conn, history_id).unwrap().is_none());
+        let entry = repository.history_entry(&conn, correction.id.unwrap()).unwrap();
+        assert_eq!(entry.and_then(|entry| entry.path_hash).as_deref(), Some("hash-b"));
+    }
+
+    #[test]
+    fn predict_leaves_the_corrected_choice_out() {
+        let (mut repository, mut conn, history_id) = with_a_choice();
+        repository.correct_choice(&mut conn, history_id, "hash-b").unwrap();
+
+        let token = CancellationToken::new();
+        let predicted: Vec<String> = repository
+            .predict(&conn, Some(PathBuf::from("/usr/bin/mail")), URL, &token)
+            .unwrap()
+            .into_iter()
+            .filter_map(|selection| selection.path_hash)
+            .collect();
+        assert_eq!(predicted, ["hash-b", "hash-a"]);
+    }
+
+    #[test]
+    fn correcting_a_missing_choice_fails_without_inserting() {
+        let (mut repository, mut conn, history_id) = with_a_choice();
+
+        assert!(repository.correct_choice(&mut conn, history_id + 1, "hash-b").is_err());
+        let count: usize = conn
+            .query_row("SELECT COUNT(*) FROM selections_history;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        assert!(repository.history_entry(&conn, history_id).unwrap().is_some());
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
-use std::{thread::JoinHandle, sync::atomic::AtomicBool};
+use std::{thread::JoinHandle, sync::atomic::{AtomicBool, Ordering}};
+use std::time::{Duration, Instant};
 use ::std::{
     sync::{mpsc, Arc},
 };
@@ -70,33 +71,99 @@ use ::std::{
 /// what type we receive in the success closure so we have to manually know and `downcast_ref` the value
 /// to extract the worker operation result.
 /// 
-pub struct ThreadWorker {
-    task_sender: mpsc::Sender<Box<dyn FnOnce() + Send>>,
+pub struct ThreadWorker<State: 'static = ()> {
+    task_sender: mpsc::Sender<Task<State>>,
     pub join_handle: JoinHandle<()>,
-    tasks_results: Vec<(ResultCallback, std::sync::mpsc::Receiver<AnythingBoxed>)>,
+    tasks_results: Vec<(ResultCallback, std::sync::mpsc::Receiver<AnythingBoxed>, CancellationToken)>,
     stop: Arc<AtomicBool>,
     exited: Arc<AtomicBool>,
 }
 
 pub type AnythingBoxed = Box<dyn std::any::Any + Send>;
 pub type ResultCallback = Box<dyn FnOnce(AnythingBoxed)>;
+type Task<State> = Box<dyn FnOnce(&mut State) + Send>;
 
-impl ThreadWorker {
-    pub fn new<LoopState>(thread_prelude: impl 'static + Send + FnOnce() -> LoopState) -> Self {
-        let (task_sender, to_be_received_tasks) = mpsc::channel();
-        let _: mpsc::Sender<Box<dyn FnOnce() + Send>> = task_sender;
+/// Handle shared between the calling thread and a task sent with
+/// [`run_async_with_token`](ThreadWorker::run_async_with_token). The calling thread can
+/// [`cancel`](CancellationToken::cancel) the task while the task itself polls
+/// [`should_stop`](CancellationToken::should_stop) to bail out early from long running work.
+///
+/// A token can optionally carry a deadline after which the task is considered timed out.
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Default::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// True when the task should stop working, either because it was cancelled or because its
+    /// deadline has passed.
+    pub fn should_stop(&self) -> bool {
+        self.is_cancelled() || self.is_expired()
+    }
+}
+
+/// Value passed to the result callback instead of the task result when the task did not
+/// complete in time. Cancelled tasks never have their result callback called.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskInterrupted {
+    TimedOut,
+}
+
+impl std::fmt::Display for TaskInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskInterrupted::TimedOut => f.write_str("Background task timed out."),
+        }
+    }
+}
+
+impl<State: 'static> ThreadWorker<State> {
+    /// Spawns the worker thread. The value returned by `thread_prelude` is created on and never leaves
+    /// the worker thread, tasks sent with [`run_async_with_state`](ThreadWorker::run_async_with_state)
+    /// get a mutable reference to it. Useful for resources that should live as long as the worker such
+    /// as a database connection.
+    pub fn new(thread_prelude: impl 'static + Send + FnOnce() -> State) -> Self {
+        let (task_sender, to_be_received_tasks) = mpsc::channel::<Task<State>>();
         let stop_flag = Arc::new(AtomicBool::new(false));
         let exited_flag = Arc::new(AtomicBool::new(false));
         let stop_flag_clone = stop_flag.clone();
         let exited_flag_clone = exited_flag.clone();
         let join_handle = ::std::thread::spawn(move || {
-            let _state = thread_prelude();
+            let mut state = thread_prelude();
             loop { // TODO: Condvar
                 let task_opt = to_be_received_tasks.try_recv();
                 if let Ok(task) = task_opt {
-                    task();
+                    task(&mut state);
                 } else {
-                    if stop_flag_clone.load(std::sync::atomic::Ordering::Relaxed) != true {
+                    if !stop_flag_clone.load(Ordering::Relaxed) {
                         std::thread::sleep(std::time::Duration::from_millis(5));
                     } else {
                         break;
@@ -104,7 +171,7 @@ impl ThreadWorker {
                     
                 }
             }
-            exited_flag_clone.store(true, std::sync::atomic::Ordering::Relaxed);
+            exited_flag_clone.store(true, Ordering::Relaxed);
         });
         Self {
             task_sender,
@@ -116,12 +183,12 @@ impl ThreadWorker {
     }
 
     /// Sends the given closure to the background thread and returns the [std::sync::mpsc::Receiver]
-    pub fn run<R>(self: &'_ ThreadWorker, f: impl 'static + Send + FnOnce() -> R) -> std::sync::mpsc::Receiver<R>
+    pub fn run<R>(self: &'_ ThreadWorker<State>, f: impl 'static + Send + FnOnce() -> R) -> std::sync::mpsc::Receiver<R>
     where
         R: 'static + Send,
     {
         let (sender, receiver) = mpsc::channel();
-        let _ = self.task_sender.send(Box::new(move || {
+        let _ = self.task_sender.send(Box::new(move |_| {
             let _ = sender.send(f());
         }));
         receiver
@@ -129,17 +196,79 @@ impl ThreadWorker {
 
     /// Sends the `task` closure to the thread and saves the `result_cb` to be called when the `task` is complete.
     /// The `result_cb` is invoked on the calling thread **only if tick() is called** in a polling loop. See module examples.
-    pub fn run_async<R: std::any::Any>(&mut self, task: impl 'static + Send + FnOnce() -> R, result_cb: impl 'static + Fn(AnythingBoxed))
-    where
-        R: 'static + Send
-    {
+    pub fn run_async<R: std::any::Any + 'static + Send>(
+        &mut self,
+        task: impl 'static + Send + FnOnce() -> R,
+        result_cb: impl 'static + Fn(AnythingBoxed),
+    ) {
+        self.run_async_with_token(move |_, _| task(), result_cb, CancellationToken::new());
+    }
+
+    /// Same as [`run_async`](ThreadWorker::run_async) but the `task` also receives the state
+    /// created by the thread prelude given to [`new`](ThreadWorker::new).
+    /// ```ignore
+    /// let mut bg_worker = ThreadWorker::new(|| open_db_connection());
+    /// bg_worker.run_async_with_state(|conn| conn.execute("DELETE FROM cache", []), |_| {});
+    /// ```
+    pub fn run_async_with_state<R: std::any::Any + 'static + Send>(
+        &mut self,
+        task: impl 'static + Send + FnOnce(&mut State) -> R,
+        result_cb: impl 'static + Fn(AnythingBoxed),
+    ) {
+        self.run_async_with_token(move |state, _| task(state), result_cb, CancellationToken::new());
+    }
+
+    /// Same as [`run_async_with_state`](ThreadWorker::run_async_with_state) but the `task` can be cancelled
+    /// or time limited through the given `token`. The `task` receives the token so it can check
+    /// [`should_stop`](CancellationToken::should_stop) while it works.
+    ///
+    /// - When the token is cancelled the task is skipped if it hasn't started yet and `result_cb` is never called.
+    /// - When the token deadline passes `result_cb` receives a [TaskInterrupted::TimedOut] instead of the task result.
+    /// ```ignore
+    /// let token = CancellationToken::with_timeout(Duration::from_secs(2));
+    /// bg_worker.run_async_with_token(
+    ///     |_state, token| { while !token.should_stop() { crunch_numbers(); } },
+    ///     |incoming| {
+    ///         if let Some(TaskInterrupted::TimedOut) = incoming.downcast_ref::<TaskInterrupted>() {
+    ///             println!("Too slow!");
+    ///         }
+    ///     },
+    ///     token.clone(),
+    /// );
+    /// token.cancel(); // changed our mind
+    /// ```
+    pub fn run_async_with_token<R: std::any::Any + 'static + Send>(
+        &mut self,
+        task: impl 'static + Send + FnOnce(&mut State, &CancellationToken) -> R,
+        result_cb: impl 'static + Fn(AnythingBoxed),
+        token: CancellationToken,
+    ) {
         let (sender, receiver) = mpsc::channel::<AnythingBoxed>();
-        let _ = self.task_sender.send(Box::new(move || {
-            let result: AnythingBoxed = Box::new(task());
+        let task_token = token.clone();
+        let _ = self.task_sender.send(Box::new(move |state: &mut State| {
+            if task_token.is_cancelled() {
+                return; // dropping the sender lets tick() know nothing will come
+            }
+
+            if task_token.is_expired() {
+                let _ = sender.send(Box::new(TaskInterrupted::TimedOut));
+                return;
+            }
+
+            let result = task(state, &task_token);
+            if task_token.is_cancelled() {
+                return;
+            }
+
+            let result: AnythingBoxed = if task_token.is_expired() {
+                Box::new(TaskInterrupted::TimedOut)
+            } else {
+                Box::new(result)
+            };
             let _ = sender.send(result);
         }));
         let rcb: ResultCallback = Box::new(result_cb);
-        self.tasks_results.push((rcb, receiver));
+        self.tasks_results.push((rcb, receiver, token));
     }
 
     /// Looks at the pending background tasks and calls the result closures for the completed ones. Returns true
@@ -151,27 +280,43 @@ impl ThreadWorker {
     ///     std::thread::sleep(Duration::from_millis(1));
     /// }
     /// ```
+    ///
+    /// Cancelled tasks are dropped without calling their result closure, tasks past their deadline
+    /// have their result closure called with [TaskInterrupted::TimedOut] without waiting for the task to return.
     pub fn tick(&mut self) -> bool {
-        for i in 0..self.tasks_results.len() {
-            if let Some(task) = self.tasks_results.get(i) {
-                let (_, receiver) = task;
-                if let Ok(res) = receiver.try_recv() {
-                    let (cb, _) = self.tasks_results.remove(i);
-                    cb(res);
-                }
+        let mut i = 0;
+        while i < self.tasks_results.len() {
+            let (_, receiver, token) = &self.tasks_results[i];
+            let outcome: Option<AnythingBoxed> = if token.is_cancelled() {
+                None
             } else {
-                // panic!("How do you loop through an array but get None?");
+                match receiver.try_recv() {
+                    Ok(res) => Some(res),
+                    Err(mpsc::TryRecvError::Empty) if token.is_expired() => {
+                        Some(Box::new(TaskInterrupted::TimedOut))
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {
+                        i += 1;
+                        continue;
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => None,
+                }
+            };
+
+            let (cb, _, _) = self.tasks_results.remove(i);
+            if let Some(res) = outcome {
+                cb(res);
             }
         }
 
-        self.tasks_results.len() != 0
+        !self.tasks_results.is_empty()
     }
 
     pub fn is_finished(&self) -> bool {
-        return self.exited.load(std::sync::atomic::Ordering::Relaxed);
+        self.exited.load(Ordering::Relaxed)
     }
 
     pub fn stop(&mut self) {
-        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+        self.stop.store(true, Ordering::Relaxed);
     }
 }
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{BSError, BSResult};
+
+const GEOMETRY_FILE_NAME: &str = "window.json";
+
+/// A rectangle on the desktop in physical pixels, ie. where the window is with its frame.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width as i32 && y < self.y + self.height as i32
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + (self.width / 2) as i32, self.y + (self.height / 2) as i32)
+    }
+
+    /// The part of `self` also in `other`, `None` when they don't overlap.
+    pub fn intersection(&self, other: &WindowGeometry) -> Option<WindowGeometry> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x + self.width as i32).min(other.x + other.width as i32);
+        let bottom = (self.y + self.height as i32).min(other.y + other.height as i32);
+
+        (left < right && top < bottom).then(|| WindowGeometry {
+            x: left,
+            y: top,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+
+    /// Moved, and shrunk if it has to, so that it fits in `area`.
+    pub fn clamp_to(&self, area: &WindowGeometry) -> WindowGeometry {
+        let width = self.width.min(area.width);
+        let height = self.height.min(area.height);
+
+        WindowGeometry {
+            x: self.x.clamp(area.x, area.x + (area.width - width) as i32),
+            y: self.y.clamp(area.y, area.y + (area.height - height) as i32),
+            width,
+            height,
+        }
+    }
+}
+
+/// Where the window was last closed for each monitor setup, for the `last` window placement. A
+/// setup is the monitors as they are arranged, see [crate::ui::placement].
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SavedGeometries {
+    /// The setup the window was last closed on
+    #[serde(default)]
+    last_setup: Option<String>,
+    #[serde(default)]
+    setups: BTreeMap<String, WindowGeometry>,
+}
+
+impl SavedGeometries {
+    pub fn default_path() -> BSResult<PathBuf> {
+        Ok(crate::os::util::get_user_data_dir()?.join(GEOMETRY_FILE_NAME))
+    }
+
+    /// Reads the saved geometries, there are none when the window was never closed before.
+    pub fn read(path: &Path) -> BSResult<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(SavedGeometries::default()),
+            Err(err) => return Err(BSError::io(path, err)),
+        };
+
+        serde_json::from_str(&text)
+            .map_err(|err| BSError::new(&format!("Invalid window geometry {}: {}", path.display(), err)))
+    }
+
+    pub fn write(&self, path: &Path) -> BSResult<()> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|err| BSError::new(&format!("Cannot write the window geometry: {}", err)))?;
+        std::fs::write(path, text).map_err(|err| BSError::io(path, err))
+    }
+
+    /// The geometry saved for the monitor `setup`. When the monitors changed since, the one saved
+    /// last on another setup, to be clamped to the monitors there are now.
+    pub fn get(&self, setup: &str) -> Option<WindowGeometry> {
+        self.setups
+            .get(setup)
+            .or_else(|| self.setups.get(self.last_setup.as_ref()?))
+            .copied()
+    }
+
+    pub fn set(&mut self, setup: &str, geometry: WindowGeometry) {
+        self.setups.insert(String::from(setup), geometry);
+        self.last_setup = Some(String::from(setup));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SavedGeometries, WindowGeometry};
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> WindowGeometry {
+        WindowGeometry { x, y, width, height }
+    }
+
+    #[test]
+    fn intersection_is_the_overlap() {
+        let screen = rect(0, 0, 1920, 1080);
+        assert_eq!(rect(1800, -20, 400, 300).intersection(&screen), Some(rect(1800, 0, 120, 280)));
+        assert_eq!(rect(100, 100, 50, 50).intersection(&screen), Some(rect(100, 100, 50, 50)));
+        assert_eq!(screen.intersection(&rect(-1280, 0, 1280, 1024)), None);
+        assert_eq!(screen.intersection(&rect(1920, 1080, 10, 10)), None);
+    }
+
+    #[test]
+    fn clamp_moves_into_the_area() {
+        let screen = rect(-1280, 0, 1280, 1024);
+        assert_eq!(rect(-100, 900, 400, 300).clamp_to(&screen), rect(-400, 724, 400, 300));
+        assert_eq!(rect(-2000, -50, 400, 300).clamp_to(&screen), rect(-1280, 0, 400, 300));
+        assert_eq!(rect(-1000, 100, 400, 300).clamp_to(&screen), rect(-1000, 100, 400, 300));
+    }
+
+    #[test]
+    fn clamp_shrinks_what_is_bigger_than_the_area() {
+        let screen = rect(0, 0, 1280, 720);
+        assert_eq!(rect(500, 500, 2000, 400).clamp_to(&screen), rect(0, 320, 1280, 400));
+        assert_eq!(rect(0, 0, 1280, 720).clamp_to(&screen), screen);
+    }
+
+    #[test]
+    fn other_setups_fall_back_to_the_last_one() {
+        let mut geometries = SavedGeometries::default();
+        assert_eq!(geometries.get("laptop"), None);
+        geometries.set("laptop", rect(0, 0, 400, 300));
+        geometries.set("docked", rect(2000, 0, 400, 300));
+        assert_eq!(geometries.get("laptop"), Some(rect(0, 0, 400, 300)));
+        assert_eq!(geometries.get("projector"), Some(rect(2000, 0, 400, 300)));
+    }
+}
@@ -1,26 +1,154 @@
+use std::path::{Path, PathBuf};
+
 #[allow(dead_code)]
 pub type BSResult<T> = std::result::Result<T, BSError>;
 
-#[derive(Debug, Default)]
-pub struct BSError {
-    msg: String,
+/// Boxed underlying error kept for [std::error::Error::source] chaining.
+/// Needs to be [Send] since errors travel back from the background worker.
+pub type ErrorSource = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors of the app, grouped by where they come from so that callers can decide
+/// which failures are fatal (ie. no UI) and which can be ignored (ie. statistics).
+#[derive(Debug)]
+pub enum BSError {
+    /// Reading, parsing or validating the configuration file
+    Config {
+        path: Option<PathBuf>,
+        msg: String,
+        source: Option<ErrorSource>,
+    },
+    /// File system or other OS I/O
+    Io {
+        path: Option<PathBuf>,
+        source: std::io::Error,
+    },
+    /// The statistics SQLite database
+    Database {
+        msg: String,
+        source: Option<rusqlite::Error>,
+    },
+    /// A URL that could not be understood
+    Url {
+        url: String,
+        source: Option<url::ParseError>,
+    },
+    /// OS specific APIs such as WinRT, WinAPI or the registry
+    Platform {
+        msg: String,
+        source: Option<ErrorSource>,
+    },
+    /// Starting the browser process
+    Launch {
+        exe_path: String,
+        source: std::io::Error,
+    },
+    Other {
+        msg: String,
+    },
 }
 
 impl BSError {
     pub fn new(msg: &str) -> BSError {
-        BSError { msg: String::from(msg) }
+        BSError::Other { msg: String::from(msg) }
+    }
+
+    pub fn config(path: Option<&Path>, msg: &str) -> BSError {
+        BSError::Config {
+            path: path.map(PathBuf::from),
+            msg: String::from(msg),
+            source: None,
+        }
+    }
+
+    pub fn io(path: &Path, source: std::io::Error) -> BSError {
+        BSError::Io {
+            path: Some(PathBuf::from(path)),
+            source,
+        }
+    }
+
+    pub fn url(url: &str, source: url::ParseError) -> BSError {
+        BSError::Url {
+            url: String::from(url),
+            source: Some(source),
+        }
+    }
+
+    pub fn platform(msg: &str) -> BSError {
+        BSError::Platform {
+            msg: String::from(msg),
+            source: None,
+        }
+    }
+
+    pub fn launch(exe_path: &str, source: std::io::Error) -> BSError {
+        BSError::Launch {
+            exe_path: String::from(exe_path),
+            source,
+        }
+    }
+
+    /// Human readable text of this error followed by all the errors that caused it,
+    /// fit for showing to the user or writing to a log.
+    pub fn report(&self) -> String {
+        let mut text = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            text.push_str(&format!("\n  Caused by: {}", cause));
+            source = cause.source();
+        }
+
+        text
     }
 }
 
 impl std::fmt::Display for BSError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.msg.as_str())
+        match self {
+            BSError::Config { path: Some(path), msg, .. } => {
+                write!(f, "Config error in {}: {}", path.display(), msg)
+            }
+            BSError::Config { path: None, msg, .. } => write!(f, "Config error: {}", msg),
+            BSError::Io { path: Some(path), .. } => write!(f, "IO error for {}", path.display()),
+            BSError::Io { path: None, .. } => f.write_str("IO error"),
+            BSError::Database { msg, .. } => write!(f, "Database error: {}", msg),
+            BSError::Url { url, .. } => write!(f, "Invalid URL \"{}\"", url),
+            BSError::Platform { msg, .. } => write!(f, "System error: {}", msg),
+            BSError::Launch { exe_path, .. } => write!(f, "Couldn't run browser program at {}", exe_path),
+            BSError::Other { msg } => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for BSError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BSError::Config { source, .. } | BSError::Platform { source, .. } => source
+                .as_ref()
+                .map(|err| err.as_ref() as &(dyn std::error::Error + 'static)),
+            BSError::Io { source, .. } | BSError::Launch { source, .. } => Some(source),
+            BSError::Database { source, .. } => source
+                .as_ref()
+                .map(|err| err as &(dyn std::error::Error + 'static)),
+            BSError::Url { source, .. } => source
+                .as_ref()
+                .map(|err| err as &(dyn std::error::Error + 'static)),
+            BSError::Other { .. } => None,
+        }
+    }
+}
+
+impl Default for BSError {
+    fn default() -> Self {
+        BSError::new("Unknown error")
     }
 }
 
+#[cfg(target_os = "windows")]
 impl From<winrt::Error> for BSError {
     fn from(err: winrt::Error) -> Self {
-        BSError::new(format!("[WinRT error] code: {} {}", err.code().0, err.message(),).as_str())
+        // winrt::Error holds COM pointers which can't be sent across threads, keep only the text
+        BSError::platform(format!("[WinRT error] code: {} {}", err.code().0, err.message(),).as_str())
     }
 }
 
@@ -32,7 +160,11 @@ impl From<simple_error::SimpleError> for BSError {
 
 impl From<serde_yaml::Error> for BSError {
     fn from(err: serde_yaml::Error) -> Self {
-        BSError::new(&err.to_string())
+        BSError::Config {
+            path: None,
+            msg: String::from("Unable to parse YAML"),
+            source: Some(Box::new(err)),
+        }
     }
 }
 
@@ -42,28 +174,26 @@ impl From<&str> for BSError {
     }
 }
 
+impl From<crate::data::thread_worker::TaskInterrupted> for BSError {
+    fn from(interrupted: crate::data::thread_worker::TaskInterrupted) -> Self {
+        BSError::new(&interrupted.to_string())
+    }
+}
+
 impl From<std::io::Error> for BSError {
     fn from(error: std::io::Error) -> Self {
-        let os_code = error.raw_os_error().unwrap_or(0);
-        let error_message = format!("IO Error: Code ({}) - {}", os_code, error.to_string());
-        BSError::new(error_message.as_str())
+        BSError::Io {
+            path: None,
+            source: error,
+        }
     }
 }
 
 impl From<rusqlite::Error> for BSError {
     fn from(error: rusqlite::Error) -> Self {
-        let error_message = format!("SQLite Error:- {}", error.to_string());
-        BSError::new(error_message.as_str())
+        BSError::Database {
+            msg: String::from("SQLite operation failed"),
+            source: Some(error),
+        }
     }
 }
-
-
-
-// impl From<std::io::Result<T>> for BSResult<T> {
-//     fn from(result: std::io::Result<T>) -> Self {
-//         match result {
-//             Ok(t) => Ok(t),
-//             Err(e) => e.into()
-//         }
-//     }
-// }
\ No newline at end of file
@@ -0,0 +1,54 @@
+# Der Auswahldialog, alle Oberflächen zeigen dieselben Texte
+
+open-intro = Gleich wird geöffnet:
+urls-more = { $url } (und { $count ->
+        [one] eine weitere
+       *[other] { $count } weitere
+    })
+url-edit-hint = Strg+L zum Bearbeiten
+url-not-valid = Keine gültige URL: { $error }
+filter-label = Filter:
+browsers = Browser
+browsers-loading = Browser (weitere werden gesucht...)
+browsers-matching = Browser passend zu „{ $filter }“
+browsers-looking = Browser werden gesucht...
+predictions = Vorschläge
+predictions-title = Vorschläge ({ $duration })
+key-space = Leertaste
+key-backspace = Rücktaste
+qr-code-title = QR-Code von { $url }
+qr-code-close = Esc zum Schließen
+footer = Tippen filtert  1-9/Alt+Buchstabe öffnen  Enter öffnen  Umschalt+Enter privat  Strg+Enter URL kopieren  Strg+L URL bearbeiten  Esc abbrechen
+footer-url-edit = Enter URL übernehmen  Esc Änderung verwerfen
+footer-qr-code = Esc QR-Code schließen
+footer-history = Tippen filtert  1-9 wählen  Enter wählen  Esc zurück zu den Browsern
+history = Zuletzt geöffnet
+history-matching = Zuletzt geöffnet passend zu „{ $filter }“
+history-hint = Einen Link wählen, um ihn im danach gewählten Browser erneut zu öffnen:
+history-entry = { $browser }, { $date }
+history-entry-source = { $browser }, { $date }, aus { $source }
+
+# Die Aktionen nach den Browsern
+
+action-copy = URL kopieren
+action-copy-description = Legt die URLs in die Zwischenablage
+action-qr-code = QR-Code anzeigen
+action-qr-code-description = Zum Scannen mit dem Handy
+action-read-later = Später lesen
+action-read-later-description = Hängt die URLs an die Leseliste an
+action-print = URL ausgeben
+action-print-description = Schreibt die URLs in die Standardausgabe
+action-history = Zuletzt geöffnet
+action-history-description = Einen Link erneut öffnen, in einem anderen Browser
+
+# Probleme, die angezeigt werden
+
+private-mode-unknown = { $browser } hat keinen bekannten privaten Modus, die URLs wurden nicht geöffnet.
+config-errors = Die Konfiguration hat { $count ->
+        [one] einen Fehler
+       *[other] { $count } Fehler
+    } und { $outcome }. { $details }
+config-ignored = wurde ignoriert
+config-kept = die vorherige bleibt aktiv
+config-details-log = Details stehen in { $path }.
+config-details-command = Details zeigt `config check`.
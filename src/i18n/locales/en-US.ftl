@@ -0,0 +1,53 @@
+# The selector, all the frontends show the same texts
+
+open-intro = You are about to open:
+# the first URL followed by how many others there are
+urls-more = { $url } (and { $count } more)
+url-edit-hint = Ctrl+L to edit
+url-not-valid = Not a valid URL: { $error }
+filter-label = Filter:
+browsers = Browsers
+browsers-loading = Browsers (looking for more...)
+browsers-matching = Browsers matching "{ $filter }"
+browsers-looking = Looking for browsers...
+predictions = Predictions
+predictions-title = Predictions ({ $duration })
+key-space = Space
+key-backspace = Backspace
+qr-code-title = QR code of { $url }
+qr-code-close = Esc to close
+# the keys, as the terminal shows them at the bottom
+footer = Type to filter  1-9/Alt+letter open  Enter open  Shift+Enter private  Ctrl+Enter copy URL  Ctrl+L edit URL  Esc cancel
+footer-url-edit = Enter use the URL  Esc undo the edit
+footer-qr-code = Esc close the QR code
+footer-history = Type to filter  1-9 pick  Enter pick  Esc back to the browsers
+history = Recent links
+history-matching = Recent links matching "{ $filter }"
+history-hint = Pick a link to open it again, in the browser chosen next:
+history-entry = { $browser }, { $date }
+history-entry-source = { $browser }, { $date }, from { $source }
+
+# The actions listed after the browsers
+
+action-copy = Copy URL
+action-copy-description = Puts the URLs on the clipboard
+action-qr-code = Show QR code
+action-qr-code-description = To scan with a phone
+action-read-later = Read later
+action-read-later-description = Appends the URLs to the read-later file
+action-print = Print URL
+action-print-description = Writes the URLs to the standard output
+action-history = Recent links
+action-history-description = Opens a link again, in another browser
+
+# Problems shown to the user
+
+private-mode-unknown = { $browser } has no known private mode, the URLs were not opened.
+config-errors = The config has { $count ->
+        [one] an error
+       *[other] { $count } errors
+    } and { $outcome }. { $details }
+config-ignored = was ignored
+config-kept = the previous config is kept
+config-details-log = See { $path } for details.
+config-details-command = Run `config check` for details.
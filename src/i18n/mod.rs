@@ -0,0 +1,129 @@
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{negotiate_languages, NegotiationStrategy};
+use unic_langid::LanguageIdentifier;
+
+/// The translations shipped with the app, in [Fluent](https://projectfluent.org/) syntax.
+/// English has every message, the other languages fall back to it for the ones they miss.
+const LOCALES: [(&str, &str); 2] = [
+    ("en-US", include_str!("locales/en-US.ftl")),
+    ("de", include_str!("locales/de.ftl")),
+];
+const DEFAULT_LOCALE: &str = "en-US";
+
+static LOCALIZATION: OnceLock<Localization> = OnceLock::new();
+
+/// The bundles to look messages up in, the languages the user prefers first and English last.
+struct Localization {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Localization {
+    /// Picks the shipped languages matching the `requested` ones, as BCP 47 tags such as `de-AT`.
+    fn new(requested: &[String]) -> Self {
+        let requested: Vec<LanguageIdentifier> = requested.iter().filter_map(|tag| tag.parse().ok()).collect();
+        let available: Vec<LanguageIdentifier> = LOCALES
+            .iter()
+            .map(|(tag, _)| tag.parse().expect("The shipped locale tags are valid"))
+            .collect();
+        let default: LanguageIdentifier = DEFAULT_LOCALE.parse().expect("The default locale tag is valid");
+
+        let supported = negotiate_languages(&requested, &available, Some(&default), NegotiationStrategy::Filtering);
+        let bundles = supported
+            .into_iter()
+            .filter_map(|locale| {
+                let (_, source) = LOCALES[available.iter().position(|tag| tag == locale)?];
+                bundle(locale, source)
+            })
+            .collect();
+
+        Localization { bundles }
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        self.bundles
+            .iter()
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                Some(bundle.format_pattern(pattern, args, &mut errors).to_string())
+            })
+            // a missing message is a bug, its id at least tells which one
+            .unwrap_or_else(|| String::from(id))
+    }
+}
+
+/// The messages of `source` in the `locale` language.
+fn bundle(locale: &LanguageIdentifier, source: &str) -> Option<FluentBundle<FluentResource>> {
+    // a message with a syntax error is left out, the others in the file still work
+    let resource = FluentResource::try_new(String::from(source)).unwrap_or_else(|(resource, _)| resource);
+    let mut bundle = FluentBundle::new_concurrent(vec![locale.clone()]);
+    // the isolation marks show as garbage in terminals and message boxes
+    bundle.set_use_isolating(false);
+    bundle.add_resource(resource).ok()?;
+    Some(bundle)
+}
+
+fn localization() -> &'static Localization {
+    LOCALIZATION.get_or_init(|| Localization::new(&crate::os::get_ui_languages()))
+}
+
+/// The message `id` in the language of the OS, or in English when it isn't translated.
+pub fn text(id: &str) -> String {
+    localization().format(id, None)
+}
+
+/// The message `id` with its variables, ie. `text_args("urls-more", &[("count", 2.into())])`.
+pub fn text_args(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let args: FluentArgs = args.iter().cloned().collect();
+
+    localization().format(id, Some(&args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ids of the messages in `source`, the lines starting a message start with a letter.
+    fn message_ids(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .filter_map(|line| Some(line.split_once('=')?.0.trim()))
+            .collect()
+    }
+
+    #[test]
+    fn every_locale_parses() {
+        for (tag, source) in LOCALES {
+            if let Err((_, errors)) = FluentResource::try_new(String::from(source)) {
+                panic!("{} has errors: {:?}", tag, errors);
+            }
+        }
+    }
+
+    #[test]
+    fn german_has_every_english_message() {
+        let german = bundle(&"de".parse().unwrap(), LOCALES[1].1).unwrap();
+        let missing: Vec<&str> = message_ids(LOCALES[0].1)
+            .into_iter()
+            .filter(|id| !german.has_message(id))
+            .collect();
+        assert_eq!(missing, Vec::<&str>::new());
+    }
+
+    #[test]
+    fn a_regional_tag_picks_the_language_and_falls_back_to_english() {
+        let mut localization = Localization::new(&[String::from("de-AT")]);
+        assert_eq!(localization.bundles.len(), 2);
+        assert_eq!(localization.format("browsers", None), "Browser");
+
+        // a German translation without the message, as when a new one isn't translated yet
+        let german: LanguageIdentifier = "de".parse().unwrap();
+        localization.bundles[0] = bundle(&german, "browsers = Browser").unwrap();
+        assert_eq!(localization.format("browsers", None), "Browser");
+        assert_eq!(localization.format("predictions", None), "Predictions");
+    }
+}
@@ -0,0 +1,314 @@
+pub mod server;
+
+use interprocess::local_socket::LocalSocketStream;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::error::{BSError, BSResult};
+
+/// Version of the messages exchanged with the daemon. Bump it on any change to [Request] or
+/// [Response] that an older build would read differently, both sides refuse other versions.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Longest message accepted, far more than any list of URLs fits on a command line.
+const MAX_MESSAGE_SIZE: u64 = 1024 * 1024;
+
+/// How long either end waits on the other to read or write a message. Both only exchange one
+/// short line, a peer that takes longer is stuck or isn't a Browsor instance.
+pub const IPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What another invocation asks the daemon to do.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    /// Show the selector for the URLs. No URLs means the `default_url` from the daemon config.
+    Open {
+        urls: Vec<String>,
+        /// Executable of the app the link was opened from, it is in the foreground when we start
+        source_exe: Option<PathBuf>,
+        source_window: Option<String>,
+    },
+    Ping,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    /// The request was queued, the daemon shows the selector shortly after
+    Accepted,
+    Pong,
+    Error { message: String },
+}
+
+/// Every message is a single line of JSON with the protocol version next to the message fields,
+/// ie. `{"version":1,"type":"open","urls":["https://example.com"],...}`.
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(flatten)]
+    message: T,
+}
+
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> BSResult<()> {
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        message,
+    };
+    let mut line = serde_json::to_string(&envelope).map_err(protocol_error)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl BufRead) -> BSResult<T> {
+    let mut line = String::new();
+    // a peer that never ends its line can't make us buffer without limit
+    let read = (&mut *reader).take(MAX_MESSAGE_SIZE + 1).read_line(&mut line)?;
+    if read == 0 {
+        return Err(BSError::new("The connection was closed before a message was received."));
+    }
+    if !line.ends_with('\n') {
+        return Err(BSError::new(&if read as u64 > MAX_MESSAGE_SIZE {
+            format!("The message is longer than {} bytes.", MAX_MESSAGE_SIZE)
+        } else {
+            String::from("The connection was closed in the middle of a message.")
+        }));
+    }
+
+    let value: serde_json::Value = serde_json::from_str(&line).map_err(protocol_error)?;
+    let version = value.get("version").and_then(serde_json::Value::as_u64);
+    if version != Some(PROTOCOL_VERSION as u64) {
+        return Err(BSError::new(&format!(
+            "Unsupported protocol version {}, expected {}.",
+            version.map_or(String::from("(none)"), |version| version.to_string()),
+            PROTOCOL_VERSION
+        )));
+    }
+    let envelope: Envelope<T> = serde_json::from_value(value).map_err(protocol_error)?;
+
+    Ok(envelope.message)
+}
+
+fn protocol_error(err: serde_json::Error) -> BSError {
+    BSError::new(&format!("Invalid message: {}", err))
+}
+
+fn transport_error(err: std::io::Error) -> BSError {
+    BSError::Platform {
+        msg: String::from("Cannot communicate with the running instance"),
+        source: Some(Box::new(err)),
+    }
+}
+
+/// Makes reads and writes on the `stream` fail after [IPC_TIMEOUT] instead of waiting on the peer
+/// for good.
+#[cfg(not(target_os = "windows"))]
+fn set_timeouts(stream: &LocalSocketStream) -> BSResult<()> {
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::os::unix::net::UnixStream;
+
+    // the timeouts are options of the socket itself, the UnixStream only lends its setters and
+    // must not close the socket when dropped
+    let socket = std::mem::ManuallyDrop::new(unsafe { UnixStream::from_raw_fd(stream.as_raw_fd()) });
+    socket
+        .set_read_timeout(Some(IPC_TIMEOUT))
+        .and_then(|_| socket.set_write_timeout(Some(IPC_TIMEOUT)))
+        .map_err(transport_error)
+}
+
+/// Named pipes have no timeouts, [send] gives up on its own and the daemon answers every
+/// connection on a thread of its own, see [server::Server::start].
+#[cfg(target_os = "windows")]
+fn set_timeouts(_stream: &LocalSocketStream) -> BSResult<()> {
+    Ok(())
+}
+
+/// Name of the local socket the daemon listens on, one per user. A named pipe on Windows
+/// (`\\.\pipe\browsor-<user>`).
+#[cfg(target_os = "windows")]
+pub fn socket_name() -> BSResult<String> {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    Ok(format!("@browsor-{}", user))
+}
+
+/// Name of the local socket the daemon listens on, one per user. A socket file in the user runtime
+/// directory, or in a directory of the temp directory private to the user when there is none.
+#[cfg(not(target_os = "windows"))]
+pub fn socket_name() -> BSResult<String> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        // private to the user as the XDG base directory spec requires
+        Some(runtime_dir) => PathBuf::from(runtime_dir),
+        None => {
+            let user = std::env::var("USER").unwrap_or_default();
+            let dir = std::env::temp_dir().join(format!("browsor-{}", user));
+            ensure_private_dir(&dir)?;
+            dir
+        }
+    };
+
+    Ok(dir.join("browsor.sock").to_string_lossy().into_owned())
+}
+
+/// Creates `dir` accessible to the current user only, or checks that it is when it exists. Anyone
+/// can create it first in the shared temp directory, the daemon socket in there would then
+/// receive the URLs of another user.
+#[cfg(not(target_os = "windows"))]
+fn ensure_private_dir(dir: &std::path::Path) -> BSResult<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(err) if err.kind() != std::io::ErrorKind::AlreadyExists => return Err(transport_error(err)),
+        _ => (),
+    }
+    // a link would be followed to a directory the checks below didn't see
+    let metadata = std::fs::symlink_metadata(dir).map_err(transport_error)?;
+    // getuid can't fail and has no preconditions
+    let uid = unsafe { libc::getuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(BSError::platform(&format!(
+            "{} is not private to the current user, remove it or set XDG_RUNTIME_DIR.",
+            dir.display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Sends the `request` to the daemon and waits for its response. `None` right away when no daemon
+/// runs, fails after [IPC_TIMEOUT] when it doesn't answer.
+pub fn send(request: &Request) -> BSResult<Option<Response>> {
+    send_to(&socket_name()?, request, IPC_TIMEOUT)
+}
+
+fn send_to(name: &str, request: &Request, timeout: Duration) -> BSResult<Option<Response>> {
+    let Ok(mut stream) = LocalSocketStream::connect(name) else {
+        return Ok(None);
+    };
+    set_timeouts(&stream)?;
+    let request = request.clone();
+    let (sender, receiver) = mpsc::channel();
+    // on a thread so that it can be given up on where the socket has no timeouts
+    std::thread::spawn(move || {
+        let response =
+            write_message(&mut stream, &request).and_then(|_| read_message(&mut BufReader::new(stream)));
+        sender.send(response).ok();
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(response) => response.map(Some),
+        Err(_) => Err(BSError::platform(&format!(
+            "The running instance didn't answer within {} seconds.",
+            timeout.as_secs_f32()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn open_request() -> Request {
+        Request::Open {
+            urls: vec![String::from("https://example.com")],
+            source_exe: Some(PathBuf::from("/usr/bin/mail")),
+            source_window: None,
+        }
+    }
+
+    #[test]
+    fn messages_are_framed_one_per_line() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &open_request()).unwrap();
+        write_message(&mut buffer, &Request::Ping).unwrap();
+
+        assert_eq!(buffer.iter().filter(|byte| **byte == b'\n').count(), 2);
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(read_message::<Request>(&mut reader).unwrap(), open_request());
+        assert_eq!(read_message::<Request>(&mut reader).unwrap(), Request::Ping);
+        assert!(read_message::<Request>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn messages_carry_the_protocol_version() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &Response::Accepted).unwrap();
+
+        let text = String::from_utf8(buffer).unwrap();
+        assert_eq!(text, format!("{{\"version\":{},\"type\":\"accepted\"}}\n", PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn other_protocol_versions_are_refused() {
+        let mut reader = Cursor::new(b"{\"version\":99,\"type\":\"ping\"}\n".to_vec());
+        assert!(read_message::<Request>(&mut reader).is_err());
+
+        let mut reader = Cursor::new(b"{\"type\":\"ping\"}\n".to_vec());
+        assert!(read_message::<Request>(&mut reader).is_err());
+    }
+
+    #[test]
+    fn truncated_messages_are_refused() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &open_request()).unwrap();
+        buffer.truncate(buffer.len() / 2);
+
+        let err = read_message::<Request>(&mut Cursor::new(buffer)).err().unwrap();
+        assert!(err.report().contains("middle of a message"));
+    }
+
+    #[test]
+    fn oversized_messages_are_refused() {
+        let urls = vec![String::from("https://example.com/"); (MAX_MESSAGE_SIZE / 20) as usize];
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &Request::Open { urls, source_exe: None, source_window: None }).unwrap();
+
+        let err = read_message::<Request>(&mut Cursor::new(buffer)).err().unwrap();
+        assert!(err.report().contains("longer than"));
+    }
+
+    #[test]
+    fn malformed_messages_are_refused() {
+        let mut reader = Cursor::new(b"{\"version\":1,\"type\":\"launch\"}\nnot json\n".to_vec());
+
+        assert!(read_message::<Request>(&mut reader).is_err());
+        assert!(read_message::<Request>(&mut reader).is_err());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn send_gives_up_on_a_daemon_that_does_not_answer() {
+        let name = std::env::temp_dir().join(format!("browsor-ipc-silent-{}.sock", std::process::id()));
+        let name = name.to_string_lossy().into_owned();
+        let listener = interprocess::local_socket::LocalSocketListener::bind(name.as_str()).unwrap();
+        // accepted and kept open without a word
+        let accepted = std::thread::spawn(move || listener.accept().unwrap());
+
+        let result = send_to(&name, &Request::Ping, Duration::from_millis(200));
+        let _silent = accepted.join().unwrap();
+        std::fs::remove_file(&name).unwrap();
+
+        assert!(result.err().unwrap().report().contains("didn't answer"));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn shared_socket_directories_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("browsor-ipc-test-{}", std::process::id()));
+        ensure_private_dir(&dir).unwrap();
+        let private_mode = std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let shared = ensure_private_dir(&dir);
+        std::fs::remove_dir(&dir).unwrap();
+
+        assert_eq!(private_mode, 0o700);
+        assert!(shared.is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream};
+use std::io::BufReader;
+use std::sync::mpsc;
+
+use super::{read_message, set_timeouts, socket_name, transport_error, write_message, Request, Response};
+use crate::error::BSResult;
+
+/// Listens for requests of other invocations on a background thread. The requests are not handled
+/// there, [`poll`](Server::poll) hands them to the event loop the same way
+/// [crate::conf::watcher::ConfigWatcher::poll] does for config changes.
+pub struct Server {
+    receiver: mpsc::Receiver<Request>,
+}
+
+impl Server {
+    /// Starts listening, fails when another daemon already listens for the current user.
+    pub fn start() -> BSResult<Self> {
+        Ok(Self::listen(bind()?))
+    }
+
+    /// Each connection is answered on a thread of its own, a client that connects and then sends
+    /// nothing holds up its thread only, until [super::IPC_TIMEOUT] where the socket has timeouts.
+    fn listen(listener: LocalSocketListener) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = handle_connection(stream, &sender) {
+                        crate::log_error("Failed handling a request", &err);
+                    }
+                });
+            }
+        });
+
+        Self { receiver }
+    }
+
+    /// The next request received, if any.
+    pub fn poll(&self) -> Option<Request> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+/// Answers a request, queueing the ones the UI has to act on. Kept apart from the socket
+/// so that the protocol can be exercised without a running daemon.
+pub fn handle_request(request: Request, sender: &mpsc::Sender<Request>) -> Response {
+    match request {
+        Request::Ping => Response::Pong,
+        request => match sender.send(request) {
+            Ok(_) => Response::Accepted,
+            Err(_) => Response::Error {
+                message: String::from("The daemon is shutting down."),
+            },
+        },
+    }
+}
+
+fn handle_connection(stream: LocalSocketStream, sender: &mpsc::Sender<Request>) -> BSResult<()> {
+    set_timeouts(&stream)?;
+    let mut reader = BufReader::new(stream);
+    let response = match read_message::<Request>(&mut reader) {
+        Ok(request) => handle_request(request, sender),
+        Err(err) => Response::Error {
+            message: err.report(),
+        },
+    };
+
+    write_message(reader.get_mut(), &response)
+}
+
+#[cfg(target_os = "windows")]
+fn bind() -> BSResult<LocalSocketListener> {
+    LocalSocketListener::bind(socket_name()?).map_err(transport_error)
+}
+
+/// A socket file left behind by a daemon that didn't exit cleanly makes binding fail,
+/// it is removed when nothing answers on it.
+#[cfg(not(target_os = "windows"))]
+fn bind() -> BSResult<LocalSocketListener> {
+    let name = socket_name()?;
+    let path = std::path::Path::new(&name);
+
+    match LocalSocketListener::bind(name.as_str()) {
+        Err(err) if err.kind() == std::io::ErrorKind::AddrInUse && LocalSocketStream::connect(name.as_str()).is_err() => {
+            std::fs::remove_file(path).map_err(transport_error)?;
+            LocalSocketListener::bind(name.as_str()).map_err(transport_error)
+        }
+        result => result.map_err(transport_error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::{send_to, IPC_TIMEOUT};
+
+    fn open_request() -> Request {
+        Request::Open {
+            urls: vec![String::from("https://example.com")],
+            source_exe: None,
+            source_window: None,
+        }
+    }
+
+    #[test]
+    fn ping_is_answered_without_queueing() {
+        let (sender, receiver) = mpsc::channel();
+
+        assert_eq!(handle_request(Request::Ping, &sender), Response::Pong);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn open_is_queued_for_the_event_loop() {
+        let (sender, receiver) = mpsc::channel();
+
+        assert_eq!(handle_request(open_request(), &sender), Response::Accepted);
+        assert_eq!(receiver.try_recv().unwrap(), open_request());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn a_silent_client_does_not_hold_up_the_others() {
+        let name = std::env::temp_dir().join(format!("browsor-server-test-{}.sock", std::process::id()));
+        let name = name.to_string_lossy().into_owned();
+        let server = Server::listen(LocalSocketListener::bind(name.as_str()).unwrap());
+
+        let _silent = LocalSocketStream::connect(name.as_str()).unwrap();
+        let response = send_to(&name, &open_request(), IPC_TIMEOUT);
+        std::fs::remove_file(&name).unwrap();
+
+        assert_eq!(response.unwrap(), Some(Response::Accepted));
+        assert_eq!(server.poll(), Some(open_request()));
+    }
+
+    #[test]
+    fn open_fails_once_the_event_loop_is_gone() {
+        let (sender, receiver) = mpsc::channel();
+        drop(receiver);
+
+        assert!(matches!(handle_request(open_request(), &sender), Response::Error { .. }));
+    }
+}
@@ -5,174 +5,710 @@ extern crate simple_error;
 #[macro_use]
 extern crate rusqlite;
 
+mod actions;
+mod cli;
 mod conf;
 mod data;
 mod error;
+mod i18n;
+mod ipc;
 mod os;
 mod ui;
 
 use core::cell::RefCell;
 use os::ActiveWindowInfo;
 use std::rc::Rc;
+use std::sync::Mutex;
 use winit::event_loop::ControlFlow;
 
-use crate::os::sys_browsers;
+use crate::actions::{Action, Target};
+use crate::error::{BSError, BSResult};
+use crate::os::discovery::{BrowserDiscovery, DiscoveredBrowser, DiscoveryEvent};
 use crate::os::sys_browsers::Browser;
-use crate::ui::{BrowserSelectorUI, ListItem, UserInterface};
+use crate::ui::{BrowserSelectorUI, ListItem, SelectionAction, TerminalUI, UserInterface};
+
+const PREDICTION_TIMEOUT_MS: u64 = 3_000;
+
+/// For errors after which the app cannot do anything useful, ie. there is no UI or no browsers to pick from.
+fn exit_with_error(err: BSError) -> ! {
+    crate::os::output_error_text(env!("CARGO_PKG_NAME"), err.report());
+    std::process::exit(1);
+}
+
+/// Log lines held while the terminal selector draws on the terminal, printing them would garble
+/// it. `None` while they are printed right away.
+static HELD_LOG: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// For errors that only take away secondary features such as statistics and predictions.
+fn log_error(context: &str, err: &BSError) {
+    let line = format!("{}: {}", context, err.report());
+    match HELD_LOG.lock().as_deref_mut() {
+        Ok(Some(held)) => held.push(line),
+        _ => eprintln!("{}", line),
+    }
+}
+
+/// Holds the errors logged from now on until [release_log], while the terminal is not ours to print to.
+fn hold_log() {
+    if let Ok(mut held) = HELD_LOG.lock() {
+        held.get_or_insert_with(Vec::new);
+    }
+}
+
+/// Prints the errors held since [hold_log], the ones logged after it are printed right away again.
+fn release_log() {
+    let held = HELD_LOG.lock().ok().and_then(|mut held| held.take());
+    for line in held.into_iter().flatten() {
+        eprintln!("{}", line);
+    }
+}
+
+/// Writes the config `diagnostics` to the log and returns the text to show in the UI, if there are any.
+/// The `outcome` tells what became of the config, translated.
+fn config_warning_text(diagnostics: &[conf::ConfigDiagnostic], outcome: &str) -> Option<String> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    let details = match conf::write_diagnostics_log(diagnostics) {
+        Ok(log_path) => i18n::text_args("config-details-log", &[("path", log_path.display().to_string().into())]),
+        Err(err) => {
+            log_error("Failed writing the config errors log", &err);
+            i18n::text("config-details-command")
+        }
+    };
+    Some(i18n::text_args(
+        "config-errors",
+        &[
+            ("count", diagnostics.len().into()),
+            ("outcome", outcome.into()),
+            ("details", details.into()),
+        ],
+    ))
+}
+
+/// The items to show given the config `hide`/`only` rules, sorted by the `order` rules and with
+/// their mnemonics assigned.
+fn visible_list_items(config: &conf::Config, items: &[ListItem<Target>]) -> Vec<ListItem<Target>> {
+    let mut visible: Vec<ListItem<Target>> = config
+        .visible_items(items, |item| item.state.match_fields())
+        .into_iter()
+        .cloned()
+        .collect();
+    ui::keys::assign_mnemonics(&mut visible, |item| config.browser_mnemonic(&item.state.match_fields()));
+
+    visible
+}
+
+/// The request the selector is shown for. In daemon mode a new one replaces it with every request.
+struct Session {
+    urls: Vec<String>,
+    source: ActiveWindowInfo,
+    /// The history entry the URL was picked from, the browser chosen for it corrects that choice
+    corrects: Option<usize>,
+}
+
+impl Session {
+    fn new(urls: Vec<String>, source: ActiveWindowInfo, config: &conf::Config) -> Self {
+        let urls = if urls.is_empty() {
+            vec![config.default_url.clone()]
+        } else {
+            urls
+        };
+
+        Session {
+            urls,
+            source,
+            corrects: None,
+        }
+    }
+}
+
+/// Hands the URLs to the daemon and exits, returns when there is no daemon to take them.
+fn forward_to_daemon(urls: &[String], source: &ActiveWindowInfo) {
+    let request = ipc::Request::Open {
+        urls: urls.to_vec(),
+        source_exe: source.exe_path.clone(),
+        source_window: source.window_name.clone(),
+    };
+    match ipc::send(&request) {
+        Ok(Some(ipc::Response::Accepted)) => std::process::exit(0),
+        Ok(Some(response)) => log_error(
+            "The running instance didn't take the URLs",
+            &BSError::new(&format!("{:?}", response)),
+        ),
+        Ok(None) => (), // no daemon running, the usual case
+        // ie. it is stuck, the selector shows here instead
+        Err(err) => log_error("The running instance didn't take the URLs", &err),
+    }
+}
+
+fn show_session(
+    ui: &mut impl UserInterface<Target>,
+    config: &conf::Config,
+    all_items: &[ListItem<Target>],
+    session: &Session,
+) -> BSResult<()> {
+    // what was typed for the previous URL doesn't carry over
+    ui.set_filter("")?;
+    ui.set_url_edit(None)?;
+    ui.set_qr_code(None)?;
+    ui.set_history(None)?;
+    ui.set_list(&visible_list_items(config, all_items))?;
+    ui.set_urls(&session.urls)?;
+    ui.set_main_window_visible(true);
+
+    Ok(())
+}
+
+/// The list entry for a browser found by discovery.
+fn discovered_list_item<UI: UserInterface<Target>>(found: DiscoveredBrowser) -> ListItem<Target> {
+    let image = found
+        .icon
+        .as_ref()
+        .and_then(|pixels| UI::image_from_pixels(pixels).ok())
+        .unwrap_or_default();
+
+    found.browser.list_item(image)
+}
+
+/// Adds the browser `items` to `all_items`, before the actions so that these stay listed last.
+fn add_browser_items(all_items: &mut Vec<ListItem<Target>>, items: impl IntoIterator<Item = ListItem<Target>>) {
+    let actions_start = all_items
+        .iter()
+        .position(|item| matches!(item.state.as_ref(), Target::Action(_)))
+        .unwrap_or(all_items.len());
+    all_items.splice(actions_start..actions_start, items);
+}
+
+/// Adds the browsers discovered since the last call to `all_items` and refreshes the list.
+/// Returns true when the list got complete, that is once discovery finished and again if the
+/// cached browsers shown were outdated and got replaced.
+fn receive_discovered_browsers<UI: UserInterface<Target>>(
+    discovery: &mut BrowserDiscovery,
+    ui: &mut UI,
+    config: &conf::Config,
+    all_items: &RefCell<Vec<ListItem<Target>>>,
+) -> bool {
+    let mut received = false;
+    let mut completed = false;
+    while let Some(event) = discovery.poll() {
+        match event {
+            DiscoveryEvent::Found(found) => {
+                add_browser_items(&mut all_items.borrow_mut(), [discovered_list_item::<UI>(found)]);
+                received = true;
+            }
+            DiscoveryEvent::Failed(err) => exit_with_error(err),
+            DiscoveryEvent::Finished => {
+                completed = true;
+                if let Err(err) = ui.set_list_is_loading(false) {
+                    log_error("Failed stopping loading state for the list", &err);
+                }
+            }
+            DiscoveryEvent::Refreshed(browsers) => {
+                let mut all_items = all_items.borrow_mut();
+                all_items.retain(|item| matches!(item.state.as_ref(), Target::Action(_)));
+                add_browser_items(&mut all_items, browsers.into_iter().map(discovered_list_item::<UI>));
+                received = true;
+                completed = true;
+            }
+        }
+    }
+
+    if received {
+        if let Err(err) = ui.set_list(&visible_list_items(config, &all_items.borrow())) {
+            log_error("Couldn't add the discovered browsers to the list", &err);
+        }
+    }
+
+    completed
+}
+
+/// Lets the statistics know which browsers are installed, and the actions.
+fn update_selections(statistics: &mut data::Statistics, all_items: &[ListItem<Target>]) {
+    let selections = all_items
+        .iter()
+        .map(|item| -> data::SelectionEntity {
+            data::SelectionEntity {
+                id: None,
+                path: Some(String::from(item.state.path())),
+                path_hash: Some(item.state.get_hash()),
+            }
+        })
+        .collect();
+
+    statistics.update_selections(selections, |res| {
+        if let Err(err) = res.as_ref() {
+            log_error(
+                "Failed updating the browsers available on the system to the statistics database",
+                err,
+            );
+        }
+    });
+}
+
+/// Opens the `urls` in the `browser`, in a private window for [SelectionAction::OpenPrivate].
+fn open_in_browser(browser: &Browser, action: SelectionAction, urls: &[String]) -> BSResult<()> {
+    let mut arguments = browser.arguments.clone();
+    if action == SelectionAction::OpenPrivate {
+        let Some(private_arguments) = browser.private_arguments() else {
+            let message = i18n::text_args("private-mode-unknown", &[("browser", browser.name.as_str().into())]);
+            return Err(BSError::new(&message));
+        };
+        arguments.extend(private_arguments.iter().map(|argument| argument.to_string()));
+    }
+
+    os::util::spawn_browser_process(&browser.exe_path, arguments, urls)
+}
+
+/// Starts predicting the browser for the `session`, the UI shows the result when it is ready.
+fn start_prediction<UI: UserInterface<Target> + 'static>(
+    statistics: &mut data::Statistics,
+    ui_ref: &Rc<RefCell<UI>>,
+    all_items: &Rc<RefCell<Vec<ListItem<Target>>>>,
+    session: &Session,
+) -> data::CancellationToken {
+    let start_time = std::time::Instant::now();
+    let ui_ref = Rc::clone(ui_ref);
+    let all_items = Rc::clone(all_items);
+    if let Err(err) = ui_ref.borrow().prediction_set_is_loading(true) {
+        log_error("Failed to set loading state for predictions", &err);
+    }
+
+    // with several URLs the first one decides, they all open in the same browser anyway
+    let prediction_deadline = start_time + std::time::Duration::from_millis(PREDICTION_TIMEOUT_MS);
+    let source = session.source.exe_path.clone();
+    statistics.predict(source, &session.urls[0], Some(prediction_deadline), move |result| {
+        if let Ok(predicted_list) = result.as_ref() {
+            let duration = start_time.elapsed();
+            let duration_msec = duration.as_millis();
+            let duration_str = format!("{} ms", duration_msec);
+            let items = all_items.borrow();
+            let list = predicted_list
+                .iter()
+                .take(2)
+                .filter_map(|item: &data::SelectionEntity| {
+                    items.iter().find(|list_item| {
+                        item.path_hash.as_deref() == Some(list_item.state.get_hash().as_str())
+                    })
+                })
+                .cloned()
+                .collect::<Vec<ListItem<Target>>>();
+
+            if let Err(err) = ui_ref
+                .borrow_mut()
+                .prediction_set_state(list.as_slice(), &duration_str)
+            {
+                log_error("Failed setting predicted state for the prediction section", &err);
+            }
+        } else {
+            if let Err(err) = result.as_ref() {
+                log_error("Prediction failed", err);
+            }
+
+            if let Err(err) = ui_ref.borrow().prediction_set_is_loading(false) {
+                log_error("Failed stopping loading state for predictions", &err);
+            }
+        }
+    })
+}
+
+/// The list entry for a link of the history, with the icon of the browser it was opened in.
+fn history_list_item(entry: data::HistoryEntity, all_items: &[ListItem<Target>]) -> ListItem<Target> {
+    let opened_in = all_items
+        .iter()
+        .find(|item| entry.path_hash.as_deref() == Some(item.state.get_hash().as_str()));
+    // the browser may be uninstalled since
+    let browser = match opened_in {
+        Some(item) => item.title.clone(),
+        None => entry
+            .path
+            .as_deref()
+            .and_then(|path| std::path::Path::new(path).file_stem())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    let date = entry.local_date().unwrap_or_default();
+    let subtitle = match entry.source_name() {
+        Some(source) => i18n::text_args(
+            "history-entry-source",
+            &[("browser", browser.into()), ("date", date.into()), ("source", source.into())],
+        ),
+        None => i18n::text_args("history-entry", &[("browser", browser.into()), ("date", date.into())]),
+    };
+    let title = entry.url.clone().unwrap_or_default();
+    let target = Target::History(entry);
+
+    ListItem {
+        title,
+        subtitle,
+        image: opened_in.map(|item| item.image.clone()).unwrap_or_default(),
+        uuid: target.get_hash(),
+        mnemonic: None,
+        state: Rc::new(target),
+    }
+}
+
+/// Loads the links opened lately and shows them in place of the browsers. The shown entries are
+/// kept in `history_items` for the selection handler to find them.
+fn show_history<UI: UserInterface<Target> + 'static>(
+    statistics: &mut data::Statistics,
+    ui_ref: &Rc<RefCell<UI>>,
+    all_items: &Rc<RefCell<Vec<ListItem<Target>>>>,
+    history_items: &Rc<RefCell<Vec<ListItem<Target>>>>,
+) {
+    let ui_ref = Rc::clone(ui_ref);
+    let all_items = Rc::clone(all_items);
+    let history_items = Rc::clone(history_items);
+    statistics.history(data::HISTORY_LENGTH, move |result| match result.as_ref() {
+        Ok(entries) => {
+            let items: Vec<ListItem<Target>> = entries
+                .iter()
+                .map(|entry| history_list_item(entry.clone(), &all_items.borrow()))
+                .collect();
+            if let Err(err) = ui_ref.borrow().set_history(Some(&items)) {
+                log_error("Couldn't show the history", &err);
+            }
+            *history_items.borrow_mut() = items;
+        }
+        Err(err) => log_error("Failed loading the history", err),
+    });
+}
 
 fn main() {
-    std::panic::set_hook(Box::new(|panic_info: &std::panic::PanicInfo| {
+    std::panic::set_hook(Box::new(|panic_info: &std::panic::PanicHookInfo| {
         crate::os::output_panic_text(panic_info.to_string());
     }));
 
-    let config = Rc::new(conf::read_config().unwrap_or_default());
+    let cli = cli::parse(std::env::args().skip(1)).unwrap_or_else(|message| {
+        crate::os::attach_parent_console();
+        eprintln!("{}\n\n{}", message, cli::USAGE);
+        std::process::exit(2);
+    });
+    let config_path = cli.config_path;
+    let (open_options, daemon) = match cli.command {
+        cli::Command::Daemon => (cli::OpenOptions::default(), true),
+        cli::Command::Open(options) if options.browser.is_none() && !options.no_ui && !options.dry_run => (options, false),
+        command => std::process::exit(cli::commands::run(command, config_path.as_deref())),
+    };
+
+    match open_options.ui.unwrap_or_else(ui::Frontend::detect) {
+        ui::Frontend::Gui => run_selector::<BrowserSelectorUI<Target>>(open_options, daemon, config_path),
+        ui::Frontend::Tui => {
+            crate::os::attach_parent_console();
+            run_selector::<TerminalUI<Target>>(open_options, daemon, config_path)
+        }
+    }
+}
+
+/// Shows the selector with the `UI` frontend until a browser is picked, or for as long as the daemon runs.
+fn run_selector<UI: UserInterface<Target> + 'static>(
+    open_options: cli::OpenOptions,
+    daemon: bool,
+    config_path: Option<std::path::PathBuf>,
+) -> ! {
+    // the app the link was opened from is in the foreground until our window shows
+    let source_app = os::get_active_window_info();
+    let ipc_server = if daemon {
+        Some(ipc::server::Server::start().unwrap_or_else(|err| exit_with_error(err)))
+    } else {
+        // the daemon would show its window, not the terminal selector asked for
+        if open_options.ui != Some(ui::Frontend::Tui) {
+            forward_to_daemon(&open_options.urls, &source_app);
+        }
+        None
+    };
+
+    let config_result = conf::read_config(config_path.as_deref());
+    let config_warning = config_warning_text(&config_result.diagnostics, &i18n::text("config-ignored"));
+    let config_layers = conf::get_config_search_paths(config_path.as_deref());
+    let mut config = Rc::new(config_result.config);
     let app_name = env!("CARGO_PKG_NAME");
     let app_version = env!("CARGO_PKG_VERSION");
-    let target_url = Rc::new(
-        std::env::args()
-            .nth(1)
-            .unwrap_or(config.default_url.clone()),
-    );
+    let session = Rc::new(RefCell::new(Session::new(open_options.urls, source_app, &config)));
     let mut statistics_optional: Option<Rc<RefCell<data::Statistics>>> = None;
 
-    let ui_ref = Rc::new(RefCell::new(
-        BrowserSelectorUI::new().expect("Failed to initialize COM or WinUI"),
-    ));
-    let event_loop = ui::ev_loop::make_ev_loop();
-    let mut browsers: Box<Vec<Browser>> = Box::new(Default::default());
-    let mut src_app_opt: Option<ActiveWindowInfo> = None;
+    // discovery starts first, it runs while the window gets created
+    let mut discovery = Some(BrowserDiscovery::start());
+    let ui_ref = Rc::new(RefCell::new(UI::new().unwrap_or_else(|err| exit_with_error(err))));
+    let prediction_token: Rc<RefCell<Option<data::CancellationToken>>> = Default::default();
+    // set by the UI when the user edited the URL, taken on the next event loop iteration
+    let edited_url: Rc<RefCell<Option<String>>> = Default::default();
+    // set by the UI when an action is chosen instead of a browser, likewise
+    let chosen_action: Rc<RefCell<Option<Action>>> = Default::default();
+    // set by the UI when a link of the history is picked to be opened again, likewise
+    let reopened_entry: Rc<RefCell<Option<data::HistoryEntity>>> = Default::default();
+    // the history entries shown, the selection handler looks them up with the browsers
+    let history_items: Rc<RefCell<Vec<ListItem<Target>>>> = Default::default();
 
-    if config.statistics {
-        src_app_opt = Some(os::get_active_window_info());
+    if config.privacy.statistics {
         statistics_optional = Some(Rc::new(RefCell::new(data::Statistics::new())));
         let statistics_ref = statistics_optional.clone().unwrap();
         let mut statistics = statistics_ref.borrow_mut();
-        let mut statistics_db_path = std::env::current_exe().unwrap_or_default();
-        statistics_db_path.set_file_name("statistics.sqlite");
-        statistics.set_db_path(&statistics_db_path);
-        statistics.migrate_async(|res| res.expect("Migration failed"));
+        statistics.set_db_path(&data::default_db_path());
+        statistics.migrate_async(|res| {
+            if let Err(err) = res.as_ref() {
+                log_error("Statistics database migration failed", err);
+            }
+        });
     }
 
     {
         let mut ui = ui_ref.borrow_mut();
         let title = format!("{} {}", app_name, app_version);
-        ui.create(&title, &event_loop)
-            .expect("Failed to create main UI.");
-    }
-
-    *browsers = sys_browsers::read_system_browsers_sync().expect("Could not read browser list");
-
-    if let Some(stats) = statistics_optional.clone() {
-        let mut statistics = stats.borrow_mut();
-        let source = src_app_opt.clone().unwrap_or_default().exe_path;
-        let start_time = std::time::Instant::now();
-        let browsers = browsers.clone();
-        let ui_ref = Rc::clone(&ui_ref);
-        let selections = browsers
-            .iter()
-            .map(|browser| -> data::SelectionEntity {
-                data::SelectionEntity {
-                    id: None,
-                    path: Some(browser.exe_path.clone()),
-                    path_hash: Some(browser.get_hash()),
-                }
-            })
-            .collect();
-
-        ui_ref
-            .borrow()
-            .prediction_set_is_loading(true)
-            .expect("Failed to set loading state for predictions.");
-        statistics.update_selections(selections, |res| {
-            res.expect(
-                "Failed updating the browsers available on the system to the statistics database.",
-            )
-        });
-        statistics.predict(source, &target_url, move |result| {
-            if let Ok(predicted_list) = result.as_ref() {
-                let duration = start_time.elapsed();
-                let duration_msec = duration.as_millis();
-                let duration_str = format!("{} ms", duration_msec);
-                let list = predicted_list
-                    .iter()
-                    .take(2)
-                    .filter_map(|item: &data::SelectionEntity| {
-                        browsers.iter().find(|browser| {
-                            browser.get_hash().as_str() == item.path_hash.as_ref().unwrap().as_str()
-                        })
-                    })
-                    .map(|browser| browser.try_into().unwrap()) // TODO: try_into() is expensive
-                    .collect::<Vec<ListItem<Browser>>>();
-
-                ui_ref
-                    .borrow_mut()
-                    .prediction_set_state(&list.as_slice(), &duration_str)
-                    .expect("Failed setting predicted state for the prediction section");
-            } else {
-                ui_ref
-                    .borrow()
-                    .prediction_set_is_loading(false)
-                    .expect("Failed stopping loading state for predictions.");
-            }
-        });
+        if let Err(err) = ui.set_theme(&config.theme) {
+            log_error("Couldn't apply the theme", &err);
+        }
+        if let Err(err) = ui.set_window_placement(config.window.placement) {
+            log_error("Couldn't set where the window opens", &err);
+        }
+        ui.create(&title)
+            .unwrap_or_else(|err| exit_with_error(err));
+        if let Err(err) = ui.set_list_is_loading(true) {
+            log_error("Failed to set loading state for the list", &err);
+        }
     }
 
-    let list_items: Vec<ListItem<Browser>> = browsers
+    // all browsers are kept, including the hidden ones, so a config reload can show them without rediscovery.
+    // The list fills as the browsers are discovered, the actions follow them. The history comes
+    // from the statistics, there is none without them.
+    let actions = Action::ALL
         .iter()
-        .filter(|browser| config.browser_is_not_hidden(&browser.name, &browser.exe_path))
-        .filter_map(|item| item.try_into().ok())
+        .filter(|action| config.privacy.statistics || **action != Action::History)
+        .map(Action::list_item)
         .collect();
+    let all_items: Rc<RefCell<Vec<ListItem<Target>>>> = Rc::new(RefCell::new(actions));
 
     {
         let mut ui = ui_ref.borrow_mut();
-        let open_url_clone = Rc::clone(&target_url);
-        let ev_loop_proxy = event_loop.create_proxy();
         let statistics_ref = statistics_optional.clone();
-        let src_app_clone = src_app_opt.clone();
-
-        ui.set_list(&list_items)
-            .expect("Couldn't populate browsers in the UI.");
-        ui.set_url(&target_url)
-            .expect("Couldn't render URL in the UI.");
-        ui.on_browser_selected(move |uuid| {
-            let source = src_app_clone.clone().unwrap_or_default().exe_path;
-            list_items
+        let list_items = Rc::clone(&all_items);
+        let selected_session = Rc::clone(&session);
+        let prediction_token = Rc::clone(&prediction_token);
+
+        if let Err(err) = ui.set_warning(config_warning.as_deref()) {
+            log_error("Couldn't show the config warning", &err);
+        }
+        let chosen_action_target = Rc::clone(&chosen_action);
+        let reopened_entry_target = Rc::clone(&reopened_entry);
+        let shown_history = Rc::clone(&history_items);
+        let url_edit_target = Rc::clone(&edited_url);
+        ui.on_url_changed(move |url| *url_edit_target.borrow_mut() = Some(String::from(url)))
+            .unwrap_or_else(|err| exit_with_error(err));
+        ui.on_browser_selected(move |uuid, action| {
+            if let Some(token) = prediction_token.borrow().as_ref() {
+                // the choice is made, don't keep the statistics write waiting on the prediction
+                token.cancel();
+            }
+
+            let session = selected_session.borrow();
+            if action == SelectionAction::CopyUrls {
+                if let Err(err) = os::util::copy_to_clipboard(&session.urls.join("\n")) {
+                    crate::os::output_error_text(env!("CARGO_PKG_NAME"), err.report());
+                }
+                return true;
+            }
+
+            let Some(target) = list_items
+                .borrow()
                 .iter()
+                .chain(shown_history.borrow().iter())
                 .find(|item| item.uuid == uuid)
-                .and_then(|item| Some(item.state.as_ref()))
-                .and_then::<std::rc::Rc<Browser>, _>(|browser| {
-                    os::util::spawn_browser_process(
-                        &browser.exe_path,
-                        browser.arguments.clone(),
-                        &open_url_clone,
-                    );
-
-                    if let Some(stats) = statistics_ref.clone() {
-                        let browser_hash = browser.get_hash();
-                        stats.borrow_mut().save_choice(
-                            source,
-                            &open_url_clone,
-                            &browser_hash,
-                            &browser.exe_path,
-                            |res| res.expect("Failed to save choice in statistics."),
-                        );
+                .map(|item| Rc::clone(&item.state))
+            else {
+                return true;
+            };
+            match target.as_ref() {
+                Target::Browser(browser) => {
+                    if let Err(err) = open_in_browser(browser, action, &session.urls) {
+                        crate::os::output_error_text(env!("CARGO_PKG_NAME"), err.report());
+                        return true;
                     }
+                }
+                // browsing the history is no choice to learn from
+                Target::Action(Action::History) => {
+                    *chosen_action_target.borrow_mut() = Some(Action::History);
+                    return false;
+                }
+                // run by the event loop delegate, it has the config as last reloaded
+                Target::Action(chosen) => *chosen_action_target.borrow_mut() = Some(*chosen),
+                // the browser picked next opens it, that choice is the one recorded
+                Target::History(entry) => {
+                    *reopened_entry_target.borrow_mut() = Some(entry.clone());
+                    return false;
+                }
+            }
+
+            // a private window is meant to leave no history, the choice isn't kept either
+            let statistics_ref = statistics_ref.clone().filter(|_| action == SelectionAction::Open);
+            if let Some(stats) = statistics_ref {
+                let target_hash = target.get_hash();
+                let mut statistics = stats.borrow_mut();
+                if let Some(history_id) = session.corrects {
+                    statistics.correct_choice(history_id, &target_hash, |res| {
+                        if let Err(err) = res.as_ref() {
+                            log_error("Failed to correct the choice in statistics", err);
+                        }
+                    });
+                    return true;
+                }
+                // one history entry per URL so predictions keep working per domain
+                for url in session.urls.iter() {
+                    statistics.save_choice(
+                        session.source.exe_path.clone(),
+                        url,
+                        &target_hash,
+                        target.path(),
+                        |res| {
+                            if let Err(err) = res.as_ref() {
+                                log_error("Failed to save choice in statistics", err);
+                            }
+                        },
+                    );
+                }
+            }
 
-                    None
-                });
-            ev_loop_proxy.send_event(ui::ev_loop::UserEvent::Close).ok();
+            // the QR code shows in the selector until it is closed
+            !matches!(target.as_ref(), Target::Action(Action::QrCode))
         })
-        .expect("Cannot set on click event handler.");
+        .unwrap_or_else(|err| exit_with_error(err));
 
-        ui.set_main_window_visible(true);
+        // the daemon starts hidden, it shows for each request
+        if !daemon {
+            show_session(&mut *ui, &config, &all_items.borrow(), &session.borrow())
+                .unwrap_or_else(|err| exit_with_error(err));
+        }
     }
     // end of scope is needed as it drops ui, releases the mutable strong ref from ui_ref
     // to allow the UI to be borrowed in other places without panicking
 
+    let mut config_watcher = match conf::watcher::ConfigWatcher::new(&config_layers) {
+        Ok(watcher) => Some(watcher),
+        Err(err) => {
+            log_error("Config changes won't be applied until restart", &err);
+            None
+        }
+    };
+
     let worker = statistics_optional.clone();
-    event_loop.run(ui::ev_loop::make_runner(
+    UI::run(
         ui_ref.clone(),
+        daemon,
         move |control_flow| {
+            let list_completed = discovery.as_mut().is_some_and(|discovery| {
+                let mut ui = ui_ref.borrow_mut();
+                receive_discovered_browsers(discovery, &mut *ui, &config, &all_items)
+            });
+            if discovery.as_ref().is_some_and(|discovery| discovery.is_over()) {
+                discovery = None;
+            }
+            if list_completed {
+                if let Some(worker_ref) = &worker {
+                    let mut statistics = worker_ref.borrow_mut();
+                    update_selections(&mut statistics, &all_items.borrow());
+                    // predictions need all the browsers to pick from, the daemon has no request yet.
+                    // Predictions made from cached browsers are kept when the list is refreshed
+                    if !daemon && prediction_token.borrow().is_none() {
+                        let token = start_prediction(&mut statistics, &ui_ref, &all_items, &session.borrow());
+                        *prediction_token.borrow_mut() = Some(token);
+                    }
+                }
+            }
+
+            let config_changed = config_watcher.as_mut().is_some_and(|watcher| watcher.poll());
+            if config_changed {
+                let reloaded = conf::read_config(config_path.as_deref());
+                let mut ui = ui_ref.borrow_mut();
+                let warning = config_warning_text(&reloaded.diagnostics, &i18n::text("config-kept"));
+                if warning.is_none() {
+                    // swapped as a whole so nothing ever sees a half applied config
+                    config = Rc::new(reloaded.config);
+                    if let Err(err) = ui.set_theme(&config.theme) {
+                        log_error("Couldn't apply the theme after the config changed", &err);
+                    }
+                    if let Err(err) = ui.set_window_placement(config.window.placement) {
+                        log_error("Couldn't set where the window opens after the config changed", &err);
+                    }
+                    if let Err(err) = ui.set_list(&visible_list_items(&config, &all_items.borrow())) {
+                        log_error("Couldn't refresh the browser list after the config changed", &err);
+                    }
+                }
+                if let Err(err) = ui.set_warning(warning.as_deref()) {
+                    log_error("Couldn't show the config warning", &err);
+                }
+            }
+
+            // a link of the history replaces the URLs, the browsers are listed again to pick one for it
+            let reopened_url = reopened_entry.borrow_mut().take().map(|entry| {
+                let url = entry.url.clone().unwrap_or_default();
+                let mut session = session.borrow_mut();
+                session.urls = vec![url.clone()];
+                session.corrects = entry.id;
+                let ui = ui_ref.borrow();
+                if let Err(err) = ui.set_history(None).and_then(|_| ui.set_urls(&session.urls)) {
+                    log_error("Couldn't show the link picked from the history", &err);
+                }
+                url
+            });
+
+            // the edited URL is the one opened and recorded, the prediction is made again for it
+            let edited = edited_url.borrow_mut().take();
+            // an edited URL is a new link, the browser picked for it doesn't correct a history entry
+            if edited.is_some() {
+                session.borrow_mut().corrects = None;
+            }
+            if let Some(url) = edited.or(reopened_url) {
+                if let Some(first_url) = session.borrow_mut().urls.first_mut() {
+                    *first_url = url;
+                }
+                // the list may still be loading, the prediction picks from the browsers found by the time it ends
+                if let Some(worker_ref) = &worker {
+                    if let Some(token) = prediction_token.borrow_mut().take() {
+                        token.cancel();
+                    }
+                    let token = start_prediction(&mut worker_ref.borrow_mut(), &ui_ref, &all_items, &session.borrow());
+                    *prediction_token.borrow_mut() = Some(token);
+                }
+            }
+
+            if let Some(action) = chosen_action.borrow_mut().take() {
+                let session = session.borrow();
+                let result = match action {
+                    Action::QrCode => ui_ref.borrow().set_qr_code(session.urls.first().map(String::as_str)),
+                    Action::History => {
+                        if let Some(worker_ref) = &worker {
+                            show_history(&mut worker_ref.borrow_mut(), &ui_ref, &all_items, &history_items);
+                        }
+                        Ok(())
+                    }
+                    _ => action.run(&session.urls, &session.source, &config.read_later),
+                };
+                if let Err(err) = result {
+                    crate::os::output_error_text(env!("CARGO_PKG_NAME"), err.report());
+                }
+            }
+
+            // a request replaces the one shown, if the user didn't pick a browser for it yet
+            if let Some(ipc::Request::Open { urls, source_exe, source_window }) =
+                ipc_server.as_ref().and_then(|server| server.poll())
+            {
+                if let Some(token) = prediction_token.borrow_mut().take() {
+                    token.cancel();
+                }
+                let source = ActiveWindowInfo {
+                    window_name: source_window,
+                    exe_path: source_exe,
+                };
+                *session.borrow_mut() = Session::new(urls, source, &config);
+                if let Some(worker_ref) = &worker {
+                    let token = start_prediction(&mut worker_ref.borrow_mut(), &ui_ref, &all_items, &session.borrow());
+                    *prediction_token.borrow_mut() = Some(token);
+                }
+                if let Err(err) = show_session(&mut *ui_ref.borrow_mut(), &config, &all_items.borrow(), &session.borrow()) {
+                    log_error("Couldn't show the selector", &err);
+                }
+            }
+
             if let Some(worker_ref) = &worker {
                 let mut statistics = worker_ref.borrow_mut();
                 statistics.tick();
@@ -196,13 +732,6 @@ fn main() {
                     *control_flow = ControlFlow::Exit
                 }
             }
-
-            #[cfg(target_os = "windows")]
-            if *control_flow == ControlFlow::Exit {
-                // TODO: Investigate why the process hangs when returning control to winit
-                // or when existing gracefully with ExitProcess
-                os::terminate_current_process()
-            }
         },
-    ));
+    )
 }
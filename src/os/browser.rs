@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::actions::Target;
+use crate::ui::{Image, ListItem};
+
+/// The `Browser` data structure is an entry mapped to the
+/// a browser program installed on the user's OS. What determines
+/// the list of present browser is platform specific.
+///
+/// It only holds data, OS resources such as the icon are loaded by the UI when it needs them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Browser {
+    // The path to the executable binary or script that is the entry point
+    // of the browser program. This path is absolute and free of arguments.
+    pub exe_path: String,
+
+    // The arguments that should be passed when executing the browser binary
+    pub arguments: Vec<String>,
+
+    // User friendly browser program name, deducted from the executable metadata
+    // as defined by the program publisher
+    pub name: String,
+
+    // Path to the browser program icon/logo. On Linux it can also be the name of an icon
+    // to look up in the icon themes, as desktop entries give it
+    pub icon: String,
+
+    pub exe_exists: bool,
+    pub icon_exists: bool,
+    pub version: VersionInfo,
+}
+
+impl Browser { 
+    /// Identifies the browser in the statistics. On Linux launchers such as `flatpak` or `env` start
+    /// several browsers, so the arguments count too. The registry browsers of Windows are identified
+    /// by their executable alone, as their statistics always were.
+    pub fn get_hash(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.exe_path.hash(&mut hasher);
+        #[cfg(not(target_os = "windows"))]
+        self.arguments.hash(&mut hasher);
+        hasher.finish().to_string()
+    }
+
+    /// The entry showing the browser in the selector list, with the given icon.
+    pub fn list_item(&self, image: Image) -> ListItem<Target> {
+        ListItem {
+            title: self.version.product_name.clone(),
+            subtitle: vec![
+                self.version.product_version.clone(),
+                self.version.binary_type.to_string(),
+                self.version.company_name.clone(),
+                self.version.file_description.clone(),
+            ]
+            .into_iter()
+            .filter(|itm| !itm.is_empty())
+            .collect::<Vec<String>>()
+            .join(" | "),
+            image,
+            uuid: self.get_hash(),
+            mnemonic: None,
+            state: std::rc::Rc::new(Target::Browser(self.clone())),
+        }
+    }
+
+    /// The arguments that open a private window, found from the executable name of the known
+    /// browsers. `None` when the browser isn't known to have such a mode.
+    pub fn private_arguments(&self) -> Option<&'static [&'static str]> {
+        let file_name = std::path::Path::new(&self.exe_path)
+            .file_stem()?
+            .to_string_lossy()
+            .to_lowercase();
+
+        match file_name.as_str() {
+            "msedge" | "microsoft-edge" | "microsoft-edge-stable" => Some(&["--inprivate"]),
+            "firefox" | "firefox-esr" | "librewolf" | "waterfox" => Some(&["--private-window"]),
+            "opera" => Some(&["--private"]),
+            "chrome" | "google-chrome" | "google-chrome-stable" | "chromium" | "chromium-browser"
+            | "brave" | "brave-browser" | "vivaldi" | "vivaldi-stable" => Some(&["--incognito"]),
+            _ => None,
+        }
+    }
+
+    /// The attributes the config rules match on.
+    pub fn match_fields(&self) -> crate::conf::BrowserFields<'_> {
+        crate::conf::BrowserFields {
+            name: &self.name,
+            path: &self.exe_path,
+            company: &self.version.company_name,
+            version: &self.version.product_version,
+            description: &self.version.file_description,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryType {
+    Bits32,
+    Bits64,
+    #[default]
+    None,
+}
+
+impl std::fmt::Display for BinaryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                BinaryType::Bits32 => "32 bits",
+                BinaryType::Bits64 => "64 bits",
+                _ => "",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub company_name: String,
+    pub file_description: String,
+    pub product_version: String,
+    pub product_name: String,
+    pub binary_type: BinaryType,
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+use std::sync::mpsc;
+
+use crate::data::browser_cache::BrowserCache;
+use crate::data::thread_worker::ThreadWorker;
+use crate::error::BSError;
+use crate::os::sys_browsers::{self, Browser};
+use crate::os::IconPixels;
+
+/// A browser with everything the selector needs to show it.
+pub struct DiscoveredBrowser {
+    pub browser: Browser,
+    /// `None` when the icon couldn't be read, the browser is listed without it
+    pub icon: Option<IconPixels>,
+}
+
+pub enum DiscoveryEvent {
+    Found(DiscoveredBrowser),
+    /// Listing the browsers failed, no other event follows
+    Failed(BSError),
+    /// All the browsers found were sent
+    Finished,
+    /// The browsers sent before came from the cache and were outdated, these replace them
+    Refreshed(Vec<DiscoveredBrowser>),
+}
+
+/// Finds the installed browsers and reads their icons on a [ThreadWorker] so the selector can
+/// show right away. Browsers are sent one by one as their details are read, [`poll`](BrowserDiscovery::poll)
+/// hands them to the event loop.
+///
+/// When the browsers from the last run are cached they are sent first, all at once. The cache is
+/// then checked against the system and rebuilt in the background if anything changed.
+pub struct BrowserDiscovery {
+    worker: ThreadWorker,
+    receiver: mpsc::Receiver<DiscoveryEvent>,
+    is_over: bool,
+}
+
+impl BrowserDiscovery {
+    pub fn start() -> Self {
+        let worker = ThreadWorker::new(|| {});
+        let (sender, receiver) = mpsc::channel();
+        // the result of the task is the events sent, the receiver for its return value isn't needed
+        let _ = worker.run(move || discover(&sender));
+
+        Self {
+            worker,
+            receiver,
+            is_over: false,
+        }
+    }
+
+    /// The next event, if any. The worker thread is stopped once discovery is over.
+    pub fn poll(&mut self) -> Option<DiscoveryEvent> {
+        match self.receiver.try_recv() {
+            Ok(event) => Some(event),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                if !self.is_over {
+                    self.is_over = true;
+                    self.worker.stop();
+                }
+                None
+            }
+        }
+    }
+
+    /// True when no event will come anymore.
+    pub fn is_over(&self) -> bool {
+        self.is_over
+    }
+}
+
+fn discover(sender: &mpsc::Sender<DiscoveryEvent>) {
+    let cache_path = BrowserCache::default_path()
+        .map_err(|err| crate::log_error("The browsers won't be cached", &err))
+        .ok();
+    let cache = cache_path.as_deref().and_then(|path| {
+        BrowserCache::read(path)
+            .map_err(|err| crate::log_error("Ignoring the browser cache", &err))
+            .ok()
+            .flatten()
+            .map(|cache| (path, cache))
+    });
+
+    if let Some((path, cache)) = &cache {
+        for (browser, icon) in cache.browsers(path) {
+            let _ = sender.send(DiscoveryEvent::Found(DiscoveredBrowser { browser, icon }));
+        }
+        let _ = sender.send(DiscoveryEvent::Finished);
+    }
+
+    let sources = match sys_browsers::find_system_browsers() {
+        Ok(browsers) => browsers,
+        Err(err) if cache.is_some() => {
+            crate::log_error("Cannot check the cached browsers", &err);
+            return;
+        }
+        Err(err) => {
+            let _ = sender.send(DiscoveryEvent::Failed(err));
+            return;
+        }
+    };
+    if let Some((_, cache)) = &cache {
+        if cache.is_valid_for(&sources) {
+            return;
+        }
+    }
+
+    let mut browsers = Vec::with_capacity(sources.len());
+    for source in sources.iter() {
+        let (browser, icon) = cache
+            .as_ref()
+            .and_then(|(path, cache)| cache.lookup(path, &source.exe_path))
+            // what the registry says is current even when the executable didn't change
+            .map(|(cached, icon)| (Browser { version: cached.version, ..source.clone() }, icon))
+            .unwrap_or_else(|| read_browser(source.clone()));
+        if cache.is_none() {
+            // nothing shows yet, the list fills as the browsers come
+            let found = DiscoveredBrowser {
+                browser: browser.clone(),
+                icon: icon.clone(),
+            };
+            if sender.send(DiscoveryEvent::Found(found)).is_err() {
+                return; // nobody listens anymore
+            }
+        }
+        browsers.push((browser, icon));
+    }
+
+    if let Some(path) = cache_path.as_deref() {
+        write_cache(path, &sources, &browsers);
+    }
+    let _ = sender.send(match cache {
+        Some(_) => DiscoveryEvent::Refreshed(
+            browsers
+                .into_iter()
+                .map(|(browser, icon)| DiscoveredBrowser { browser, icon })
+                .collect(),
+        ),
+        None => DiscoveryEvent::Finished,
+    });
+}
+
+fn read_browser(mut browser: Browser) -> (Browser, Option<IconPixels>) {
+    sys_browsers::read_browser_details(&mut browser);
+    let icon = sys_browsers::read_browser_icon(&browser)
+        .map_err(|err| crate::log_error(&format!("Cannot read the icon of {}", browser.exe_path), &err))
+        .ok();
+
+    (browser, icon)
+}
+
+fn write_cache(path: &Path, sources: &[Browser], browsers: &[(Browser, Option<IconPixels>)]) {
+    if let Err(err) = BrowserCache::write(path, sources, browsers) {
+        crate::log_error("Failed writing the browser cache", &err);
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{BSError, BSResult};
+use crate::os::IconPixels;
+
+/// Every theme falls back to it, apps install their icons there.
+const FALLBACK_THEME: &str = "hicolor";
+
+/// The sizes looked for, the ones closest to how big the list shows icons come first.
+const ICON_SIZES: &[&str] = &["48x48", "64x64", "32x32", "96x96", "128x128", "256x256", "24x24"];
+
+/// Finds the file of the icon `name` as given by a desktop entry: either a path or a name to look
+/// up in the user's icon theme, then in the fallback theme and in `/usr/share/pixmaps`.
+///
+/// This is a simplified take on the
+/// [Icon Theme Specification](https://specifications.freedesktop.org/icon-theme-spec/latest/):
+/// only the usual `<size>/apps` layout is searched, theme inheritance isn't followed and only
+/// PNG icons are found as there is no SVG renderer.
+pub fn find_icon(name: &str) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+    if Path::new(name).is_absolute() {
+        return Path::new(name).is_file().then(|| PathBuf::from(name));
+    }
+
+    let file_name = format!("{}.png", name);
+    let base_dirs = icon_base_dirs();
+    let themes = user_icon_theme()
+        .into_iter()
+        .chain(std::iter::once(String::from(FALLBACK_THEME)));
+    for theme in themes {
+        for size in ICON_SIZES {
+            for base_dir in base_dirs.iter() {
+                let path = base_dir.join(&theme).join(size).join("apps").join(&file_name);
+                if path.is_file() {
+                    return Some(path);
+                }
+            }
+        }
+    }
+
+    let pixmap = Path::new("/usr/share/pixmaps").join(&file_name);
+    pixmap.is_file().then_some(pixmap)
+}
+
+/// Reads the PNG icon at `path`, whatever its color type.
+pub fn read_icon(path: &Path) -> BSResult<IconPixels> {
+    let file = std::fs::File::open(path).map_err(|err| BSError::io(path, err))?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    // palettes and 16 bit channels become plain 8 bit ones
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| icon_error(path, err))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|err| icon_error(path, err))?;
+    buffer.truncate(info.buffer_size());
+
+    let bgra = match info.color_type {
+        png::ColorType::Rgba => buffer
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect(),
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&gray| [gray, gray, gray, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(icon_error(path, "the palette wasn't expanded"));
+        }
+    };
+
+    Ok(IconPixels {
+        width: info.width as i32,
+        height: info.height as i32,
+        bgra,
+    })
+}
+
+/// Where icon themes are installed, in the order they are searched.
+fn icon_base_dirs() -> Vec<PathBuf> {
+    let home_icons = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".icons"));
+    let data_dirs = crate::os::util::xdg_data_dirs()
+        .into_iter()
+        .map(|data_dir| data_dir.join("icons"));
+
+    home_icons.into_iter().chain(data_dirs).collect()
+}
+
+/// The icon theme picked in the GTK settings, which most desktops keep in sync with their own.
+fn user_icon_theme() -> Option<String> {
+    let settings_path = crate::os::util::xdg_dir("XDG_CONFIG_HOME", ".config")
+        .ok()?
+        .join("gtk-3.0")
+        .join("settings.ini");
+    let settings = std::fs::read_to_string(settings_path).ok()?;
+
+    settings.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "gtk-icon-theme-name").then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn icon_error(path: &Path, err: impl std::fmt::Display) -> BSError {
+    BSError::new(&format!("Cannot read the icon {}: {}", path.display(), err))
+}
@@ -0,0 +1,4 @@
+pub mod icons;
+pub mod registration;
+pub mod sys_browsers;
+pub mod util;
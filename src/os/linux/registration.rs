@@ -0,0 +1,48 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{BSError, BSResult};
+
+/// File name of the desktop entry written by [register], also its desktop file ID.
+pub const DESKTOP_FILE_NAME: &str = "browsor.desktop";
+
+/// How to finish what [register] started, printed after it succeeds.
+pub const SET_DEFAULT_HINT: &str =
+    "Set it as default with `xdg-settings set default-web-browser browsor.desktop`.";
+
+/// Registers `exe_path` as a browser for the current user by writing a desktop entry that handles
+/// web links to `$XDG_DATA_HOME/applications`, where the desktop settings look for browsers.
+pub fn register(exe_path: &Path) -> BSResult<()> {
+    let entry_path = desktop_entry_path()?;
+    let entry = format!(
+        "[Desktop Entry]
+Type=Application
+Name=Browsor
+Comment=Shows a browser selector
+Exec=\"{}\" %U
+Terminal=false
+Categories=Network;WebBrowser;
+MimeType=text/html;application/xhtml+xml;x-scheme-handler/http;x-scheme-handler/https;x-scheme-handler/ftp;
+",
+        exe_path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"")
+    );
+
+    if let Some(applications_dir) = entry_path.parent() {
+        std::fs::create_dir_all(applications_dir).map_err(|err| BSError::io(applications_dir, err))?;
+    }
+    std::fs::write(&entry_path, entry).map_err(|err| BSError::io(&entry_path, err))
+}
+
+/// Removes the desktop entry written by [register]. An entry that is already gone is not an error.
+pub fn unregister() -> BSResult<()> {
+    let entry_path = desktop_entry_path()?;
+    match std::fs::remove_file(&entry_path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(BSError::io(&entry_path, err)),
+        _ => Ok(()),
+    }
+}
+
+fn desktop_entry_path() -> BSResult<PathBuf> {
+    Ok(crate::os::util::xdg_dir("XDG_DATA_HOME", ".local/share")?
+        .join("applications")
+        .join(DESKTOP_FILE_NAME))
+}
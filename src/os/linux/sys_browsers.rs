@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{BSError, BSResult as Result};
+use crate::os::IconPixels;
+pub use crate::os::browser::{Browser, VersionInfo};
+
+/// The desktop entries of apps that open web links declare one of these.
+const URL_MIME_TYPES: &[&str] = &["x-scheme-handler/http", "x-scheme-handler/https"];
+
+pub fn read_system_browsers_sync() -> Result<Vec<Browser>> {
+    let mut list = find_system_browsers()?;
+    for browser in list.iter_mut() {
+        read_browser_details(browser);
+    }
+    Ok(list)
+}
+
+/// Lists the browsers from the desktop entries of the installed apps, see the
+/// [Desktop Entry Specification](https://specifications.freedesktop.org/desktop-entry-spec/latest/).
+/// An entry in the user's data dir hides the system one with the same file name.
+pub fn find_system_browsers() -> Result<Vec<Browser>> {
+    let mut entry_paths: Vec<(String, PathBuf)> = Vec::new();
+    for data_dir in crate::os::util::xdg_data_dirs() {
+        let applications_dir = data_dir.join("applications");
+        let Ok(dir_entries) = std::fs::read_dir(&applications_dir) else { continue; };
+        let mut paths: Vec<PathBuf> = dir_entries
+            .filter_map(|dir_entry| dir_entry.ok().map(|dir_entry| dir_entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "desktop"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let is_hidden = entry_paths.iter().any(|(known_name, _)| *known_name == file_name);
+            // our own entry from `register`, the selector shouldn't list itself
+            if !is_hidden && file_name != crate::os::registration::DESKTOP_FILE_NAME {
+                entry_paths.push((file_name, path));
+            }
+        }
+    }
+    if entry_paths.is_empty() {
+        return Err(BSError::platform("No desktop entries found, is XDG_DATA_DIRS set?"));
+    }
+
+    let mut list: Vec<Browser> = Vec::new();
+    for (_, path) in entry_paths {
+        match read_browser_from_desktop_entry(&path) {
+            // the same command can have several entries, ie. one kept from an older install. Those
+            // starting the same launcher with other arguments, such as `flatpak run <app>`, differ
+            Ok(Some(browser)) if !list.iter().any(|known| known.get_hash() == browser.get_hash()) => {
+                list.push(browser)
+            }
+            Ok(_) => (),
+            Err(err) => crate::log_error("Cannot read the browser entry", &err),
+        }
+    }
+    Ok(list)
+}
+
+/// Looks up the browser icon in the icon themes, the desktop entry gives everything else.
+pub fn read_browser_details(browser: &mut Browser) {
+    browser.icon_exists = super::icons::find_icon(&browser.icon).is_some();
+}
+
+/// Reads the icon the desktop entry names, as the app menus show it.
+pub fn read_browser_icon(browser: &Browser) -> Result<IconPixels> {
+    let path = super::icons::find_icon(&browser.icon)
+        .ok_or_else(|| BSError::new(&format!("No PNG icon named {} found", browser.icon)))?;
+
+    super::icons::read_icon(&path)
+}
+
+/// `None` when the entry isn't for a browser or its program isn't installed.
+fn read_browser_from_desktop_entry(path: &Path) -> Result<Option<Browser>> {
+    let text = std::fs::read_to_string(path).map_err(|err| BSError::io(path, err))?;
+    let fields = parse_desktop_entry(&text);
+    let field = |key: &str| fields.get(key).map(String::as_str).unwrap_or_default();
+
+    let is_url_handler = field("MimeType")
+        .split(';')
+        .any(|mime_type| URL_MIME_TYPES.contains(&mime_type));
+    let is_shown = field("Hidden") != "true" && field("NoDisplay") != "true";
+    if field("Type") != "Application" || !is_url_handler || !is_shown {
+        return Ok(None);
+    }
+
+    let mut command = split_exec(field("Exec"), field("Name"), field("Icon"), path);
+    if command.is_empty() {
+        return Ok(None);
+    }
+    let Some(exe_path) = find_program(&command.remove(0)) else { return Ok(None); };
+
+    let description = match field("Comment") {
+        "" => field("GenericName"),
+        comment => comment,
+    };
+    Ok(Some(Browser {
+        exe_path: exe_path.to_string_lossy().into_owned(),
+        arguments: command,
+        name: String::from(field("Name")),
+        icon: String::from(field("Icon")),
+        exe_exists: true,
+        version: VersionInfo {
+            product_name: String::from(field("Name")),
+            file_description: String::from(description),
+            ..VersionInfo::default()
+        },
+        ..Browser::default()
+    }))
+}
+
+/// The keys of the `[Desktop Entry]` group with their values unescaped. Localized keys such as
+/// `Name[fr]` are skipped, the untranslated value is shown.
+fn parse_desktop_entry(text: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut in_main_group = false;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            fields
+                .entry(String::from(key.trim()))
+                .or_insert_with(|| unescape_value(value.trim()));
+        }
+    }
+
+    fields
+}
+
+fn unescape_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            // other escapes, such as the quoting ones of `Exec`, are kept for the next step
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Splits the `Exec` command line into the program and its arguments, dropping the field codes
+/// for the files and URLs to open since the URLs are appended when the browser is started.
+fn split_exec(exec: &str, name: &str, icon: &str, entry_path: &Path) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut is_quoted = false;
+    let mut in_word = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                is_quoted = !is_quoted;
+                in_word = true;
+            }
+            '\\' if is_quoted => word.extend(chars.next()),
+            ' ' | '\t' if !is_quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+
+    let mut arguments = Vec::with_capacity(words.len());
+    for word in words {
+        match word.as_str() {
+            "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => (),
+            "%i" if !icon.is_empty() => arguments.extend([String::from("--icon"), String::from(icon)]),
+            "%i" => (),
+            _ => arguments.push(
+                word.replace("%c", name)
+                    .replace("%k", &entry_path.to_string_lossy())
+                    .replace("%%", "%"),
+            ),
+        }
+    }
+
+    arguments
+}
+
+/// The absolute path of `program`, looked up in `PATH` when it is only a name.
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        return Path::new(program).is_file().then(|| PathBuf::from(program));
+    }
+
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|path| path.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_exec, Browser};
+    use std::path::Path;
+
+    fn browser(exec: &str) -> Browser {
+        let mut command = split_exec(exec, "Browser", "browser", Path::new("browser.desktop"));
+        Browser {
+            exe_path: command.remove(0),
+            arguments: command,
+            ..Browser::default()
+        }
+    }
+
+    #[test]
+    fn exec_field_codes_are_dropped() {
+        let firefox = browser(r#"/usr/bin/flatpak run --branch=stable "org.mozilla.firefox" %U"#);
+        assert_eq!(firefox.exe_path, "/usr/bin/flatpak");
+        assert_eq!(firefox.arguments, ["run", "--branch=stable", "org.mozilla.firefox"]);
+    }
+
+    #[test]
+    fn launchers_of_several_browsers_are_told_apart() {
+        let firefox = browser("/usr/bin/flatpak run org.mozilla.firefox %u");
+        let chromium = browser("/usr/bin/flatpak run org.chromium.Chromium %U");
+        assert_ne!(firefox.get_hash(), chromium.get_hash());
+        assert_eq!(firefox.get_hash(), browser("/usr/bin/flatpak run org.mozilla.firefox").get_hash());
+    }
+}
@@ -0,0 +1,224 @@
+use std::ffi::CString;
+use std::os::raw::{c_int, c_uchar, c_ulong};
+use std::path::PathBuf;
+
+use x11_dl::xlib;
+
+/// Connection to the X server for what winit doesn't tell, such as the window in the foreground.
+/// Under Wayland it goes through XWayland, which only knows about X11 windows.
+struct XConnection {
+    xlib: xlib::Xlib,
+    display: *mut xlib::Display,
+}
+
+impl XConnection {
+    /// `None` when there is no X server to connect to or libX11 is missing.
+    fn open() -> Option<Self> {
+        let xlib = xlib::Xlib::open().ok()?;
+        let display = unsafe { (xlib.XOpenDisplay)(std::ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+
+        Some(XConnection { xlib, display })
+    }
+
+    fn root_window(&self) -> xlib::Window {
+        unsafe { (self.xlib.XDefaultRootWindow)(self.display) }
+    }
+
+    /// The raw bytes of the window property `name` with the size of its items: 8, 16 or 32 bits.
+    /// Items of 32 bits come as `c_ulong` as Xlib stores them.
+    fn read_property(&self, window: xlib::Window, name: &str) -> Option<(c_int, Vec<u8>)> {
+        let atom_name = CString::new(name).ok()?;
+        unsafe {
+            let atom = (self.xlib.XInternAtom)(self.display, atom_name.as_ptr(), xlib::True);
+            if atom == 0 {
+                return None;
+            }
+
+            let mut actual_type: xlib::Atom = 0;
+            let mut format: c_int = 0;
+            let mut item_count: c_ulong = 0;
+            let mut bytes_after: c_ulong = 0;
+            let mut data: *mut c_uchar = std::ptr::null_mut();
+            let status = (self.xlib.XGetWindowProperty)(
+                self.display,
+                window,
+                atom,
+                0,
+                1024,
+                xlib::False,
+                xlib::AnyPropertyType as xlib::Atom,
+                &mut actual_type,
+                &mut format,
+                &mut item_count,
+                &mut bytes_after,
+                &mut data,
+            );
+            if data.is_null() {
+                return None;
+            }
+
+            let item_size = match format {
+                32 => std::mem::size_of::<c_ulong>(),
+                format => format as usize / 8,
+            };
+            let bytes = std::slice::from_raw_parts(data, item_count as usize * item_size).to_vec();
+            (self.xlib.XFree)(data as *mut _);
+
+            (status == xlib::Success as c_int && item_count > 0).then_some((format, bytes))
+        }
+    }
+
+    /// Reads a property holding a window, a PID or any other single 32 bit value.
+    fn read_u32_property(&self, window: xlib::Window, name: &str) -> Option<c_ulong> {
+        match self.read_property(window, name)? {
+            (32, bytes) => bytes
+                .get(..std::mem::size_of::<c_ulong>())
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(c_ulong::from_ne_bytes),
+            _ => None,
+        }
+    }
+
+    fn read_string_property(&self, window: xlib::Window, name: &str) -> Option<String> {
+        match self.read_property(window, name)? {
+            (8, bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for XConnection {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xlib.XCloseDisplay)(self.display);
+        }
+    }
+}
+
+/// Nothing to attach to, the process keeps the terminal it was started from.
+pub fn attach_parent_console() {}
+
+pub fn output_panic_text(text: String) {
+    output_error_text("Panic!", text);
+}
+
+/// Prints the given `text` to stderr, desktop launchers usually keep it in the session log.
+pub fn output_error_text(title: &str, text: String) {
+    eprintln!("{}: {}", title, text);
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ActiveWindowInfo {
+    pub window_name: Option<String>,
+    pub exe_path: Option<PathBuf>,
+}
+
+/// Asks the window manager for the window in the foreground as per the
+/// [EWMH](https://specifications.freedesktop.org/wm-spec/latest/) properties. Without an X server
+/// or a window manager setting them, nothing is known about it.
+pub fn get_active_window_info() -> ActiveWindowInfo {
+    let Some(connection) = XConnection::open() else { return ActiveWindowInfo::default(); };
+    let Some(window) = connection.read_u32_property(connection.root_window(), "_NET_ACTIVE_WINDOW") else {
+        return ActiveWindowInfo::default();
+    };
+
+    let window_name = connection
+        .read_string_property(window, "_NET_WM_NAME")
+        .or_else(|| connection.read_string_property(window, "WM_NAME"));
+    let exe_path = connection
+        .read_u32_property(window, "_NET_WM_PID")
+        .and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok());
+
+    ActiveWindowInfo {
+        window_name,
+        exe_path,
+    }
+}
+
+/// Where the mouse cursor is on the whole desktop, `None` without an X server to ask.
+pub fn get_cursor_position() -> Option<(i32, i32)> {
+    let connection = XConnection::open()?;
+    let mut root_return: xlib::Window = 0;
+    let mut child_return: xlib::Window = 0;
+    let (mut root_x, mut root_y, mut window_x, mut window_y) = (0, 0, 0, 0);
+    let mut mask = 0;
+    let is_on_screen = unsafe {
+        (connection.xlib.XQueryPointer)(
+            connection.display,
+            connection.root_window(),
+            &mut root_return,
+            &mut child_return,
+            &mut root_x,
+            &mut root_y,
+            &mut window_x,
+            &mut window_y,
+            &mut mask,
+        )
+    };
+
+    (is_on_screen == xlib::True).then_some((root_x, root_y))
+}
+
+/// The part of the desktop the panels leave to windows, as x, y, width and height, from the
+/// `_NET_WORKAREA` of the current desktop. It spans all monitors, the caller keeps the part on the
+/// monitor it is about.
+pub fn get_work_area(_x: i32, _y: i32) -> Option<(i32, i32, u32, u32)> {
+    let connection = XConnection::open()?;
+    let root = connection.root_window();
+    let desktop = connection.read_u32_property(root, "_NET_CURRENT_DESKTOP").unwrap_or(0) as usize;
+    let (32, bytes) = connection.read_property(root, "_NET_WORKAREA")? else { return None; };
+
+    // x, y, width and height for each desktop
+    let values: Vec<c_ulong> = bytes
+        .chunks_exact(std::mem::size_of::<c_ulong>())
+        .filter_map(|bytes| bytes.try_into().ok().map(c_ulong::from_ne_bytes))
+        .collect();
+    let [x, y, width, height] = values.get(desktop * 4..desktop * 4 + 4)? else { return None; };
+
+    Some((*x as i32, *y as i32, *width as u32, *height as u32))
+}
+
+pub fn terminate_current_process() {
+    std::process::exit(0);
+}
+
+/// The languages the user wants messages in, most preferred first, as BCP 47 tags such as `de-AT`.
+/// Read from the locale environment variables as gettext does: `LANGUAGE` is a list of
+/// preferences, honoured unless the locale is `C`, then `LC_ALL`, `LC_MESSAGES` and `LANG`.
+pub fn get_ui_languages() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty());
+    let is_posix = locale.as_deref().is_none_or(|locale| locale == "C" || locale == "POSIX");
+    let preferences = std::env::var("LANGUAGE").ok().filter(|_| !is_posix).unwrap_or_default();
+
+    preferences
+        .split(':')
+        .chain(locale.as_deref())
+        .filter(|locale| !locale.is_empty() && *locale != "C" && *locale != "POSIX")
+        // ie. de_AT.UTF-8@euro, the encoding and the modifier don't matter for the language
+        .map(|locale| locale.split(['.', '@']).next().unwrap_or_default().replace('_', "-"))
+        .collect()
+}
+
+/// Whether the desktop asks apps for dark colors, `None` when it doesn't tell. Read from the GTK
+/// theme forced for the session, or else the GNOME setting most desktops follow.
+pub fn prefers_dark_mode() -> Option<bool> {
+    if let Some(gtk_theme) = std::env::var("GTK_THEME").ok().filter(|theme| !theme.is_empty()) {
+        return Some(gtk_theme.to_lowercase().contains("dark"));
+    }
+
+    let output = std::process::Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+        .output()
+        .ok()?;
+    match String::from_utf8_lossy(&output.stdout).trim().trim_matches('\'') {
+        "prefer-dark" => Some(true),
+        "prefer-light" | "default" => Some(false),
+        _ => None,
+    }
+}
@@ -1,8 +1,23 @@
+mod browser;
 pub mod util;
 
+pub use util::IconPixels;
+
 #[cfg(target_os = "windows")]
 mod win;
 #[cfg(target_os = "windows")]
 pub use win::util::*;
 #[cfg(target_os = "windows")]
 pub use win::sys_browsers;
+#[cfg(target_os = "windows")]
+pub use win::registration;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::util::*;
+#[cfg(target_os = "linux")]
+pub use linux::{registration, sys_browsers};
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+pub mod discovery;
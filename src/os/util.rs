@@ -1,13 +1,116 @@
-pub fn spawn_browser_process(exe_path: &String, args: Vec<String>, url: &str) {
+use crate::error::{BSError, BSResult};
+use std::path::PathBuf;
+
+/// The arguments the browser is started with to open `urls`, browsers open each URL
+/// given on their command line in its own tab.
+pub fn browser_command_arguments(args: Vec<String>, urls: &[String]) -> Vec<String> {
     let mut command_arguments = args;
-    command_arguments.push(String::from(url));
+    command_arguments.extend(urls.iter().cloned());
+
+    command_arguments
+}
 
+pub fn spawn_browser_process(exe_path: &String, args: Vec<String>, urls: &[String]) -> BSResult<()> {
     std::process::Command::new(exe_path)
-        .args(command_arguments)
+        .args(browser_command_arguments(args, urls))
         .spawn()
-        .expect(
-            format!("Couldn't run browser program at {}", exe_path)
-                .to_owned()
-                .as_str(),
-        );
+        .map_err(|err| BSError::launch(exe_path, err))?;
+
+    Ok(())
+}
+
+/// Puts `text` on the system clipboard.
+pub fn copy_to_clipboard(text: &str) -> BSResult<()> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|err| BSError::platform(&format!("Can't open the clipboard: {}", err)))?;
+
+    // X11 and Wayland serve the text from the process that copied it, give the clipboard
+    // manager time to take it over as the process exits right after
+    #[cfg(target_os = "linux")]
+    let result = {
+        use arboard::SetExtLinux;
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(500);
+        clipboard.set().wait_until(deadline).text(text)
+    };
+    #[cfg(not(target_os = "linux"))]
+    let result = clipboard.set_text(text);
+
+    result.map_err(|err| BSError::platform(&format!("Can't copy to the clipboard: {}", err)))
+}
+
+/// The pixels of an icon, 4 bytes per pixel in BGRA order with straight alpha, rows top to bottom.
+/// Unlike the OS handles they come from, they can be sent across threads.
+#[derive(Debug, Clone, Default)]
+pub struct IconPixels {
+    pub width: i32,
+    pub height: i32,
+    pub bgra: Vec<u8>,
+}
+
+#[cfg(target_os = "windows")]
+const APP_DIR_NAME: &str = "Browsor";
+#[cfg(not(target_os = "windows"))]
+const APP_DIR_NAME: &str = "browsor";
+
+/// Directory for the config file of the current user.
+/// `%APPDATA%\Browsor` on Windows, `$XDG_CONFIG_HOME/browsor` elsewhere.
+pub fn get_user_config_dir() -> BSResult<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let os_dir = crate::os::get_os_user_config_dir()?;
+    #[cfg(not(target_os = "windows"))]
+    let os_dir = xdg_dir("XDG_CONFIG_HOME", ".config")?;
+
+    Ok(os_dir.join(APP_DIR_NAME))
+}
+
+/// Directory for the config file shared by all users of the machine.
+/// `%ProgramData%\Browsor` on Windows, `/etc/browsor` elsewhere.
+pub fn get_system_config_dir() -> BSResult<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let os_dir = crate::os::get_os_system_config_dir()?;
+    #[cfg(not(target_os = "windows"))]
+    let os_dir = PathBuf::from("/etc");
+
+    Ok(os_dir.join(APP_DIR_NAME))
+}
+
+/// Directory for files the app writes such as the statistics DB and logs, created if missing.
+/// `%LOCALAPPDATA%\Browsor` on Windows, `$XDG_DATA_HOME/browsor` elsewhere.
+pub fn get_user_data_dir() -> BSResult<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let os_dir = crate::os::get_os_user_data_dir()?;
+    #[cfg(not(target_os = "windows"))]
+    let os_dir = xdg_dir("XDG_DATA_HOME", ".local/share")?;
+
+    let data_dir = os_dir.join(APP_DIR_NAME);
+    std::fs::create_dir_all(&data_dir).map_err(|err| BSError::io(&data_dir, err))?;
+
+    Ok(data_dir)
+}
+
+/// Reads the XDG base directory from `env_name` falling back to `$HOME/<home_fallback>`
+/// as per the [XDG Base Directory Specification](https://specifications.freedesktop.org/basedir-spec/latest/).
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn xdg_dir(env_name: &str, home_fallback: &str) -> BSResult<PathBuf> {
+    match std::env::var_os(env_name) {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(home_fallback))
+            .ok_or_else(|| BSError::platform(&format!("Neither {} nor HOME are set.", env_name))),
+    }
+}
+
+/// The directories shared data such as desktop entries and icons is read from, most important first:
+/// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`.
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn xdg_data_dirs() -> Vec<PathBuf> {
+    let data_dirs = match std::env::var("XDG_DATA_DIRS") {
+        Ok(dirs) if !dirs.is_empty() => dirs,
+        _ => String::from("/usr/local/share:/usr/share"),
+    };
+
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+        .into_iter()
+        .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .collect()
 }
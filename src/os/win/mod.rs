@@ -1,2 +1,3 @@
+pub mod registration;
 pub mod sys_browsers;
 pub mod util;
\ No newline at end of file
@@ -0,0 +1,115 @@
+use std::path::Path;
+use winreg::enums::{HKEY_CURRENT_USER, KEY_SET_VALUE};
+use winreg::RegKey;
+
+use crate::error::{BSError, BSResult};
+
+const APP_NAME: &str = "Browsor";
+const DESCRIPTION: &str = "Shows a browser selector";
+const CLASS_KEY: &str = "Software\\Classes\\Browsor";
+const CLIENT_KEY: &str = "Software\\Clients\\StartMenuInternet\\Browsor";
+const REGISTERED_APPLICATIONS_KEY: &str = "Software\\RegisteredApplications";
+const FILE_EXTENSIONS: &[&str] = &[".html", ".htm", ".xht", ".xhtml"];
+const URL_SCHEMES: &[&str] = &["ftp", "http", "https"];
+
+/// How to finish what [register] started, printed after it succeeds.
+pub const SET_DEFAULT_HINT: &str = "Set it as default in Settings > Apps > Default apps.";
+
+/// Registers `exe_path` as a browser for the current user, so that it can be picked as the
+/// default browser in the Windows settings. Writes the same keys as `scripts/windows/setup.ps1`
+/// but in `HKEY_CURRENT_USER`, which does not need administrator rights.
+pub fn register(exe_path: &Path) -> BSResult<()> {
+    let exe_path = exe_path.to_string_lossy();
+    let icon = format!("{},0", exe_path);
+    let open_command = format!("\"{}\" \"%1\"", exe_path);
+    let capabilities_key = format!("{}\\Capabilities", CLIENT_KEY);
+
+    set_values(
+        &format!("{}\\Application", CLASS_KEY),
+        &[
+            ("AppUserModelId", APP_NAME),
+            ("ApplicationIcon", &icon),
+            ("ApplicationName", APP_NAME),
+            ("ApplicationDescription", DESCRIPTION),
+        ],
+    )?;
+    set_values(&format!("{}\\DefaultIcon", CLASS_KEY), &[("", &icon)])?;
+    set_values(&format!("{}\\shell\\open\\command", CLASS_KEY), &[("", &open_command)])?;
+
+    set_values(CLIENT_KEY, &[("", APP_NAME)])?;
+    set_values(&format!("{}\\DefaultIcon", CLIENT_KEY), &[("", &icon)])?;
+    set_values(&format!("{}\\shell\\open\\command", CLIENT_KEY), &[("", &open_command)])?;
+    set_values(
+        &capabilities_key,
+        &[
+            ("ApplicationDescription", DESCRIPTION),
+            ("ApplicationName", APP_NAME),
+            ("ApplicationIcon", &icon),
+        ],
+    )?;
+    let file_associations: Vec<(&str, &str)> =
+        FILE_EXTENSIONS.iter().map(|extension| (*extension, APP_NAME)).collect();
+    set_values(&format!("{}\\FileAssociations", capabilities_key), &file_associations)?;
+    let url_associations: Vec<(&str, &str)> =
+        URL_SCHEMES.iter().map(|scheme| (*scheme, APP_NAME)).collect();
+    set_values(&format!("{}\\UrlAssociations", capabilities_key), &url_associations)?;
+    set_values(&format!("{}\\StartMenu", capabilities_key), &[("StartMenuInternet", APP_NAME)])?;
+
+    set_values(REGISTERED_APPLICATIONS_KEY, &[(APP_NAME, &capabilities_key)])?;
+
+    notify_associations_changed();
+    Ok(())
+}
+
+/// Removes the keys written by [register]. Keys that are already gone are not an error.
+pub fn unregister() -> BSResult<()> {
+    let root = RegKey::predef(HKEY_CURRENT_USER);
+    for key in [CLASS_KEY, CLIENT_KEY] {
+        ignore_not_found(root.delete_subkey_all(key))
+            .map_err(|err| registry_error("Cannot delete registry key", key, err))?;
+    }
+
+    let registered_applications = root.open_subkey_with_flags(REGISTERED_APPLICATIONS_KEY, KEY_SET_VALUE);
+    if let Ok(registered_applications) = registered_applications {
+        ignore_not_found(registered_applications.delete_value(APP_NAME))
+            .map_err(|err| registry_error("Cannot delete registry value", REGISTERED_APPLICATIONS_KEY, err))?;
+    }
+
+    notify_associations_changed();
+    Ok(())
+}
+
+fn set_values(key: &str, values: &[(&str, &str)]) -> BSResult<()> {
+    let (reg_key, _) = RegKey::predef(HKEY_CURRENT_USER)
+        .create_subkey(key)
+        .map_err(|err| registry_error("Cannot create registry key", key, err))?;
+    for (name, value) in values {
+        reg_key
+            .set_value(name, &String::from(*value))
+            .map_err(|err| registry_error("Cannot write registry key", key, err))?;
+    }
+
+    Ok(())
+}
+
+fn ignore_not_found(result: std::io::Result<()>) -> std::io::Result<()> {
+    match result {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+fn registry_error(msg: &str, key: &str, err: std::io::Error) -> BSError {
+    BSError::Platform {
+        msg: format!("{} HKCU\\{}", msg, key),
+        source: Some(Box::new(err)),
+    }
+}
+
+/// Lets Explorer and the settings app know they should read the associations again.
+fn notify_associations_changed() {
+    use winapi::um::shlobj::{SHChangeNotify, SHCNE_ASSOCCHANGED, SHCNF_IDLIST};
+    unsafe {
+        SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, std::ptr::null(), std::ptr::null());
+    }
+}
@@ -1,11 +1,8 @@
-use ::std::hash::{Hash, Hasher};
-use std::collections::hash_map::DefaultHasher;
-
-
-use crate::{error::BSResult as Result, ui::{BrowserSelectorUI, UserInterface, ListItem}};
+use crate::error::{BSError, BSResult as Result};
+use crate::os::IconPixels;
+pub use crate::os::browser::{BinaryType, Browser, VersionInfo};
 mod winapi {
     pub use winapi::shared::minwindef::DWORD;
-    pub use winapi::shared::windef::HICON;
     pub use winapi::um::errhandlingapi::GetLastError;
     pub use winapi::um::winbase::GetBinaryTypeW;
     pub use winapi::um::winnls::GetUserDefaultUILanguage;
@@ -13,86 +10,6 @@ mod winapi {
     pub use winapi::ctypes::*;
 }
 
-/// The `Browser` data structure is an entry mapped to the
-/// a browser program installed on the user's OS. What determines
-/// the list of present browser is platform specific.
-#[derive(Debug, Clone)]
-pub struct Browser {
-    // The path to the executable binary or script that is the entry point
-    // of the browser program. This path is absolute and free of arguments.
-    pub exe_path: String,
-
-    // The arguments that should be passed when executing the browser binary
-    pub arguments: Vec<String>,
-
-    // User friendly browser program name, deducted from the executable metadata
-    // as defined by the program publisher
-    pub name: String,
-
-    // Path to the browser program icon/logo
-    pub icon: String,
-
-    pub handle_icon: winapi::HICON,
-    pub exe_exists: bool,
-    pub icon_exists: bool,
-    pub version: VersionInfo,
-}
-
-impl Browser { 
-    pub fn get_hash(&self) -> String {
-        let mut hasher = DefaultHasher::new();
-        self.exe_path.hash(&mut hasher);
-        hasher.finish().to_string()
-    }
-}
-
-impl Default for Browser {
-    fn default() -> Browser {
-        Browser {
-            exe_path: String::default(),
-            arguments: Vec::default(),
-            name: String::default(),
-            version: VersionInfo::default(),
-            icon: String::default(),
-            exe_exists: false,
-            icon_exists: false,
-            handle_icon: std::ptr::null_mut(),
-        }
-    }
-}
-
-impl TryInto<ListItem<Browser>> for &Browser {
-    type Error = crate::error::BSError;
-    fn try_into(self) -> Result<ListItem<Browser>> {
-        let image =
-            BrowserSelectorUI::<Browser>::load_image(self.exe_path.as_str())
-                .unwrap_or_default();
-
-        let uuid = {
-            let mut hasher = DefaultHasher::new();
-            self.exe_path.hash(&mut hasher);
-            hasher.finish().to_string()
-        };
-
-        Ok(ListItem {
-            title: self.version.product_name.clone(),
-            subtitle: vec![
-                self.version.product_version.clone(),
-                self.version.binary_type.to_string(),
-                self.version.company_name.clone(),
-                self.version.file_description.clone(),
-            ]
-            .into_iter()
-            .filter(|itm| itm.len() > 0)
-            .collect::<Vec<String>>()
-            .join(" | "),
-            image,
-            uuid,
-            state: std::rc::Rc::new(self.clone()),
-        })
-    }
-}
-
 #[derive(Debug, Default)]
 struct WinExePath {
     pub path_to_exe: String,
@@ -135,43 +52,17 @@ impl From<&str> for WinExePath {
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum BinaryType {
-    Bits32,
-    Bits64,
-    None,
-}
-
-impl Default for BinaryType {
-    fn default() -> BinaryType {
-        BinaryType::None
-    }
-}
-
-impl std::fmt::Display for BinaryType {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                BinaryType::Bits32 => "32 bits",
-                BinaryType::Bits64 => "64 bits",
-                _ => "",
-            }
-        )
+pub fn read_system_browsers_sync() -> Result<Vec<Browser>> {
+    let mut list = find_system_browsers()?;
+    for browser in list.iter_mut() {
+        read_browser_details(browser);
     }
+    Ok(list)
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct VersionInfo {
-    pub company_name: String,
-    pub file_description: String,
-    pub product_version: String,
-    pub product_name: String,
-    pub binary_type: BinaryType,
-}
-
-pub fn read_system_browsers_sync() -> Result<Vec<Browser>> {
+/// Lists the browsers registered on the system, only with what the registry tells about them.
+/// Completed by [read_browser_details] which is much slower since it reads the executables.
+pub fn find_system_browsers() -> Result<Vec<Browser>> {
     // windows registry
     let path32 = "SOFTWARE\\Clients\\StartMenuInternet";
     let path64 = "SOFTWARE\\WOW6432Node\\Clients\\StartMenuInternet";
@@ -190,39 +81,42 @@ pub fn read_system_browsers_sync() -> Result<Vec<Browser>> {
         let path_and_args = WinExePath::from(browser.exe_path.as_str());
         browser.exe_path = path_and_args.path_to_exe;
         browser.arguments = path_and_args.arguments;
-
-        match read_browser_exe_info(&browser.exe_path) {
-            Ok(version) => browser.version = version,
-            Err(e) => println!(
-                "Error with reading browser info for {}. Reason: {}",
-                browser.exe_path, e
-            ),
-        }
-
-        match crate::os::get_exe_file_icon(&browser.exe_path) {
-            Ok(icon) => browser.handle_icon = icon,
-            Err(e) => println!(
-                "Error loading icon from file {}, Reason: {}",
-                browser.exe_path, e
-            ),
-        }
     }
     Ok(list)
 }
 
+/// Reads the version information from the browser executable.
+pub fn read_browser_details(browser: &mut Browser) {
+    match read_browser_exe_info(&browser.exe_path) {
+        Ok(version) => browser.version = version,
+        Err(e) => println!(
+            "Error with reading browser info for {}. Reason: {}",
+            browser.exe_path, e
+        ),
+    }
+}
+
+/// Reads the icon of the browser executable, the one Explorer shows for it.
+pub fn read_browser_icon(browser: &Browser) -> Result<IconPixels> {
+    crate::os::get_exe_file_icon_pixels(&browser.exe_path)
+}
+
 fn read_browsers_from_reg_path_sync(win_reg_path: &str) -> Result<Vec<Browser>> {
     let mut browsers: Vec<Browser> = Vec::new();
     let root = winreg::RegKey::predef(winreg::enums::HKEY_LOCAL_MACHINE)
         .open_subkey(win_reg_path)
-        .unwrap(); // TODO: grecefully handle error instead of panicing
+        .map_err(|err| BSError::Platform {
+            msg: format!("Cannot open registry key HKLM\\{}", win_reg_path),
+            source: Some(Box::new(err)),
+        })?;
 
-    for key in root.enum_keys().map(|x| x.unwrap()) {
+    for key in root.enum_keys().filter_map(|x| x.ok()) {
         match read_browser_info_from_reg_key(&[win_reg_path, "\\", &key].join("")) {
             Ok(browser) => browsers.push(browser),
             Err(e) => println!("Error reading browser info: {:?}", e),
         }
     }
-    for (name, value) in root.enum_values().map(|x| x.unwrap()) {
+    for (name, value) in root.enum_values().filter_map(|x| x.ok()) {
         println!("\t{} = {:?}", name, value);
     }
     Ok(browsers)
@@ -5,6 +5,7 @@ use raw_window_handle::HasRawWindowHandle;
 use simple_error::SimpleResult as Result;
 
 use crate::error::*;
+use crate::os::util::IconPixels;
 use winapi::ctypes::c_void;
 use winapi::um::stringapiset::MultiByteToWideChar;
 use winapi::um::winuser::{GetWindowTextW, MessageBoxW};
@@ -65,11 +66,6 @@ pub fn ansi_str_to_wide(src_string: &Vec<i8>, code_page: u16) -> Result<Vec<u16>
     Ok(dst_string)
 }
 
-pub fn as_u8_slice(v: &[u32]) -> &[u8] {
-    let element_size = std::mem::size_of::<u32>();
-    unsafe { std::slice::from_raw_parts(v.as_ptr() as *const u8, v.len() * element_size) }
-}
-
 pub fn get_exe_file_icon(path: &str) -> Result<winapi::shared::windef::HICON> {
     use winapi::um::shellapi::{SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_LARGEICON};
 
@@ -98,39 +94,146 @@ pub fn get_exe_file_icon(path: &str) -> Result<winapi::shared::windef::HICON> {
     Ok(file_info.hIcon)
 }
 
-pub fn _get_config_directory() -> BSResult<String> {
+/// Reads the pixels of the icon the shell shows for the given executable. Only uses GDI so
+/// it can run off the UI thread, turning the pixels into an image is left to the UI.
+pub fn get_exe_file_icon_pixels(path: &str) -> BSResult<IconPixels> {
+    let hicon = get_exe_file_icon(path)?;
+    let pixels = hicon_to_pixels(hicon);
+    unsafe {
+        winapi::um::winuser::DestroyIcon(hicon);
+    }
+
+    pixels
+}
+
+/// Copies the color bitmap of the given HICON
+///
+/// Notes:
+/// - There probably is a simpler way to achieve this
+/// - The function does not implement all possiblities described in the Windows API doc
+/// thus it is possible that it might not work for certain icon formats
+pub fn hicon_to_pixels(hicon: winapi::shared::windef::HICON) -> BSResult<IconPixels> {
+    use std::mem::MaybeUninit;
+    use winapi::shared::windef::HGDIOBJ;
+    use winapi::um::wingdi::{DeleteObject, GetBitmapBits, GetObjectW, BITMAP, DIBSECTION};
+    use winapi::um::winuser::{GetIconInfo, ICONINFO};
+
+    let mut icon_info: ICONINFO = unsafe { MaybeUninit::uninit().assume_init() };
+    let icon_result = unsafe { GetIconInfo(hicon, &mut icon_info) };
+    if icon_result == 0 {
+        bail!("Couldn't get icon info for HICON {:?}", hicon);
+    }
+
+    let dib_struct_size = std::mem::size_of::<DIBSECTION>()
+        .try_into()
+        .unwrap_or(0);
+    let bitmap_struct_size = std::mem::size_of::<BITMAP>()
+        .try_into()
+        .unwrap_or(0);
+
+    let mut dib: DIBSECTION = unsafe { MaybeUninit::uninit().assume_init() };
+    let bytes_read = unsafe {
+        GetObjectW(
+            icon_info.hbmColor as *mut _ as *mut c_void,
+            dib_struct_size,
+            &mut dib as *mut _ as *mut c_void,
+        )
+    };
+
+    if bytes_read == 0 {
+        unsafe {
+            DeleteObject(icon_info.hbmColor as HGDIOBJ);
+            DeleteObject(icon_info.hbmMask as HGDIOBJ);
+        }
+
+        bail!("Error: winapi::GetObject returned 0 on ICONINFO.hbmColor bitmap.");
+    }
+
+    // BITMAP size is 32 bytes
+    // DIBSECTION is 104 bytes
+    let bmp_size_in_bytes =
+        (dib.dsBm.bmHeight * dib.dsBm.bmWidth) * (dib.dsBm.bmBitsPixel as i32 / 8);
+
+    let pixel_bytes_result = match bytes_read {
+        bytes_read if bytes_read == bitmap_struct_size => {
+            // when GetObject returns the size of the BITMAP structure
+            // then dib.dsBm is a device dependent bitmap we need to use GetBitmapBits
+            let mut img_bytes = Vec::<u8>::new();
+            img_bytes.resize(bmp_size_in_bytes as usize, 0);
+
+            let img_bytes_read = unsafe {
+                GetBitmapBits(
+                    icon_info.hbmColor,
+                    bmp_size_in_bytes,
+                    img_bytes.as_mut_slice().as_mut_ptr() as *mut c_void,
+                )
+            };
+            // TODO: Replace GetBitmapBits with GetDibBits because GetBitmapBits is deprecated
+
+            if img_bytes_read == 0 {
+                Err("winapi::GetBitmapBits read 0 bytes from the ICONINFO.hbmColor")
+            } else {
+                Ok(img_bytes)
+            }
+        }
+        bytes_read if bytes_read == dib_struct_size => {
+            if dib.dsBm.bmBits as usize != 0 {
+                Ok(unsafe {
+                    std::slice::from_raw_parts::<u8>(
+                        dib.dsBm.bmBits as *const u8,
+                        bmp_size_in_bytes as usize,
+                    )
+                    .to_vec()
+                })
+            } else {
+                Err("Unexpected NULL pointer for image bits from DIBSECTION.dsBm.bmBits")
+            }
+        }
+        0 => Err("winapi::GetObject returned 0 on ICONINFO.hbmColor bitmap."),
+        _ => Err(
+            "Unexpected response from winapi::GetObject, was expecting read bytes \
+            to match either the BITMAP struct size or the DIBSECTION struct size.",
+        ),
+    };
+
+    unsafe {
+        DeleteObject(icon_info.hbmColor as HGDIOBJ);
+        DeleteObject(icon_info.hbmMask as HGDIOBJ);
+    }
+
+    match pixel_bytes_result {
+        Ok(bgra) => Ok(IconPixels {
+            width: dib.dsBm.bmWidth,
+            height: dib.dsBm.bmHeight,
+            bgra,
+        }),
+        Err(error) => bail!(error),
+    }
+}
+
+/// Returns the path of the given Windows known folder, ie. `FOLDERID_RoamingAppData`.
+///
+/// [MSDN Info](https://learn.microsoft.com/en-us/windows/win32/shell/knownfolderid)
+pub fn get_known_folder_path(folder_id: &winapi::shared::guiddef::GUID) -> BSResult<PathBuf> {
     use winapi::shared::winerror::S_OK;
     use winapi::um::combaseapi::CoTaskMemFree;
-    use winapi::um::knownfolders::FOLDERID_RoamingAppData;
     use winapi::um::shlobj::SHGetKnownFolderPath;
 
     let mut wide_system_path: *mut u16 = std::ptr::null_mut();
-    let result_path: BSResult<String> = unsafe {
-        match SHGetKnownFolderPath(
-            &FOLDERID_RoamingAppData,
-            0,
-            std::ptr::null_mut(),
-            &mut wide_system_path,
-        ) {
+    let result_path: BSResult<PathBuf> = unsafe {
+        match SHGetKnownFolderPath(folder_id, 0, std::ptr::null_mut(), &mut wide_system_path) {
             S_OK => {
-                //let string_length = 0;
-                let mut buff = Vec::<u16>::new();
-                for i in 0usize.. {
-                    if *wide_system_path.offset(i as isize) == 0 {
-                        buff = Vec::<u16>::from_raw_parts(wide_system_path, i, i);
-                    }
+                let mut len = 0usize;
+                while *wide_system_path.add(len) != 0 {
+                    len += 1;
                 }
 
-                let path = wide_to_str(&buff);
-
-                std::mem::forget(buff); // from_raw_parts does not clone the buffer
-                                        // we need to allow the buffer to exist after the vec is dealocated
-                                        // as we free the memory using CoTaskMemFree as recommended by the WinAPI
-
-                Ok(path)
+                // the buffer is owned by the OS, we free it with CoTaskMemFree below
+                let buff = std::slice::from_raw_parts(wide_system_path, len);
+                Ok(PathBuf::from(wide_slice_to_str(buff)))
             }
-            code => Err(BSError::from(
-                format!("Error getting OS config directory. Error code: {:?}", code).as_str(),
+            code => Err(BSError::platform(
+                format!("Error getting OS known folder path. Error code: {:?}", code).as_str(),
             )),
         }
     };
@@ -139,24 +242,79 @@ pub fn _get_config_directory() -> BSResult<String> {
     result_path
 }
 
-pub fn _get_create_config_directory(app_name: &str, env_name: &str) -> BSResult<String> {
-    let app_data_dir = _get_config_directory()?;
-    let os_path = std::path::Path::new(&app_data_dir);
-    let subpath = format!("{}/{}", app_name, env_name);
-    let app_env_path = std::path::Path::new(&subpath);
-    let full_path = os_path.join(app_env_path);
-    let full_path_str = full_path.to_string_lossy().to_string();
+/// Per user roaming settings, ie. `C:\Users\<user>\AppData\Roaming`
+pub fn get_os_user_config_dir() -> BSResult<PathBuf> {
+    get_known_folder_path(&winapi::um::knownfolders::FOLDERID_RoamingAppData)
+}
+
+/// Machine wide settings, ie. `C:\ProgramData`
+pub fn get_os_system_config_dir() -> BSResult<PathBuf> {
+    get_known_folder_path(&winapi::um::knownfolders::FOLDERID_ProgramData)
+}
+
+/// Per user, per machine data, ie. `C:\Users\<user>\AppData\Local`
+pub fn get_os_user_data_dir() -> BSResult<PathBuf> {
+    get_known_folder_path(&winapi::um::knownfolders::FOLDERID_LocalAppData)
+}
 
-    if std::fs::create_dir_all(&full_path).is_err() {
-        bail!("Error creating config path {}", full_path_str);
+/// Where the mouse cursor is on the whole desktop.
+pub fn get_cursor_position() -> Option<(i32, i32)> {
+    let mut point: winapi::shared::windef::POINT = Default::default();
+    let is_known = unsafe { winapi::um::winuser::GetCursorPos(&mut point) } != 0;
+
+    is_known.then(|| (point.x, point.y))
+}
+
+/// The part of the monitor nearest to `x`, `y` that the taskbar leaves to windows, as x, y, width
+/// and height.
+pub fn get_work_area(x: i32, y: i32) -> Option<(i32, i32, u32, u32)> {
+    use winapi::shared::windef::POINT;
+    use winapi::um::winuser::{GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST};
+    let mut info: MONITORINFO = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+    let is_known = unsafe {
+        let monitor = MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST);
+        GetMonitorInfoW(monitor, &mut info) != 0
+    };
+
+    let area = info.rcWork;
+    is_known.then(|| (area.left, area.top, (area.right - area.left) as u32, (area.bottom - area.top) as u32))
+}
+
+/// The language of the Windows display as a BCP 47 tag such as `de-DE`, empty when it can't be read.
+pub fn get_ui_languages() -> Vec<String> {
+    use winapi::um::winnls::{GetUserDefaultUILanguage, LCIDToLocaleName};
+    let mut name = [0u16; winapi::um::winnt::LOCALE_NAME_MAX_LENGTH];
+    // the language ID makes the locale ID with the default sort order
+    let length = unsafe {
+        LCIDToLocaleName(GetUserDefaultUILanguage() as u32, name.as_mut_ptr(), name.len() as i32, 0)
+    };
+
+    match length {
+        0 => Vec::new(),
+        // the length counts the terminating null
+        _ => vec![wide_slice_to_str(&name[..length as usize - 1])],
     }
+}
 
-    Ok(full_path_str)
+/// The app is built for the `windows` subsystem so it has no console of its own. For CLI
+/// commands, attach to the console of the shell that started us so that printing works.
+pub fn attach_parent_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
 }
 
 pub fn output_panic_text(text: String) {
+    output_error_text("Panic!", text);
+}
+
+/// Shows the given `text` in a blocking message box, used for errors that prevent
+/// the app from doing its job so the user isn't left wondering why nothing opened.
+pub fn output_error_text(title: &str, text: String) {
     let wide_text = str_to_wide(&text);
-    let title = str_to_wide(&"Panic!");
+    let title = str_to_wide(title);
     unsafe {
         MessageBoxW(
             std::ptr::null_mut(),
@@ -1,10 +1,11 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use winit::event::{Event, WindowEvent};
+use winit::event::{Event, ModifiersState, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopWindowTarget};
 
-use crate::os::sys_browsers::Browser;
+use super::keys::{handle_key, SelectorKey};
+use super::{BrowserSelectorUI, SelectionAction, UserInterface};
 
 pub enum UserEvent {
     Close,
@@ -14,29 +15,46 @@ pub fn make_ev_loop() -> EventLoop<UserEvent> {
     EventLoopBuilder::with_user_event().build()
 }
 
-pub fn make_runner<UIType>(
-    ui_ref: Rc<RefCell<UIType>>,
-    mut delegate: impl FnMut(&mut ControlFlow) -> (),
-) -> impl FnMut(Event<UserEvent>, &EventLoopWindowTarget<UserEvent>, &mut ControlFlow) -> ()
-where
-    UIType: crate::ui::UserInterface<Browser>,
-{
+/// With `keep_running` closing the selector only hides it, as done in daemon mode.
+pub fn make_runner<T: Clone + 'static>(
+    ui_ref: Rc<RefCell<BrowserSelectorUI<T>>>,
+    keep_running: bool,
+    mut delegate: impl FnMut(&mut ControlFlow),
+) -> impl FnMut(Event<UserEvent>, &EventLoopWindowTarget<UserEvent>, &mut ControlFlow) {
+    let mut modifiers = ModifiersState::empty();
     move |event: Event<UserEvent>, _, control_flow: &mut ControlFlow| {
         *control_flow = ControlFlow::WaitUntil(
             std::time::Instant::now() + std::time::Duration::from_millis(10),
         );
 
-        handle_ui_event(event, control_flow, ui_ref.clone());
+        handle_ui_event(event, control_flow, ui_ref.clone(), keep_running, &mut modifiers);
         delegate(control_flow);
+
+        if *control_flow == ControlFlow::Exit {
+            // TODO: Investigate why the process hangs when returning control to winit
+            // or when existing gracefully with ExitProcess
+            crate::os::terminate_current_process()
+        }
     }
 }
 
-pub fn handle_ui_event<UIType>(event: Event<UserEvent>, control_flow: &mut ControlFlow, ui_ref: Rc<RefCell<UIType>>)
-where
-    UIType: crate::ui::UserInterface<Browser>,
-{
+pub fn handle_ui_event<T: Clone + 'static>(
+    event: Event<UserEvent>,
+    control_flow: &mut ControlFlow,
+    ui_ref: Rc<RefCell<BrowserSelectorUI<T>>>,
+    keep_running: bool,
+    modifiers: &mut ModifiersState,
+) {
     let main_window_id = { ui_ref.borrow().get_window_id() };
 
+    // egui needs the mouse input, WinUI gets it through its own window
+    #[cfg(target_os = "linux")]
+    if let Event::WindowEvent { event, window_id } = &event {
+        if *window_id == main_window_id {
+            ui_ref.borrow().handle_window_event(event);
+        }
+    }
+
     match event {
         Event::UserEvent(_) => {
             *control_flow = ControlFlow::Exit;
@@ -54,71 +72,112 @@ where
             let ui = ui_ref.borrow();
             ui.update_layout_size(&_size).unwrap();
         }
+        Event::WindowEvent {
+            event: WindowEvent::ModifiersChanged(state),
+            ..
+        } => {
+            *modifiers = state;
+        }
         Event::WindowEvent {
             event: WindowEvent::KeyboardInput { input, .. },
             ..
         } if input.state == winit::event::ElementState::Pressed => {
-            use winit::event::VirtualKeyCode;
             let ui = ui_ref.borrow();
+            // not every key has a virtual key code, ie. some media keys
+            let is_filtering = !ui.get_filter().is_empty();
             let key = input
                 .virtual_keycode
-                .expect("Couldn't identify pressed key.");
-            let length = ui
-                .get_list_length()
-                .expect("Couldn't determine list length")
-                - 1;
-            let mut current_index = ui
-                .get_selected_list_item_index()
-                .expect("Couldn't determine currently selected item");
-
-            match key {
-                VirtualKeyCode::Down => {
-                    current_index = (current_index + 1).clamp(0, length as isize);
-                    ui.select_list_item_by_index(current_index)
-                        .expect("Couldn't select next item.");
-                }
-                VirtualKeyCode::Up => {
-                    current_index = (current_index - 1).clamp(0, length as isize);
-                    ui.select_list_item_by_index(current_index)
-                        .expect("Couldn't select previous item.");
-                }
-                VirtualKeyCode::NumpadEnter | VirtualKeyCode::Return => {
-                    let item = ui.get_selected_list_item().ok().unwrap().unwrap();
-                    ui.trigger_browser_selected(&item.uuid);
-                }
-                VirtualKeyCode::Space => {
-                    if let Some(item) = ui.prediction_get_state().iter().take(1).last() {
-                        ui.trigger_browser_selected(&item.uuid);
-                    }
-                }
-                VirtualKeyCode::Back => {
-                    if let Some(item) = ui.prediction_get_state().iter().take(2).last() {
-                        ui.trigger_browser_selected(&item.uuid);
-                    }
-                }
-                VirtualKeyCode::Escape => {
+                .and_then(|vkey| selector_key_from_vkey(vkey, *modifiers, is_filtering));
+            if let Some(key) = key {
+                if handle_key(&*ui, key) {
                     *control_flow = ControlFlow::Exit;
                 }
-                vkey => {
-                    if let Some(pos) = list_number_from_vkey(vkey) {
-                        ui.select_list_item_by_index(pos.clamp(0, length as isize))
-                            .expect("Couldn't select specific item number");
-                    };
-                }
             }
         }
+        Event::WindowEvent {
+            event: WindowEvent::ReceivedCharacter(character),
+            ..
+        } => {
+            let ui = ui_ref.borrow();
+            // with Alt or Ctrl held the letters are shortcuts, not text
+            let is_shortcut = modifiers.alt() || modifiers.ctrl();
+            let is_filtering = !ui.get_filter().is_empty();
+            if let Some(key) = selector_key_from_char(character, is_filtering).filter(|_| !is_shortcut) {
+                handle_key(&*ui, key);
+            }
+        }
+        #[cfg(target_os = "linux")]
+        Event::RedrawRequested(window_id) if window_id == main_window_id => {
+            ui_ref.borrow().invalidate();
+        }
+        #[cfg(target_os = "linux")]
+        Event::RedrawEventsCleared => ui_ref.borrow().paint(),
         _ => (),
     }
 
     if *control_flow == ControlFlow::Exit {
         let ui = ui_ref.borrow();
         ui.set_main_window_visible(false);
-        ui.destroy();
+        if keep_running {
+            *control_flow = ControlFlow::WaitUntil(
+                std::time::Instant::now() + std::time::Duration::from_millis(10),
+            );
+        } else {
+            ui.destroy();
+        }
+    }
+}
+
+/// The digits open the list items until a filter is typed, then they come as text, see
+/// [selector_key_from_char].
+fn selector_key_from_vkey(vkey: VirtualKeyCode, modifiers: ModifiersState, is_filtering: bool) -> Option<SelectorKey> {
+    match vkey {
+        VirtualKeyCode::Down => Some(SelectorKey::Down),
+        VirtualKeyCode::Up => Some(SelectorKey::Up),
+        VirtualKeyCode::NumpadEnter | VirtualKeyCode::Return => {
+            Some(SelectorKey::Enter(match modifiers {
+                modifiers if modifiers.ctrl() => SelectionAction::CopyUrls,
+                modifiers if modifiers.shift() => SelectionAction::OpenPrivate,
+                _ => SelectionAction::Open,
+            }))
+        }
+        VirtualKeyCode::Space => Some(SelectorKey::Space),
+        VirtualKeyCode::Back => Some(SelectorKey::Backspace),
+        VirtualKeyCode::Escape => Some(SelectorKey::Escape),
+        VirtualKeyCode::Left => Some(SelectorKey::Left),
+        VirtualKeyCode::Right => Some(SelectorKey::Right),
+        VirtualKeyCode::Home => Some(SelectorKey::Home),
+        VirtualKeyCode::End => Some(SelectorKey::End),
+        VirtualKeyCode::Delete => Some(SelectorKey::Delete),
+        VirtualKeyCode::L if modifiers.ctrl() => Some(SelectorKey::EditUrl),
+        vkey if modifiers.alt() => letter_from_vkey(vkey).map(SelectorKey::Mnemonic),
+        vkey if !is_filtering => list_number_from_vkey(vkey).map(SelectorKey::Number),
+        _ => None,
+    }
+}
+
+fn letter_from_vkey(vkey: VirtualKeyCode) -> Option<char> {
+    // the letter keys are declared from A to Z one after the other
+    let offset = (vkey as u32).checked_sub(VirtualKeyCode::A as u32)?;
+    match offset {
+        0..=25 => char::from_u32('a' as u32 + offset),
+        _ => None,
+    }
+}
+
+/// Typed text for the filter. Space and the list numbers come as key presses too and are taken
+/// from those, as are control characters such as backspace. Once a filter is typed the digits
+/// are text as well.
+fn selector_key_from_char(character: char, is_filtering: bool) -> Option<SelectorKey> {
+    match character {
+        ' ' => None,
+        '1'..='9' if !is_filtering => None,
+        character if character.is_control() => None,
+        character => Some(SelectorKey::Char(character)),
     }
 }
 
-fn list_number_from_vkey(vkey: winit::event::VirtualKeyCode) -> Option<isize> {
-    use winit::event::VirtualKeyCode;
+fn list_number_from_vkey(vkey: VirtualKeyCode) -> Option<isize> {
     match vkey {
         VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(0),
         VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(1),
@@ -0,0 +1,115 @@
+use triple_accel::levenshtein::{levenshtein_search_simd_with_opts, RDAMERAU_COSTS};
+use triple_accel::SearchType;
+
+use super::ListItem;
+
+/// How well an item matches, compared field by field and lower is better: the typos it took,
+/// which field matched with the title first, and where in it the match starts.
+type Score = (u32, usize, usize);
+
+/// The items matching the typed `query` anywhere in their title or subtitle, ignoring case, best
+/// match first. Items matching equally well keep their order and an empty query keeps them all.
+/// This is what the list shows as the user types, whatever the frontend.
+pub fn rank<T: Clone>(items: &[ListItem<T>], query: &str) -> Vec<ListItem<T>> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return items.to_vec();
+    }
+
+    let mut ranked: Vec<(Score, &ListItem<T>)> = items
+        .iter()
+        .filter_map(|item| score(&query, item).map(|score| (score, item)))
+        .collect();
+    // the sort is stable, ties stay in the list order
+    ranked.sort_by_key(|(score, _)| *score);
+
+    ranked.into_iter().map(|(_, item)| item.clone()).collect()
+}
+
+/// How many typos a query of `length` characters can have and still match, short queries have
+/// to match exactly or nearly everything would.
+fn allowed_edits(length: usize) -> u32 {
+    (length / 4) as u32
+}
+
+/// `None` when the lowercase `query` doesn't match the item.
+fn score<T: Clone>(query: &str, item: &ListItem<T>) -> Option<Score> {
+    let max_edits = allowed_edits(query.chars().count());
+
+    [&item.title, &item.subtitle]
+        .iter()
+        .enumerate()
+        .filter_map(|(field_index, field)| {
+            let field = field.to_lowercase();
+            levenshtein_search_simd_with_opts(
+                query.as_bytes(),
+                field.as_bytes(),
+                max_edits,
+                SearchType::Best,
+                RDAMERAU_COSTS,
+                false,
+            )
+            .map(|found| (found.k, field_index, found.start))
+            .min()
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rank;
+    use crate::ui::{Image, ListItem};
+    use std::rc::Rc;
+
+    fn items(names: &[(&str, &str)]) -> Vec<ListItem<()>> {
+        names
+            .iter()
+            .map(|(title, subtitle)| ListItem {
+                title: title.to_string(),
+                subtitle: subtitle.to_string(),
+                image: Image::default(),
+                uuid: title.to_string(),
+                mnemonic: None,
+                state: Rc::new(()),
+            })
+            .collect()
+    }
+
+    fn titles(items: Vec<ListItem<()>>) -> Vec<String> {
+        items.into_iter().map(|item| item.title).collect()
+    }
+
+    #[test]
+    fn empty_query_keeps_every_item() {
+        let list = items(&[("Firefox", ""), ("Chrome", "")]);
+        assert_eq!(titles(rank(&list, "  ")), ["Firefox", "Chrome"]);
+    }
+
+    #[test]
+    fn matches_anywhere_ignoring_case() {
+        let list = items(&[("Firefox", "Mozilla"), ("Chrome", "Google"), ("Edge", "Microsoft")]);
+        assert_eq!(titles(rank(&list, "FOX")), ["Firefox"]);
+        assert_eq!(titles(rank(&list, "goo")), ["Chrome"]);
+        assert!(rank(&list, "opera").is_empty());
+    }
+
+    #[test]
+    fn title_matches_come_before_subtitle_matches() {
+        let list = items(&[("Browser", "Edge based"), ("Edge", "Microsoft")]);
+        assert_eq!(titles(rank(&list, "edge")), ["Edge", "Browser"]);
+    }
+
+    #[test]
+    fn earlier_matches_come_first_and_ties_keep_the_order() {
+        let list = items(&[("Tor Browser", ""), ("Brave", ""), ("Brave Beta", "")]);
+        assert_eq!(titles(rank(&list, "br")), ["Brave", "Brave Beta", "Tor Browser"]);
+    }
+
+    #[test]
+    fn long_queries_allow_typos() {
+        let list = items(&[("Chromium", ""), ("Firefox", "")]);
+        assert_eq!(titles(rank(&list, "chormium")), ["Chromium"]);
+        // short queries have to match exactly
+        assert!(rank(&list, "fyr").is_empty());
+    }
+}
@@ -0,0 +1,337 @@
+use super::{ListItem, SelectionAction, UrlEdit, UserInterface};
+use crate::error::BSResult;
+
+/// The keys the selector responds to, each frontend maps its own key events to these
+/// so that the keyboard works the same everywhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectorKey {
+    Up,
+    Down,
+    /// Acts on the selected item, the top match while filtering
+    Enter(SelectionAction),
+    /// Opens the first prediction, or types a space while filtering
+    Space,
+    /// Opens the second prediction, or erases the last typed character while filtering
+    Backspace,
+    /// Closes the QR code, the history, the URL edit and clears the filter, one at a time in that
+    /// order. Closes the selector once there is none of these
+    Escape,
+    /// Opens the list item at the index, the 1 key gives 0. The digits only come as this while no
+    /// filter is typed, after that they come as [SelectorKey::Char] and filter the list
+    Number(isize),
+    /// Alt with a letter, opens the list item with that mnemonic
+    Mnemonic(char),
+    /// A typed character, narrows the list down, see [super::filter]
+    Char(char),
+    /// Ctrl+L, starts editing the URL, see [UrlEdit]
+    EditUrl,
+    /// The caret keys, only used while editing the URL
+    Left,
+    Right,
+    Home,
+    End,
+    Delete,
+}
+
+/// Acts on the pressed `key`, returns true when the selector should close without a choice.
+pub fn handle_key<T: Clone>(ui: &impl UserInterface<T>, key: SelectorKey) -> bool {
+    if ui.get_qr_code().is_some() {
+        // the list is behind the QR code, no key acts on it until the code is closed
+        if matches!(key, SelectorKey::Escape | SelectorKey::Enter(_) | SelectorKey::Backspace) {
+            log_failure(ui.set_qr_code(None), "Couldn't close the QR code");
+        }
+        return false;
+    }
+    if key == SelectorKey::Escape && ui.is_history_shown() {
+        // back to the browsers rather than closing the selector, what was typed goes with the history
+        log_failure(ui.set_history(None), "Couldn't close the history");
+        return false;
+    }
+    if let Some(edit) = ui.get_url_edit() {
+        handle_url_edit_key(ui, edit, key);
+        return false;
+    }
+    if key == SelectorKey::EditUrl {
+        log_failure(ui.set_url_edit(Some(UrlEdit::new(&ui.get_url()))), "Couldn't start editing the URL");
+        return false;
+    }
+
+    if let Some(filter) = edit_filter(&ui.get_filter(), key) {
+        log_failure(ui.set_filter(&filter), "Couldn't filter the list");
+        return false;
+    }
+    // the predictions are browsers, they don't pick a link
+    if ui.is_history_shown() && matches!(key, SelectorKey::Space | SelectorKey::Backspace) {
+        return false;
+    }
+
+    // the list can be empty while the browsers are being discovered
+    let (last_index, current_index) = match (ui.get_list_length(), ui.get_selected_list_item_index()) {
+        (Ok(length), Ok(index)) => (length as isize - 1, index),
+        (Err(err), _) | (_, Err(err)) => {
+            crate::log_error("Couldn't read the list", &err);
+            return false;
+        }
+    };
+
+    match key {
+        SelectorKey::Down if last_index >= 0 => {
+            let index = (current_index + 1).clamp(0, last_index);
+            log_failure(ui.select_list_item_by_index(index), "Couldn't select the next item");
+        }
+        SelectorKey::Up if last_index >= 0 => {
+            let index = (current_index - 1).clamp(0, last_index);
+            log_failure(ui.select_list_item_by_index(index), "Couldn't select the previous item");
+        }
+        SelectorKey::Enter(action) => {
+            if let Ok(Some(item)) = ui.get_selected_list_item() {
+                ui.trigger_browser_selected(&item.uuid, action);
+            }
+        }
+        SelectorKey::Space => {
+            if let Some(item) = ui.prediction_get_state().first() {
+                ui.trigger_browser_selected(&item.uuid, SelectionAction::Open);
+            }
+        }
+        SelectorKey::Backspace => {
+            if let Some(item) = ui.prediction_get_state().get(1) {
+                ui.trigger_browser_selected(&item.uuid, SelectionAction::Open);
+            }
+        }
+        SelectorKey::Escape => return true,
+        SelectorKey::Number(index) if index <= last_index => {
+            if let Ok(Some(item)) = ui.get_list_item(index as usize) {
+                ui.trigger_browser_selected(&item.uuid, SelectionAction::Open);
+            }
+        }
+        SelectorKey::Mnemonic(letter) => {
+            let letter = letter.to_ascii_lowercase();
+            let item = (0..=last_index)
+                .filter_map(|index| ui.get_list_item(index as usize).ok().flatten())
+                .find(|item| item.mnemonic == Some(letter));
+            if let Some(item) = item {
+                ui.trigger_browser_selected(&item.uuid, SelectionAction::Open);
+            }
+        }
+        _ => (),
+    }
+
+    false
+}
+
+/// Edits the URL with the `key`. Enter takes the URL when it is valid, Escape leaves it as it was.
+fn handle_url_edit_key<T: Clone>(ui: &impl UserInterface<T>, mut edit: UrlEdit, key: SelectorKey) {
+    match key {
+        SelectorKey::Enter(_) => match edit.validate() {
+            Ok(url) => {
+                log_failure(ui.set_url_edit(None), "Couldn't stop editing the URL");
+                ui.trigger_url_changed(&url);
+                return;
+            }
+            Err(message) => edit.error = Some(message),
+        },
+        SelectorKey::Escape => {
+            log_failure(ui.set_url_edit(None), "Couldn't stop editing the URL");
+            return;
+        }
+        SelectorKey::Char(character) => edit.insert(character),
+        SelectorKey::Space => edit.insert(' '),
+        // the digit keys come as list numbers
+        SelectorKey::Number(index) => edit.insert(char::from_digit(index as u32 + 1, 10).unwrap_or('0')),
+        SelectorKey::Backspace => edit.backspace(),
+        SelectorKey::Delete => edit.delete(),
+        SelectorKey::Left => edit.move_cursor(-1),
+        SelectorKey::Right => edit.move_cursor(1),
+        SelectorKey::Home => edit.move_cursor_to_start(),
+        SelectorKey::End => edit.move_cursor_to_end(),
+        _ => return,
+    }
+
+    log_failure(ui.set_url_edit(Some(edit)), "Couldn't edit the URL");
+}
+
+/// A key that failed to update the UI is logged and otherwise ignored, the selector stays usable.
+fn log_failure(result: BSResult<()>, context: &str) {
+    if let Err(err) = result {
+        crate::log_error(context, &err);
+    }
+}
+
+/// The filter once `key` is pressed, `None` when the key isn't typing. Space, Backspace and Escape
+/// only edit a filter that was started, otherwise they keep their own actions.
+fn edit_filter(filter: &str, key: SelectorKey) -> Option<String> {
+    let mut filter = String::from(filter);
+    match key {
+        SelectorKey::Char(character) => filter.push(character),
+        _ if filter.is_empty() => return None,
+        SelectorKey::Space => filter.push(' '),
+        SelectorKey::Backspace => {
+            filter.pop();
+        }
+        SelectorKey::Escape => filter.clear(),
+        _ => return None,
+    }
+
+    Some(filter)
+}
+
+/// Gives the items their mnemonic letter: the `configured` one if any, otherwise a letter of the
+/// title that no other item took, word initials first. Items left without a free letter get none.
+pub fn assign_mnemonics<T: Clone>(items: &mut [ListItem<T>], configured: impl Fn(&ListItem<T>) -> Option<char>) {
+    let mut taken: Vec<char> = Vec::new();
+    // the configured letters go first so that no item takes them automatically
+    for item in items.iter_mut() {
+        item.mnemonic = configured(item)
+            .map(|letter| letter.to_ascii_lowercase())
+            .filter(|letter| !taken.contains(letter));
+        taken.extend(item.mnemonic);
+    }
+
+    for item in items.iter_mut().filter(|item| item.mnemonic.is_none()) {
+        let initials = item.title.split_whitespace().filter_map(|word| word.chars().next());
+        item.mnemonic = initials
+            .chain(item.title.chars())
+            .map(|letter| letter.to_ascii_lowercase())
+            .find(|letter| letter.is_ascii_alphabetic() && !taken.contains(letter));
+        taken.extend(item.mnemonic);
+    }
+}
+
+/// The keys opening the list item at `index`, as the list shows them next to it, ie. `1  Alt+F`.
+pub fn shortcut_hint<T: Clone>(index: usize, item: &ListItem<T>) -> String {
+    let number = match index {
+        0..=8 => Some((index + 1).to_string()),
+        _ => None,
+    };
+    let mnemonic = item
+        .mnemonic
+        .map(|letter| format!("Alt+{}", letter.to_ascii_uppercase()));
+
+    number.into_iter().chain(mnemonic).collect::<Vec<String>>().join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assign_mnemonics, handle_key, SelectorKey};
+    use crate::ui::{Image, ListItem, SelectionAction, TerminalUI, UrlEdit, UserInterface};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn items(titles: &[&str]) -> Vec<ListItem<()>> {
+        titles
+            .iter()
+            .map(|title| ListItem {
+                title: title.to_string(),
+                subtitle: String::new(),
+                image: Image::default(),
+                uuid: title.to_string(),
+                mnemonic: None,
+                state: Rc::new(()),
+            })
+            .collect()
+    }
+
+    fn mnemonics(items: &[ListItem<()>]) -> Vec<Option<char>> {
+        items.iter().map(|item| item.mnemonic).collect()
+    }
+
+    /// The terminal selector draws nothing until it runs, it keeps the state the keys act on.
+    /// Returns it with the uuids it selected.
+    fn selector(titles: &[&str]) -> (TerminalUI<()>, Rc<RefCell<Vec<String>>>) {
+        let mut ui = TerminalUI::new().unwrap();
+        ui.set_list(&items(titles)).unwrap();
+        let selected = Rc::new(RefCell::new(Vec::new()));
+        let selected_ref = selected.clone();
+        ui.on_browser_selected(move |uuid, _| {
+            selected_ref.borrow_mut().push(String::from(uuid));
+            true
+        })
+        .unwrap();
+
+        (ui, selected)
+    }
+
+    #[test]
+    fn configured_mnemonics_win() {
+        let mut list = items(&["Firefox", "Google Chrome"]);
+        assign_mnemonics(&mut list, |item| (item.title == "Google Chrome").then_some('F'));
+
+        assert_eq!(mnemonics(&list), [Some('i'), Some('f')]);
+    }
+
+    #[test]
+    fn mnemonics_are_word_initials_first() {
+        let mut list = items(&["Google Chrome", "Microsoft Edge"]);
+        assign_mnemonics(&mut list, |_| None);
+
+        assert_eq!(mnemonics(&list), [Some('g'), Some('m')]);
+    }
+
+    #[test]
+    fn mnemonics_skip_taken_letters() {
+        let mut list = items(&["Firefox", "Firefox Nightly", "Falkon"]);
+        assign_mnemonics(&mut list, |_| None);
+
+        assert_eq!(mnemonics(&list), [Some('f'), Some('n'), Some('a')]);
+    }
+
+    #[test]
+    fn mnemonics_are_letters_only() {
+        let mut list = items(&["360 Browser", "42"]);
+        assign_mnemonics(&mut list, |_| None);
+
+        assert_eq!(mnemonics(&list), [Some('b'), None]);
+    }
+
+    #[test]
+    fn numbers_open_the_list_items_until_a_filter_is_typed() {
+        let (ui, selected) = selector(&["Firefox", "Chrome", "Edge"]);
+        handle_key(&ui, SelectorKey::Number(1));
+        assert_eq!(*selected.borrow(), ["Chrome"]);
+
+        // once typing, the frontends send the digits as characters
+        handle_key(&ui, SelectorKey::Char('e'));
+        handle_key(&ui, SelectorKey::Char('7'));
+        assert_eq!(ui.get_filter(), "e7");
+        assert_eq!(selected.borrow().len(), 1);
+    }
+
+    #[test]
+    fn numbers_past_the_list_do_nothing() {
+        let (ui, selected) = selector(&["Firefox"]);
+        handle_key(&ui, SelectorKey::Number(4));
+
+        assert!(selected.borrow().is_empty());
+    }
+
+    #[test]
+    fn escape_closes_one_thing_at_a_time() {
+        let (ui, selected) = selector(&["Firefox", "Chrome"]);
+        handle_key(&ui, SelectorKey::Char('f'));
+        ui.set_url_edit(Some(UrlEdit::new("https://example.com"))).unwrap();
+        ui.set_history(Some(&items(&["https://example.com"]))).unwrap();
+        ui.set_qr_code(Some("https://example.com")).unwrap();
+
+        assert!(!handle_key(&ui, SelectorKey::Escape));
+        assert!(ui.get_qr_code().is_none() && ui.is_history_shown());
+        assert!(!handle_key(&ui, SelectorKey::Escape));
+        assert!(!ui.is_history_shown() && ui.get_url_edit().is_some());
+        // the filter went with the history, one typed for the browsers is cleared last
+        ui.set_filter("f").unwrap();
+        assert!(!handle_key(&ui, SelectorKey::Escape));
+        assert!(ui.get_url_edit().is_none());
+        assert!(!handle_key(&ui, SelectorKey::Escape));
+        assert_eq!(ui.get_filter(), "");
+        assert!(handle_key(&ui, SelectorKey::Escape));
+        assert!(selected.borrow().is_empty());
+    }
+
+    #[test]
+    fn enter_opens_the_top_match() {
+        let (ui, selected) = selector(&["Firefox", "Chrome"]);
+        handle_key(&ui, SelectorKey::Char('c'));
+        handle_key(&ui, SelectorKey::Enter(SelectionAction::Open));
+
+        assert_eq!(*selected.borrow(), ["Chrome"]);
+    }
+}
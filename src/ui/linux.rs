@@ -0,0 +1,721 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Instant;
+
+use egui::{Align, Color32, Layout, RichText, Sense, TextureHandle, TextureOptions};
+use winit::dpi::PhysicalSize;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
+use winit::window::{Window, WindowBuilder, WindowId};
+
+use super::ev_loop::UserEvent;
+use super::software_painter::{SoftwarePainter, MAX_TEXTURE_SIDE};
+use super::{Image, ListItem, SelectionAction, UrlEdit, UserInterface};
+use crate::conf::{ConfigTheme, ListDensity, ThemeMode, WindowPlacement};
+use crate::error::{BSError, BSResult};
+use crate::i18n::text;
+use crate::os::IconPixels;
+
+/// How big list icons are drawn, in points.
+const ICON_SIZE: f32 = 32.0;
+const COMPACT_ICON_SIZE: f32 = 20.0;
+/// How big the QR code is drawn, in points.
+const QR_CODE_SIZE: f32 = 240.0;
+/// Lines scrolled per mouse wheel notch are this many points.
+const SCROLL_LINE_HEIGHT: f32 = 24.0;
+
+type UrlChangedHandler = Box<dyn FnMut(&str)>;
+type BrowserSelectedHandler = Box<dyn FnMut(&str, SelectionAction) -> bool>;
+
+/// The selector window on Linux. It runs on the same winit event loop as the Windows one and is
+/// drawn with egui, painted on the CPU by [SoftwarePainter] into the window with softbuffer.
+///
+/// egui draws a whole frame at a time from the state kept here, the setters only change the state
+/// and ask for a new frame which [paint](BrowserSelectorUI::paint) draws once the events are handled.
+pub struct BrowserSelectorUI<T: Clone> {
+    window: Option<Window>,
+    surface: RefCell<Option<softbuffer::GraphicsContext>>,
+    /// Taken out once [run](UserInterface::run) starts it
+    event_loop: Option<EventLoop<UserEvent>>,
+    ev_loop_proxy: EventLoopProxy<UserEvent>,
+    egui_ctx: egui::Context,
+    /// Its colors and font size are in the style of [egui_ctx](Self::egui_ctx), the rest applies as drawn
+    theme: ConfigTheme,
+    placement: WindowPlacement,
+    painter: RefCell<SoftwarePainter>,
+    /// The input gathered since the last frame
+    input: RefCell<egui::RawInput>,
+    pointer_pos: Cell<egui::Pos2>,
+    start_time: Instant,
+    needs_repaint: Cell<bool>,
+    /// When egui asked to be drawn again, ie. while the spinners turn
+    repaint_at: Cell<Option<Instant>>,
+    /// Textures by list item uuid for the icons, with the pixels they were made from
+    icons: RefCell<HashMap<String, (Rc<IconPixels>, TextureHandle)>>,
+    urls: RefCell<Vec<String>>,
+    url_edit: RefCell<Option<UrlEdit>>,
+    url_changed_handler: RefCell<Option<UrlChangedHandler>>,
+    warning: RefCell<Option<String>>,
+    /// Every item set, the list shows the ones matching the filter
+    items: Vec<ListItem<T>>,
+    /// Listed in place of the items while set, see [UserInterface::set_history]
+    history: RefCell<Option<Vec<ListItem<T>>>>,
+    list: RefCell<Vec<ListItem<T>>>,
+    filter: RefCell<String>,
+    list_is_loading: Cell<bool>,
+    selected_index: Cell<isize>,
+    /// Set when the keyboard moved the selection, the list scrolls to it on the next frame
+    scroll_to_selected: Cell<bool>,
+    /// `None` until a prediction starts, the section is hidden without statistics
+    prediction_duration: RefCell<Option<String>>,
+    prediction_is_loading: Cell<bool>,
+    predictions: Vec<ListItem<T>>,
+    visible: Cell<bool>,
+    /// The text shown as QR code with its pixels, one per module
+    qr_code: RefCell<Option<(String, Rc<IconPixels>)>>,
+    browser_selected_handler: RefCell<Option<BrowserSelectedHandler>>,
+}
+
+impl<T: Clone + 'static> UserInterface<T> for BrowserSelectorUI<T> {
+    fn new() -> BSResult<Self> {
+        let event_loop = super::ev_loop::make_ev_loop();
+
+        Ok(BrowserSelectorUI {
+            window: None,
+            surface: RefCell::new(None),
+            ev_loop_proxy: event_loop.create_proxy(),
+            event_loop: Some(event_loop),
+            egui_ctx: egui::Context::default(),
+            theme: ConfigTheme::default(),
+            placement: WindowPlacement::default(),
+            painter: Default::default(),
+            input: Default::default(),
+            pointer_pos: Cell::new(egui::Pos2::ZERO),
+            start_time: Instant::now(),
+            needs_repaint: Cell::new(true),
+            repaint_at: Cell::new(None),
+            icons: Default::default(),
+            urls: Default::default(),
+            url_edit: RefCell::new(None),
+            url_changed_handler: RefCell::new(None),
+            warning: Default::default(),
+            items: Vec::new(),
+            history: RefCell::new(None),
+            list: Default::default(),
+            filter: Default::default(),
+            list_is_loading: Cell::new(false),
+            selected_index: Cell::new(-1),
+            scroll_to_selected: Cell::new(false),
+            prediction_duration: RefCell::new(None),
+            prediction_is_loading: Cell::new(false),
+            predictions: Vec::new(),
+            visible: Cell::new(false),
+            qr_code: RefCell::new(None),
+            browser_selected_handler: RefCell::new(None),
+        })
+    }
+
+    fn create(&mut self, title: &str) -> BSResult<()> {
+        let event_loop = self
+            .event_loop
+            .as_ref()
+            .expect("The event loop is already running, the UI must be created before.");
+        let window = WindowBuilder::new()
+            .with_title(title)
+            .with_decorations(true)
+            .with_always_on_top(true)
+            .with_inner_size(winit::dpi::LogicalSize {
+                height: self.theme.max_height,
+                width: self.theme.width,
+            })
+            .with_resizable(self.placement == WindowPlacement::Last)
+            .with_visible(false)
+            .build(event_loop)
+            .map_err(|err| BSError::platform(&format!("Failed to create the main window: {}", err)))?;
+        let surface = unsafe { softbuffer::GraphicsContext::new(&window, &window) }
+            .map_err(|err| BSError::platform(&format!("Cannot draw in the main window: {}", err)))?;
+
+        *self.surface.borrow_mut() = Some(surface);
+        self.window = Some(window);
+
+        Ok(())
+    }
+
+    fn set_theme(&mut self, theme: &ConfigTheme) -> BSResult<()> {
+        let is_dark = match theme.mode {
+            // egui is dark by default, it stays so when the desktop doesn't tell
+            ThemeMode::System => crate::os::prefers_dark_mode().unwrap_or(true),
+            ThemeMode::Light => false,
+            ThemeMode::Dark => true,
+        };
+
+        // from the default style so that options removed from the config go back to it
+        let mut style = egui::Style {
+            visuals: match is_dark {
+                true => egui::Visuals::dark(),
+                false => egui::Visuals::light(),
+            },
+            ..Default::default()
+        };
+        if let Some([red, green, blue]) = theme.accent_rgb() {
+            // translucent for the selected row, so that its text stays readable
+            style.visuals.selection.bg_fill = Color32::from_rgba_unmultiplied(red, green, blue, 96);
+            style.visuals.selection.stroke.color = Color32::from_rgb(red, green, blue);
+        }
+        if let Some(size) = theme.font_size {
+            for (text_style, font) in style.text_styles.iter_mut() {
+                font.size = match text_style {
+                    egui::TextStyle::Small => size * 0.8,
+                    egui::TextStyle::Heading => size * 1.4,
+                    _ => size,
+                };
+            }
+        }
+        self.egui_ctx.set_style(style);
+
+        if let Some(window) = self.window.as_ref() {
+            window.set_inner_size(winit::dpi::LogicalSize::new(theme.width, theme.max_height));
+        }
+        self.theme = theme.clone();
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn set_window_placement(&mut self, placement: WindowPlacement) -> BSResult<()> {
+        if let Some(window) = self.window.as_ref() {
+            window.set_resizable(placement == WindowPlacement::Last);
+        }
+        self.placement = placement;
+
+        Ok(())
+    }
+
+    fn run(
+        ui_ref: Rc<RefCell<Self>>,
+        keep_running: bool,
+        delegate: impl FnMut(&mut ControlFlow) + 'static,
+    ) -> ! {
+        let event_loop = ui_ref
+            .borrow_mut()
+            .event_loop
+            .take()
+            .expect("The event loop is already running.");
+
+        event_loop.run(super::ev_loop::make_runner(ui_ref, keep_running, delegate))
+    }
+
+    fn set_main_window_visible(&self, visible: bool) {
+        self.visible.set(visible);
+        self.invalidate();
+        let window = self.window.as_ref().expect("No main window. ui::create needs to be called.");
+        super::placement::set_window_visible(window, visible, self.placement);
+    }
+
+    fn set_list(&mut self, list: &[ListItem<T>]) -> BSResult<()> {
+        // the list is set again as browsers are discovered, keep the item the user moved to
+        let selected_uuid = usize::try_from(self.selected_index.get())
+            .ok()
+            .and_then(|index| self.list.borrow().get(index).map(|item| item.uuid.clone()));
+
+        self.items = list.to_vec();
+        *self.list.borrow_mut() = self.rank_shown_items(&self.filter.borrow());
+        self.select_uuid_or_first(selected_uuid);
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn set_list_is_loading(&self, is_loading: bool) -> BSResult<()> {
+        self.list_is_loading.set(is_loading);
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn set_urls(&self, urls: &[String]) -> BSResult<()> {
+        *self.urls.borrow_mut() = urls.to_vec();
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn get_url(&self) -> String {
+        self.urls.borrow().first().cloned().unwrap_or_default()
+    }
+
+    fn set_url_edit(&self, edit: Option<UrlEdit>) -> BSResult<()> {
+        *self.url_edit.borrow_mut() = edit;
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn get_url_edit(&self) -> Option<UrlEdit> {
+        self.url_edit.borrow().clone()
+    }
+
+    fn on_url_changed(&mut self, event_handler: impl FnMut(&str) + 'static) -> BSResult<()> {
+        *self.url_changed_handler.borrow_mut() = Some(Box::new(event_handler));
+
+        Ok(())
+    }
+
+    fn trigger_url_changed(&self, url: &str) {
+        if let Some(first_url) = self.urls.borrow_mut().first_mut() {
+            *first_url = String::from(url);
+        }
+        self.invalidate();
+        if let Some(handler) = self.url_changed_handler.borrow_mut().as_mut() {
+            handler(url);
+        }
+    }
+
+    fn set_warning(&self, warning: Option<&str>) -> BSResult<()> {
+        *self.warning.borrow_mut() = warning.map(String::from);
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn image_from_pixels(pixels: &IconPixels) -> BSResult<Image> {
+        Ok(Some(Rc::new(pixels.clone())))
+    }
+
+    fn select_list_item_by_index(&self, index: isize) -> BSResult<()> {
+        if index >= 0 && (index as usize) < self.list.borrow().len() {
+            self.selected_index.set(index);
+            self.scroll_to_selected.set(true);
+            self.invalidate();
+        }
+
+        Ok(())
+    }
+
+    fn get_selected_list_item_index(&self) -> BSResult<isize> {
+        Ok(self.selected_index.get())
+    }
+
+    fn get_selected_list_item(&self) -> BSResult<Option<ListItem<T>>> {
+        Ok(usize::try_from(self.selected_index.get())
+            .ok()
+            .and_then(|index| self.list.borrow().get(index).cloned()))
+    }
+
+    fn get_list_item(&self, index: usize) -> BSResult<Option<ListItem<T>>> {
+        Ok(self.list.borrow().get(index).cloned())
+    }
+
+    fn get_list_length(&self) -> BSResult<usize> {
+        Ok(self.list.borrow().len())
+    }
+
+    fn set_filter(&self, query: &str) -> BSResult<()> {
+        *self.filter.borrow_mut() = String::from(query);
+        *self.list.borrow_mut() = self.rank_shown_items(query);
+        self.select_uuid_or_first(None);
+        self.scroll_to_selected.set(true);
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn get_filter(&self) -> String {
+        self.filter.borrow().clone()
+    }
+
+    fn set_qr_code(&self, text: Option<&str>) -> BSResult<()> {
+        *self.qr_code.borrow_mut() = match text {
+            Some(text) => Some((String::from(text), Rc::new(super::qr::pixels(text, 1)?))),
+            None => None,
+        };
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn get_qr_code(&self) -> Option<String> {
+        self.qr_code.borrow().as_ref().map(|(text, _)| text.clone())
+    }
+
+    fn set_history(&self, history: Option<&[ListItem<T>]>) -> BSResult<()> {
+        *self.history.borrow_mut() = history.map(<[ListItem<T>]>::to_vec);
+        // what was typed to find a browser wouldn't find a link
+        self.set_filter("")
+    }
+
+    fn is_history_shown(&self) -> bool {
+        self.history.borrow().is_some()
+    }
+
+    fn prediction_set_is_loading(&self, is_loading: bool) -> BSResult<()> {
+        self.prediction_is_loading.set(is_loading);
+        self.prediction_duration
+            .borrow_mut()
+            .get_or_insert_with(|| String::from("..."));
+        self.invalidate();
+
+        Ok(())
+    }
+
+    fn prediction_set_state(&mut self, list: &[ListItem<T>], duration: &str) -> BSResult<()> {
+        self.prediction_set_is_loading(false)?;
+        *self.prediction_duration.borrow_mut() = Some(String::from(duration));
+        self.predictions = list.to_vec();
+
+        Ok(())
+    }
+
+    fn prediction_get_state(&self) -> &[ListItem<T>] {
+        &self.predictions
+    }
+
+    fn on_browser_selected(
+        &mut self,
+        event_handler: impl FnMut(&str, SelectionAction) -> bool + 'static,
+    ) -> BSResult<()> {
+        *self.browser_selected_handler.borrow_mut() = Some(Box::new(event_handler));
+
+        Ok(())
+    }
+
+    fn trigger_browser_selected(&self, uuid: &str, action: SelectionAction) {
+        if let Some(handler) = self.browser_selected_handler.borrow_mut().as_mut() {
+            if handler(uuid, action) {
+                self.ev_loop_proxy.send_event(UserEvent::Close).ok();
+            }
+        }
+    }
+
+    fn destroy(&self) {
+        self.surface.borrow_mut().take();
+    }
+}
+
+impl<T: Clone + 'static> BrowserSelectorUI<T> {
+    pub fn get_window_id(&self) -> WindowId {
+        self.window.as_ref().expect("Mising main window.").id()
+    }
+
+    pub fn update_layout_size(&self, _size: &PhysicalSize<u32>) -> BSResult<()> {
+        // the next frame is drawn at the new size
+        self.invalidate();
+
+        Ok(())
+    }
+
+    /// Hands the mouse input to egui, the keyboard is handled by the event loop as for the other frontends.
+    pub fn handle_window_event(&self, event: &WindowEvent) {
+        let pixels_per_point = self.pixels_per_point();
+        let mut input = self.input.borrow_mut();
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let pos = egui::pos2(
+                    position.x as f32 / pixels_per_point,
+                    position.y as f32 / pixels_per_point,
+                );
+                self.pointer_pos.set(pos);
+                input.events.push(egui::Event::PointerMoved(pos));
+            }
+            WindowEvent::CursorLeft { .. } => input.events.push(egui::Event::PointerGone),
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    MouseButton::Other(_) => return,
+                };
+                let modifiers = input.modifiers;
+                input.events.push(egui::Event::PointerButton {
+                    pos: self.pointer_pos.get(),
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers,
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => egui::vec2(*x, *y) * SCROLL_LINE_HEIGHT,
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        egui::vec2(delta.x as f32, delta.y as f32) / pixels_per_point
+                    }
+                };
+                input.events.push(egui::Event::Scroll(delta));
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                input.modifiers = egui::Modifiers {
+                    alt: modifiers.alt(),
+                    ctrl: modifiers.ctrl(),
+                    shift: modifiers.shift(),
+                    mac_cmd: false,
+                    command: modifiers.ctrl(),
+                };
+            }
+            WindowEvent::Focused(_) | WindowEvent::ScaleFactorChanged { .. } => (),
+            _ => return,
+        }
+
+        self.invalidate();
+    }
+
+    /// Asks for the window to be drawn again, the frame is drawn by the next [paint](Self::paint).
+    pub fn invalidate(&self) {
+        self.needs_repaint.set(true);
+    }
+
+    /// Draws a frame if anything changed since the last one or egui asked for it.
+    pub fn paint(&self) {
+        let is_due = self.needs_repaint.get()
+            || self.repaint_at.get().is_some_and(|repaint_at| repaint_at <= Instant::now());
+        if !self.visible.get() || !is_due {
+            return;
+        }
+        let Some(window) = self.window.as_ref() else { return; };
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+
+        let pixels_per_point = self.pixels_per_point();
+        let mut input = self.input.take();
+        input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(size.width as f32, size.height as f32) / pixels_per_point,
+        ));
+        input.pixels_per_point = Some(pixels_per_point);
+        input.max_texture_side = Some(MAX_TEXTURE_SIDE);
+        input.time = Some(self.start_time.elapsed().as_secs_f64());
+        // the modifiers last until they change, only the events are for this frame
+        self.input.borrow_mut().modifiers = input.modifiers;
+
+        let mut clicked_uuid = None;
+        let output = self.egui_ctx.run(input, |ctx| self.draw(ctx, &mut clicked_uuid));
+        self.needs_repaint.set(false);
+        self.repaint_at.set(Instant::now().checked_add(output.repaint_after));
+
+        let primitives = self.egui_ctx.tessellate(output.shapes);
+        let mut painter = self.painter.borrow_mut();
+        let pixels = painter.paint(
+            size.width as usize,
+            size.height as usize,
+            pixels_per_point,
+            &output.textures_delta,
+            &primitives,
+        );
+        if let Some(surface) = self.surface.borrow_mut().as_mut() {
+            surface.set_buffer(pixels, size.width as u16, size.height as u16);
+        }
+
+        if let Some(uuid) = clicked_uuid {
+            // clicking with the modifiers does what they do with Enter
+            let action = match self.egui_ctx.input(|input| input.modifiers) {
+                modifiers if modifiers.ctrl => SelectionAction::CopyUrls,
+                modifiers if modifiers.shift => SelectionAction::OpenPrivate,
+                _ => SelectionAction::Open,
+            };
+            self.trigger_browser_selected(&uuid, action);
+        }
+    }
+
+    /// The history while it is shown, otherwise the items, that match the `query`.
+    fn rank_shown_items(&self, query: &str) -> Vec<ListItem<T>> {
+        match self.history.borrow().as_deref() {
+            Some(history) => super::filter::rank(history, query),
+            None => super::filter::rank(&self.items, query),
+        }
+    }
+
+    /// Selects the listed item with `uuid`, or the first one when it isn't listed.
+    fn select_uuid_or_first(&self, uuid: Option<String>) {
+        let list = self.list.borrow();
+        let index = uuid
+            .and_then(|uuid| list.iter().position(|item| item.uuid == uuid))
+            .unwrap_or(0);
+        self.selected_index.set(match list.is_empty() {
+            true => -1,
+            false => index as isize,
+        });
+    }
+
+    fn pixels_per_point(&self) -> f32 {
+        self.window
+            .as_ref()
+            .map_or(1.0, |window| window.scale_factor() as f32)
+    }
+
+    /// The header with the URLs, the list and the predictions, the same sections as the WinUI window has.
+    fn draw(&self, ctx: &egui::Context, clicked_uuid: &mut Option<String>) {
+        egui::TopBottomPanel::top("header").show(ctx, |ui| {
+            let urls = self.urls.borrow();
+            ui.add_space(4.0);
+            ui.label(text("open-intro"));
+            match self.url_edit.borrow().as_ref() {
+                Some(edit) => self.url_edit_field(ui, edit),
+                None => {
+                    let mut url_text = RichText::new(super::collapsed_urls_text(&urls)).strong();
+                    if self.theme.accent_rgb().is_some() {
+                        url_text = url_text.color(ui.visuals().selection.stroke.color);
+                    }
+                    ui.label(url_text)
+                        .on_hover_text(format!("{}\n\n{}", urls.join("\n"), text("url-edit-hint")));
+                }
+            }
+            if let Some(warning) = self.warning.borrow().as_ref() {
+                ui.colored_label(ui.visuals().warn_fg_color, warning);
+            }
+            let filter = self.filter.borrow();
+            if !filter.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.weak(text("filter-label"));
+                    ui.label(filter.as_str());
+                });
+            }
+            ui.add_space(4.0);
+        });
+
+        if let Some(duration) = self.prediction_duration.borrow().as_ref() {
+            egui::TopBottomPanel::bottom("predictions").show(ctx, |ui| {
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    ui.label(text("predictions"));
+                    ui.weak(duration);
+                });
+                if self.prediction_is_loading.get() {
+                    ui.spinner();
+                }
+                for (key, item) in [text("key-space"), text("key-backspace")].iter().zip(self.predictions.iter()) {
+                    if self.list_row(ui, item, key, false).clicked() {
+                        *clicked_uuid = Some(item.uuid.clone());
+                    }
+                }
+                ui.add_space(4.0);
+            });
+        }
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if let Some((url, pixels)) = self.qr_code.borrow().as_ref() {
+                ui.vertical_centered(|ui| {
+                    // the modules are scaled up without smoothing so that they stay sharp
+                    let texture = self.texture(ui.ctx(), "qr-code", pixels, TextureOptions::NEAREST);
+                    ui.image(texture.id(), egui::vec2(QR_CODE_SIZE, QR_CODE_SIZE));
+                    ui.weak(url);
+                    ui.weak(text("qr-code-close"));
+                });
+                return;
+            }
+
+            if self.is_history_shown() {
+                ui.strong(text("history"));
+                ui.weak(text("history-hint"));
+            } else if self.list_is_loading.get() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.weak(text("browsers-looking"));
+                });
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                let selected_index = usize::try_from(self.selected_index.get()).ok();
+                for (index, item) in self.list.borrow().iter().enumerate() {
+                    let shortcut = super::keys::shortcut_hint(index, item);
+                    let is_selected = selected_index == Some(index);
+                    let response = self.list_row(ui, item, &shortcut, is_selected);
+                    if response.clicked() {
+                        *clicked_uuid = Some(item.uuid.clone());
+                    }
+                    if is_selected && self.scroll_to_selected.take() {
+                        response.scroll_to_me(None);
+                    }
+                }
+            });
+        });
+    }
+
+    /// The URL being edited with its caret, and why it wasn't taken if it wasn't.
+    fn url_edit_field(&self, ui: &mut egui::Ui, edit: &UrlEdit) {
+        let (before, after) = edit.split_at_cursor();
+        egui::Frame::none()
+            .stroke(ui.visuals().selection.stroke)
+            .inner_margin(2.0)
+            .rounding(2.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 0.0;
+                    ui.label(RichText::new(before).strong());
+                    ui.label(RichText::new("|").color(ui.visuals().selection.stroke.color));
+                    ui.label(RichText::new(after).strong());
+                });
+            });
+        if let Some(error) = edit.error.as_ref() {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+
+    /// A clickable row with the icon, title and subtitle of the `item`, and the key that picks it.
+    fn list_row(&self, ui: &mut egui::Ui, item: &ListItem<T>, key: &str, is_selected: bool) -> egui::Response {
+        let fill = match is_selected {
+            true => ui.visuals().selection.bg_fill,
+            false => Color32::TRANSPARENT,
+        };
+
+        let (margin, icon_size) = match self.theme.density {
+            ListDensity::Compact => (1.0, COMPACT_ICON_SIZE),
+            ListDensity::Comfortable => (4.0, ICON_SIZE),
+        };
+
+        egui::Frame::none()
+            .fill(fill)
+            .inner_margin(margin)
+            .rounding(4.0)
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.horizontal(|ui| {
+                    if self.theme.icons {
+                        match self.icon_texture(ui.ctx(), item) {
+                            Some(texture) => ui.image(texture.id(), egui::vec2(icon_size, icon_size)),
+                            None => ui.allocate_exact_size(egui::vec2(icon_size, icon_size), Sense::hover()).1,
+                        };
+                    }
+                    ui.vertical(|ui| {
+                        ui.label(RichText::new(&item.title).strong());
+                        if self.theme.subtitles {
+                            ui.weak(&item.subtitle);
+                        }
+                    });
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| ui.weak(key));
+                });
+            })
+            .response
+            .interact(Sense::click())
+    }
+
+    /// The texture for the icon of `item`, made when the item is first drawn with these pixels.
+    fn icon_texture(&self, ctx: &egui::Context, item: &ListItem<T>) -> Option<TextureHandle> {
+        let pixels = item.image.as_ref()?;
+
+        Some(self.texture(ctx, &item.uuid, pixels, TextureOptions::LINEAR))
+    }
+
+    /// The texture named `name` with the `pixels`, made again only when they change.
+    fn texture(&self, ctx: &egui::Context, name: &str, pixels: &Rc<IconPixels>, options: TextureOptions) -> TextureHandle {
+        let mut icons = self.icons.borrow_mut();
+        if let Some((known_pixels, texture)) = icons.get(name) {
+            if Rc::ptr_eq(known_pixels, pixels) {
+                return texture.clone();
+            }
+        }
+
+        let rgba: Vec<u8> = pixels
+            .bgra
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect();
+        let image = egui::ColorImage::from_rgba_unmultiplied([pixels.width as usize, pixels.height as usize], &rgba);
+        let texture = ctx.load_texture(name, image, options);
+        icons.insert(String::from(name), (Rc::clone(pixels), texture.clone()));
+
+        texture
+    }
+}
@@ -1,12 +1,25 @@
 #[cfg(target_os = "windows")]
 mod win;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+mod software_painter;
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 pub mod ev_loop;
+pub mod filter;
+pub mod keys;
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+pub mod placement;
+pub mod qr;
+mod tui;
+pub mod url_edit;
 
+use crate::conf::{ConfigTheme, WindowPlacement};
 use crate::error::BSResult;
-use winit::dpi::PhysicalSize;
-use winit::event_loop::EventLoop;
-use winit::window::{WindowId};
+use crate::os::IconPixels;
+use winit::event_loop::ControlFlow;
 
+use std::cell::RefCell;
 use std::rc::Rc;
 
 /*
@@ -14,47 +27,163 @@ use std::rc::Rc;
 */
 #[cfg(target_os = "windows")]
 pub type Image = bindings::windows::ui::xaml::controls::Image;
+/// The icon pixels, the window turns them into a texture when it first draws them
+#[cfg(target_os = "linux")]
+pub type Image = Option<Rc<IconPixels>>;
+/// Only the window frontends show icons
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub type Image = ();
 #[cfg(target_os = "windows")]
 pub use win::BrowserSelectorUI;
+#[cfg(target_os = "linux")]
+pub use linux::BrowserSelectorUI;
+pub use tui::TerminalUI;
+pub use url_edit::UrlEdit;
 
-use self::ev_loop::UserEvent;
 #[cfg(target_os = "windows")]
 mod windows_desktop_window_xaml_source;
 
+/// The frontends the selector can be shown with, picked with `--ui`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frontend {
+    /// A window, WinUI on Windows and drawn with egui on Linux
+    Gui,
+    /// Full screen in the terminal we were started from
+    Tui,
+}
+
+impl Frontend {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gui" => Some(Frontend::Gui),
+            "tui" => Some(Frontend::Tui),
+            _ => None,
+        }
+    }
+
+    /// The terminal frontend when there is no display to show a window on, ie. over SSH.
+    pub fn detect() -> Self {
+        #[cfg(target_os = "windows")]
+        let has_display = std::env::var_os("SSH_CONNECTION").is_none();
+        #[cfg(not(target_os = "windows"))]
+        let has_display = ["DISPLAY", "WAYLAND_DISPLAY"]
+            .iter()
+            .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()));
+
+        match has_display {
+            true => Frontend::Gui,
+            false => Frontend::Tui,
+        }
+    }
+}
+
 pub trait UserInterface<T: Clone> {
-    fn new() -> BSResult<BrowserSelectorUI<T>>;
-    fn create(&mut self, window_title: &str, event_loop: &EventLoop<UserEvent>) -> BSResult<()>;
+    fn new() -> BSResult<Self>
+    where
+        Self: Sized;
+    fn create(&mut self, window_title: &str) -> BSResult<()>;
+    /// Applies the `theme` config, it is set before [create](Self::create) and again when the
+    /// config changes. What a frontend can't change on a window already shown applies from the
+    /// next start, ie. the colors of the WinUI window.
+    fn set_theme(&mut self, theme: &ConfigTheme) -> BSResult<()>;
+    /// Sets where the window opens, applied each time it is shown. With [WindowPlacement::Last] it
+    /// can be resized and where it is is saved as it hides. The terminal stays where it is.
+    fn set_window_placement(&mut self, placement: WindowPlacement) -> BSResult<()>;
+    /// Runs the UI until it is closed, calling `delegate` on every iteration of its event loop. The
+    /// delegate can set the control flow to [ControlFlow::Exit] to close the UI. With `keep_running`
+    /// closing the selector only hides it, as done in daemon mode.
+    fn run(
+        ui_ref: Rc<RefCell<Self>>,
+        keep_running: bool,
+        delegate: impl FnMut(&mut ControlFlow) + 'static,
+    ) -> !
+    where
+        Self: Sized;
     fn set_main_window_visible(&self, visible: bool);
-    fn get_window_id(&self) -> WindowId;
 
+    /// Replaces the items of the list, the selected item stays selected if it is still listed.
     fn set_list(&mut self, list: &[ListItem<T>]) -> BSResult<()>;
-    fn set_url(&self, url: &str) -> BSResult<()>;
+    /// Shows that more items are on their way while the list fills.
+    fn set_list_is_loading(&self, is_loading: bool) -> BSResult<()>;
+    /// Shows the URLs about to be opened, see [collapsed_urls_text].
+    fn set_urls(&self, urls: &[String]) -> BSResult<()>;
+    /// The first of the URLs shown, the one the user can edit.
+    fn get_url(&self) -> String;
+    /// Shows the first URL being edited, `None` shows it as set again.
+    fn set_url_edit(&self, edit: Option<UrlEdit>) -> BSResult<()>;
+    fn get_url_edit(&self) -> Option<UrlEdit>;
+    /// The `event_handler` gets the first URL once the user confirmed an edit of it.
+    fn on_url_changed(&mut self, event_handler: impl FnMut(&str) + 'static) -> BSResult<()>;
+    /// Shows `url` as the first URL and hands it to the handler set with [on_url_changed](Self::on_url_changed).
+    fn trigger_url_changed(&self, url: &str);
+    /// Shows a non-blocking warning above the list, `None` hides it.
+    fn set_warning(&self, warning: Option<&str>) -> BSResult<()>;
 
-    fn update_layout_size(&self, size: &PhysicalSize<u32>) -> BSResult<()>;
-    fn load_image(path: &str) -> BSResult<Image>;
+    /// Makes the image for icon pixels read in the background, see [crate::os::sys_browsers::read_browser_icon].
+    fn image_from_pixels(pixels: &IconPixels) -> BSResult<Image>
+    where
+        Self: Sized;
 
     fn select_list_item_by_index(&self, index: isize) -> BSResult<()>;
     fn get_selected_list_item_index(&self) -> BSResult<isize>;
     fn get_selected_list_item(&self) -> BSResult<Option<ListItem<T>>>;
+    /// The item shown at `index` in the list, `None` past its end.
+    fn get_list_item(&self, index: usize) -> BSResult<Option<ListItem<T>>>;
     fn get_list_length(&self) -> BSResult<usize>;
+    /// Shows only the list items matching the typed `query`, best match first as ranked by
+    /// [filter::rank], and selects the top one. An empty query shows the whole list again.
+    fn set_filter(&self, query: &str) -> BSResult<()>;
+    fn get_filter(&self) -> String;
+    /// Shows the QR code of `text` in place of the list, `None` shows the list again. See [qr].
+    fn set_qr_code(&self, text: Option<&str>) -> BSResult<()>;
+    fn get_qr_code(&self) -> Option<String>;
+    /// Lists the `history` items in place of the browsers, to pick a link to open again. `None`
+    /// lists the browsers again. Filtering and the keys work on the history while it is shown.
+    fn set_history(&self, history: Option<&[ListItem<T>]>) -> BSResult<()>;
+    fn is_history_shown(&self) -> bool;
     fn prediction_set_is_loading(&self, is_loading: bool) -> BSResult<()>;
     fn prediction_set_state(&mut self, list: &[ListItem<T>], duration: &str) -> BSResult<()>;
     fn prediction_get_state(&self) -> &[ListItem<T>];
 
+    /// The `event_handler` gets the uuid of the chosen item and what to do with it. The selector
+    /// closes once it returns, unless it returns false to keep showing.
     fn on_browser_selected(
         &mut self,
-        event_handler: impl FnMut(&str) -> () + 'static,
+        event_handler: impl FnMut(&str, SelectionAction) -> bool + 'static,
     ) -> BSResult<()>;
-    fn trigger_browser_selected(&self, uuid: &str);
+    fn trigger_browser_selected(&self, uuid: &str, action: SelectionAction);
 
     fn destroy(&self);
 }
 
+/// The URLs as the header shows them: the first one followed by how many others there are,
+/// the full list is up to the UI to show on demand.
+pub fn collapsed_urls_text(urls: &[String]) -> String {
+    match urls {
+        [] => String::new(),
+        [url] => url.clone(),
+        [url, others @ ..] => crate::i18n::text_args("urls-more", &[("url", url.into()), ("count", others.len().into())]),
+    }
+}
+
+/// What is done with the browser picked in the selector.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SelectionAction {
+    #[default]
+    Open,
+    /// Opens the URLs in a private window, ie. with Shift+Enter
+    OpenPrivate,
+    /// Copies the URLs instead of opening them, ie. with Ctrl+Enter
+    CopyUrls,
+}
+
 #[derive(Clone)]
 pub struct ListItem<T: Clone> {
     pub title: String,
     pub subtitle: String,
     pub image: Image,
     pub uuid: String,
+    /// Letter opening the item together with Alt, see [keys::assign_mnemonics]
+    pub mnemonic: Option<char>,
     pub state: Rc<T>,
 }
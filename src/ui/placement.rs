@@ -0,0 +1,160 @@
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::monitor::MonitorHandle;
+use winit::window::Window;
+
+use crate::conf::WindowPlacement;
+use crate::data::window_geometry::{SavedGeometries, WindowGeometry};
+use crate::error::{BSError, BSResult};
+
+/// Shows or hides the `window` of a frontend. It is placed as configured as it shows and, with
+/// [WindowPlacement::Last], where it is is saved as it hides.
+pub fn set_window_visible(window: &Window, visible: bool, placement: WindowPlacement) {
+    let result = match (visible, placement) {
+        (true, placement) => place_window(window, placement),
+        (false, WindowPlacement::Last) => save_window_geometry(window),
+        (false, _) => Ok(()),
+    };
+    if let Err(err) = result {
+        crate::log_error("Couldn't place the window", &err);
+    }
+
+    window.set_visible(visible);
+}
+
+/// Moves the `window` where the `placement` says, always within the work area of a monitor so
+/// that it can't open off screen or under the taskbar. Wayland ignores it, the compositor places
+/// the windows.
+pub fn place_window(window: &Window, placement: WindowPlacement) -> BSResult<()> {
+    let size = window.outer_size();
+    let geometry = match placement {
+        WindowPlacement::Cursor => centered(&work_area(&cursor_monitor(window)?), size),
+        WindowPlacement::Center => centered(&work_area(&primary_monitor(window)?), size),
+        WindowPlacement::Last => {
+            let path = SavedGeometries::default_path()?;
+            match SavedGeometries::read(&path)?.get(&monitor_setup(window)) {
+                Some(saved) => saved.clamp_to(&work_area(&monitor_of(window, &saved)?)),
+                None => centered(&work_area(&cursor_monitor(window)?), size),
+            }
+        }
+        WindowPlacement::Fixed { x, y } => {
+            let fixed = WindowGeometry {
+                x,
+                y,
+                width: size.width,
+                height: size.height,
+            };
+            fixed.clamp_to(&work_area(&monitor_of(window, &fixed)?))
+        }
+    };
+
+    if (geometry.width, geometry.height) != (size.width, size.height) {
+        // the saved size includes the frame, as the position does
+        let inner_size = window.inner_size();
+        window.set_inner_size(PhysicalSize::new(
+            geometry.width.saturating_sub(size.width.saturating_sub(inner_size.width)),
+            geometry.height.saturating_sub(size.height.saturating_sub(inner_size.height)),
+        ));
+    }
+    window.set_outer_position(PhysicalPosition::new(geometry.x, geometry.y));
+
+    Ok(())
+}
+
+/// Saves where the `window` is for the monitors there are, to be placed there again by
+/// [WindowPlacement::Last].
+pub fn save_window_geometry(window: &Window) -> BSResult<()> {
+    let position = window
+        .outer_position()
+        .map_err(|err| BSError::platform(&format!("Can't tell where the window is: {}", err)))?;
+    let size = window.outer_size();
+
+    let path = SavedGeometries::default_path()?;
+    let mut saved = SavedGeometries::read(&path).unwrap_or_default();
+    saved.set(
+        &monitor_setup(window),
+        WindowGeometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        },
+    );
+    saved.write(&path)
+}
+
+/// Identifies the monitors as they are arranged, ie. `1920x1080+0+0 2560x1440+1920+0`.
+fn monitor_setup(window: &Window) -> String {
+    let mut monitors: Vec<String> = window
+        .available_monitors()
+        .map(|monitor| {
+            let bounds = bounds(&monitor);
+            format!("{}x{}{:+}{:+}", bounds.width, bounds.height, bounds.x, bounds.y)
+        })
+        .collect();
+    monitors.sort();
+
+    monitors.join(" ")
+}
+
+fn bounds(monitor: &MonitorHandle) -> WindowGeometry {
+    let position = monitor.position();
+    let size = monitor.size();
+
+    WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    }
+}
+
+/// The part of the `monitor` that windows can use, without the taskbar or the panels.
+fn work_area(monitor: &MonitorHandle) -> WindowGeometry {
+    let bounds = bounds(monitor);
+    let (center_x, center_y) = bounds.center();
+
+    crate::os::get_work_area(center_x, center_y)
+        .and_then(|(x, y, width, height)| WindowGeometry { x, y, width, height }.intersection(&bounds))
+        .unwrap_or(bounds)
+}
+
+fn centered(area: &WindowGeometry, size: PhysicalSize<u32>) -> WindowGeometry {
+    let (center_x, center_y) = area.center();
+
+    WindowGeometry {
+        x: center_x - (size.width / 2) as i32,
+        y: center_y - (size.height / 2) as i32,
+        width: size.width,
+        height: size.height,
+    }
+    .clamp_to(area)
+}
+
+/// The monitor showing the mouse cursor, or the primary one when the cursor can't be found.
+fn cursor_monitor(window: &Window) -> BSResult<MonitorHandle> {
+    let cursor_monitor = crate::os::get_cursor_position().and_then(|(x, y)| {
+        window
+            .available_monitors()
+            .find(|monitor| bounds(monitor).contains(x, y))
+    });
+
+    cursor_monitor.map_or_else(|| primary_monitor(window), Ok)
+}
+
+/// The monitor showing the center of `geometry`, or the primary one when none does, ie. when
+/// the monitor it was saved on is gone.
+fn monitor_of(window: &Window, geometry: &WindowGeometry) -> BSResult<MonitorHandle> {
+    let (center_x, center_y) = geometry.center();
+    let monitor = window
+        .available_monitors()
+        .find(|monitor| bounds(monitor).contains(center_x, center_y));
+
+    monitor.map_or_else(|| primary_monitor(window), Ok)
+}
+
+fn primary_monitor(window: &Window) -> BSResult<MonitorHandle> {
+    window
+        .primary_monitor()
+        .or_else(|| window.available_monitors().next())
+        .ok_or_else(|| BSError::platform("There is no monitor to show the window on"))
+}
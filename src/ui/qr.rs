@@ -0,0 +1,99 @@
+use qrcode::{Color, QrCode};
+
+use crate::error::{BSError, BSResult};
+use crate::os::IconPixels;
+
+/// Light modules kept around the code, scanners need the margin to find it.
+const QUIET_ZONE: usize = 4;
+
+/// The modules of the QR code for `text` with the quiet zone around them, row by row and true
+/// for the dark ones, and how many there are on a side. Made locally, nothing leaves the machine.
+fn modules(text: &str) -> BSResult<(usize, Vec<bool>)> {
+    let code = QrCode::new(text.as_bytes())
+        .map_err(|err| BSError::new(&format!("Can't make a QR code for the URL: {}", err)))?;
+    let code_side = code.width();
+    let colors = code.to_colors();
+
+    let side = code_side + 2 * QUIET_ZONE;
+    let is_dark = (0..side * side)
+        .map(|index| {
+            let (row, column) = (index / side, index % side);
+            let in_code = QUIET_ZONE..QUIET_ZONE + code_side;
+            in_code.contains(&row)
+                && in_code.contains(&column)
+                && colors[(row - QUIET_ZONE) * code_side + column - QUIET_ZONE] == Color::Dark
+        })
+        .collect();
+
+    Ok((side, is_dark))
+}
+
+/// The QR code for `text` as black on white pixels, `module_size` pixels wide per module.
+pub fn pixels(text: &str, module_size: usize) -> BSResult<IconPixels> {
+    let (side, is_dark) = modules(text)?;
+    let pixel_side = side * module_size;
+
+    let bgra = (0..pixel_side * pixel_side)
+        .flat_map(|index| {
+            let (row, column) = (index / pixel_side / module_size, index % pixel_side / module_size);
+            match is_dark[row * side + column] {
+                true => [0, 0, 0, 255],
+                false => [255, 255, 255, 255],
+            }
+        })
+        .collect();
+
+    Ok(IconPixels {
+        width: pixel_side as i32,
+        height: pixel_side as i32,
+        bgra,
+    })
+}
+
+/// The QR code for `text` as lines of half blocks, two module rows per line. The blocks stand for
+/// the light modules, they have to be drawn light on a dark background to be scanned.
+pub fn text_lines(text: &str) -> BSResult<Vec<String>> {
+    let (side, is_dark) = modules(text)?;
+    let is_light = |row: usize, column: usize| row < side && !is_dark[row * side + column];
+
+    Ok((0..side)
+        .step_by(2)
+        .map(|row| {
+            (0..side)
+                .map(|column| match (is_light(row, column), is_light(row + 1, column)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 19 bytes take a version 2 code, 25 modules on a side
+    const URL: &str = "https://example.com";
+
+    #[test]
+    fn modules_are_square_with_the_quiet_zone() {
+        let (side, is_dark) = modules(URL).unwrap();
+        assert_eq!(side, 25 + 2 * QUIET_ZONE);
+        assert_eq!(is_dark.len(), side * side);
+        assert!((0..side).all(|index| !is_dark[index] && !is_dark[index * side]));
+        // the top left finder pattern starts right after the quiet zone
+        assert!(is_dark[QUIET_ZONE * side + QUIET_ZONE]);
+    }
+
+    #[test]
+    fn text_lines_hold_two_rows_each() {
+        let side = 25 + 2 * QUIET_ZONE;
+        let lines = text_lines(URL).unwrap();
+        assert_eq!(lines.len(), side.div_ceil(2));
+        assert!(lines.iter().all(|line| line.chars().count() == side));
+        assert!(lines[0].chars().all(|c| c == '█'));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use egui::epaint::{ImageDelta, Primitive, Vertex};
+use egui::{ClippedPrimitive, Color32, ImageData, TextureId, TexturesDelta, Vec2};
+
+/// Largest texture egui is allowed to ask for, the font atlas grows up to it.
+pub const MAX_TEXTURE_SIDE: usize = 2048;
+
+/// A texture as egui gives it, premultiplied sRGBA rows top to bottom.
+struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color32>,
+}
+
+impl Texture {
+    /// Nearest texel to the normalized coordinates `uv`, text is laid out on whole pixels so
+    /// filtering wouldn't add anything at the list's sizes.
+    fn sample(&self, uv: Vec2) -> Color32 {
+        let x = ((uv.x * self.width as f32) as usize).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+}
+
+/// Draws the triangles egui tessellates into a pixel buffer on the CPU, so that showing the window
+/// needs no GPU or OpenGL driver. The selector is small and mostly still, which keeps this cheap.
+#[derive(Default)]
+pub struct SoftwarePainter {
+    textures: HashMap<TextureId, Texture>,
+    canvas: Vec<Color32>,
+    /// The canvas in the `0RGB` format the window buffer takes
+    pixels: Vec<u32>,
+}
+
+impl SoftwarePainter {
+    /// Paints the `primitives` onto a canvas of `width` by `height` physical pixels and returns
+    /// its pixels. The textures are updated as told by `textures_delta` on the way.
+    pub fn paint(
+        &mut self,
+        width: usize,
+        height: usize,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        primitives: &[ClippedPrimitive],
+    ) -> &[u32] {
+        for (id, delta) in textures_delta.set.iter() {
+            self.set_texture(*id, delta);
+        }
+
+        self.canvas.clear();
+        self.canvas.resize(width * height, Color32::BLACK);
+        for primitive in primitives.iter() {
+            let Primitive::Mesh(mesh) = &primitive.primitive else { continue; };
+            let Some(texture) = self.textures.get(&mesh.texture_id) else { continue; };
+            let clip_min = primitive.clip_rect.min.to_vec2() * pixels_per_point;
+            let clip_max = primitive.clip_rect.max.to_vec2() * pixels_per_point;
+            let clip = [
+                clip_min.x.max(0.0) as usize,
+                clip_min.y.max(0.0) as usize,
+                (clip_max.x.max(0.0) as usize).min(width),
+                (clip_max.y.max(0.0) as usize).min(height),
+            ];
+
+            for triangle in mesh.indices.chunks_exact(3) {
+                let vertices = [
+                    &mesh.vertices[triangle[0] as usize],
+                    &mesh.vertices[triangle[1] as usize],
+                    &mesh.vertices[triangle[2] as usize],
+                ];
+                draw_triangle(&mut self.canvas, width, clip, vertices, pixels_per_point, texture);
+            }
+        }
+
+        for id in textures_delta.free.iter() {
+            self.textures.remove(id);
+        }
+
+        self.pixels.clear();
+        self.pixels.extend(
+            self.canvas
+                .iter()
+                .map(|color| (color.r() as u32) << 16 | (color.g() as u32) << 8 | color.b() as u32),
+        );
+        &self.pixels
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        let (size, pixels): ([usize; 2], Vec<Color32>) = match &delta.image {
+            ImageData::Color(image) => (image.size, image.pixels.clone()),
+            ImageData::Font(image) => (image.size, image.srgba_pixels(None).collect()),
+        };
+
+        match delta.pos {
+            None => {
+                let texture = Texture {
+                    width: size[0],
+                    height: size[1],
+                    pixels,
+                };
+                self.textures.insert(id, texture);
+            }
+            // only a region changed, ie. glyphs were added to the font atlas
+            Some([x, y]) => {
+                let Some(texture) = self.textures.get_mut(&id) else { return; };
+                for (row, row_pixels) in pixels.chunks_exact(size[0]).enumerate() {
+                    let start = (y + row) * texture.width + x;
+                    texture.pixels[start..start + size[0]].copy_from_slice(row_pixels);
+                }
+            }
+        }
+    }
+}
+
+/// Fills the pixels whose center is in the triangle, within the `clip` rectangle given as
+/// `[min_x, min_y, max_x, max_y]`. Colors and texture coordinates are interpolated across it,
+/// the anti-aliasing egui adds as thin faded triangles along edges works as is.
+fn draw_triangle(
+    canvas: &mut [Color32],
+    width: usize,
+    clip: [usize; 4],
+    vertices: [&Vertex; 3],
+    pixels_per_point: f32,
+    texture: &Texture,
+) {
+    let points = vertices.map(|vertex| vertex.pos.to_vec2() * pixels_per_point);
+    let area = edge(points[0], points[1], points[2]);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+
+    let min_x = points.iter().map(|point| point.x).fold(f32::INFINITY, f32::min);
+    let max_x = points.iter().map(|point| point.x).fold(f32::NEG_INFINITY, f32::max);
+    let min_y = points.iter().map(|point| point.y).fold(f32::INFINITY, f32::min);
+    let max_y = points.iter().map(|point| point.y).fold(f32::NEG_INFINITY, f32::max);
+    let x_range = (min_x.floor().max(clip[0] as f32) as usize)..(max_x.ceil().max(0.0) as usize).min(clip[2]);
+    let y_range = (min_y.floor().max(clip[1] as f32) as usize)..(max_y.ceil().max(0.0) as usize).min(clip[3]);
+
+    for y in y_range {
+        for x in x_range.clone() {
+            let center = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            // barycentric weights, dividing by the signed area makes the winding order irrelevant
+            let weights = [
+                edge(points[1], points[2], center) / area,
+                edge(points[2], points[0], center) / area,
+                edge(points[0], points[1], center) / area,
+            ];
+            if weights.iter().any(|weight| *weight < 0.0) {
+                continue;
+            }
+
+            let uv = vertices
+                .iter()
+                .zip(weights.iter())
+                .fold(Vec2::ZERO, |uv, (vertex, weight)| uv + vertex.uv.to_vec2() * *weight);
+            let color = interpolate_color(vertices.map(|vertex| vertex.color), weights);
+            let source = multiply(color, texture.sample(uv));
+            let pixel = &mut canvas[y * width + x];
+            *pixel = blend(source, *pixel);
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `c`.
+fn edge(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn interpolate_color(colors: [Color32; 3], weights: [f32; 3]) -> Color32 {
+    let channel = |index: usize| {
+        let value: f32 = colors
+            .iter()
+            .zip(weights.iter())
+            .map(|(color, weight)| color.to_array()[index] as f32 * weight)
+            .sum();
+        value.round().clamp(0.0, 255.0) as u8
+    };
+
+    Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+}
+
+/// Tints the texel with the vertex color, both premultiplied.
+fn multiply(color: Color32, texel: Color32) -> Color32 {
+    let [r, g, b, a] = color.to_array();
+    let [texel_r, texel_g, texel_b, texel_a] = texel.to_array();
+    let channel = |value: u8, texel_value: u8| ((value as u16 * texel_value as u16 + 127) / 255) as u8;
+
+    Color32::from_rgba_premultiplied(
+        channel(r, texel_r),
+        channel(g, texel_g),
+        channel(b, texel_b),
+        channel(a, texel_a),
+    )
+}
+
+/// Premultiplied `source` over `destination`.
+fn blend(source: Color32, destination: Color32) -> Color32 {
+    let remaining = 255 - source.a() as u16;
+    let channel = |source_value: u8, destination_value: u8| {
+        (source_value as u16 + (destination_value as u16 * remaining + 127) / 255).min(255) as u8
+    };
+
+    Color32::from_rgba_premultiplied(
+        channel(source.r(), destination.r()),
+        channel(source.g(), destination.g()),
+        channel(source.b(), destination.b()),
+        channel(source.a(), destination.a()),
+    )
+}
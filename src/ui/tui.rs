@@ -0,0 +1,552 @@
+use std::cell::{Cell, RefCell};
+use std::io::Stdout;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Span, Spans, Text};
+use ratatui::widgets::{Block, Borders, List, ListState, Paragraph};
+use ratatui::Terminal;
+use winit::event_loop::ControlFlow;
+
+use super::keys::{handle_key, SelectorKey};
+use super::{Image, ListItem, SelectionAction, UrlEdit, UserInterface};
+use crate::conf::{ConfigTheme, ListDensity, WindowPlacement};
+use crate::error::BSResult;
+use crate::i18n::{text, text_args};
+use crate::os::IconPixels;
+
+/// How long to wait for a key press before giving the delegate its turn, same pace as the window event loop.
+const TICK: Duration = Duration::from_millis(10);
+
+type UrlChangedHandler = Box<dyn FnMut(&str)>;
+type BrowserSelectedHandler = Box<dyn FnMut(&str, SelectionAction) -> bool>;
+
+/// The selector drawn in the terminal it was started from, for when there is no display such as over SSH.
+/// It takes the whole terminal screen until closed and is driven by the keyboard only.
+pub struct TerminalUI<T: Clone> {
+    terminal: RefCell<Option<Terminal<CrosstermBackend<Stdout>>>>,
+    title: String,
+    /// Only the accent, the density and the subtitles apply, the terminal has its own colors and size
+    theme: ConfigTheme,
+    urls: RefCell<Vec<String>>,
+    url_edit: RefCell<Option<UrlEdit>>,
+    url_changed_handler: RefCell<Option<UrlChangedHandler>>,
+    warning: RefCell<Option<String>>,
+    /// Every item set, the list shows the ones matching the filter
+    items: Vec<ListItem<T>>,
+    /// Listed in place of the items while set, see [UserInterface::set_history]
+    history: RefCell<Option<Vec<ListItem<T>>>>,
+    list: RefCell<Vec<ListItem<T>>>,
+    filter: RefCell<String>,
+    list_is_loading: Cell<bool>,
+    selected_index: Cell<isize>,
+    /// `None` until a prediction starts, the section is hidden without statistics
+    prediction_duration: RefCell<Option<String>>,
+    prediction_is_loading: Cell<bool>,
+    predictions: Vec<ListItem<T>>,
+    visible: Cell<bool>,
+    /// The text shown as QR code with the lines drawing it, see [super::qr::text_lines]
+    qr_code: RefCell<Option<(String, Vec<String>)>>,
+    browser_selected_handler: RefCell<Option<BrowserSelectedHandler>>,
+    close_requested: Cell<bool>,
+    /// Set when the terminal reports modifiers with Enter, they have to be asked for
+    keyboard_enhanced: Cell<bool>,
+}
+
+impl<T: Clone> UserInterface<T> for TerminalUI<T> {
+    fn new() -> BSResult<Self> {
+        Ok(TerminalUI {
+            terminal: RefCell::new(None),
+            title: String::new(),
+            theme: ConfigTheme::default(),
+            urls: Default::default(),
+            url_edit: RefCell::new(None),
+            url_changed_handler: RefCell::new(None),
+            warning: Default::default(),
+            items: Vec::new(),
+            history: RefCell::new(None),
+            list: Default::default(),
+            filter: Default::default(),
+            list_is_loading: Cell::new(false),
+            selected_index: Cell::new(-1),
+            prediction_duration: RefCell::new(None),
+            prediction_is_loading: Cell::new(false),
+            predictions: Vec::new(),
+            visible: Cell::new(false),
+            qr_code: RefCell::new(None),
+            browser_selected_handler: RefCell::new(None),
+            close_requested: Cell::new(false),
+            keyboard_enhanced: Cell::new(false),
+        })
+    }
+
+    fn create(&mut self, window_title: &str) -> BSResult<()> {
+        // the errors logged meanwhile are printed once the terminal is given back, see destroy
+        crate::hold_log();
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(std::io::stdout(), EnterAlternateScreen)?;
+        // most terminals send Shift+Enter and Ctrl+Enter as a plain Enter otherwise
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES;
+            crossterm::execute!(std::io::stdout(), PushKeyboardEnhancementFlags(flags))?;
+            self.keyboard_enhanced.set(true);
+        }
+        let terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+
+        self.title = String::from(window_title);
+        *self.terminal.borrow_mut() = Some(terminal);
+
+        Ok(())
+    }
+
+    fn set_theme(&mut self, theme: &ConfigTheme) -> BSResult<()> {
+        self.theme = theme.clone();
+
+        Ok(())
+    }
+
+    fn set_window_placement(&mut self, _placement: WindowPlacement) -> BSResult<()> {
+        Ok(())
+    }
+
+    fn run(
+        ui_ref: Rc<RefCell<Self>>,
+        keep_running: bool,
+        mut delegate: impl FnMut(&mut ControlFlow) + 'static,
+    ) -> ! {
+        loop {
+            let mut control_flow = ControlFlow::Poll;
+            if let Err(err) = ui_ref.borrow().draw() {
+                crate::log_error("Failed drawing the selector", &err);
+            }
+
+            match event::poll(TICK).and_then(|has_event| has_event.then(event::read).transpose()) {
+                Ok(Some(Event::Key(key_event))) if key_event.kind != KeyEventKind::Release => {
+                    let ui = ui_ref.borrow();
+                    if let Some(key) = selector_key(key_event, !ui.get_filter().is_empty()) {
+                        if handle_key(&*ui, key) {
+                            control_flow = ControlFlow::Exit;
+                        }
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => crate::log_error("Failed reading the terminal input", &err.into()),
+            }
+
+            if ui_ref.borrow().close_requested.take() {
+                control_flow = ControlFlow::Exit;
+            }
+            if control_flow == ControlFlow::Exit {
+                let ui = ui_ref.borrow();
+                ui.set_main_window_visible(false);
+                if keep_running {
+                    control_flow = ControlFlow::Poll;
+                } else {
+                    ui.destroy();
+                }
+            }
+
+            delegate(&mut control_flow);
+            if control_flow == ControlFlow::Exit {
+                std::process::exit(0);
+            }
+        }
+    }
+
+    fn set_main_window_visible(&self, visible: bool) {
+        self.visible.set(visible);
+    }
+
+    fn set_list(&mut self, list: &[ListItem<T>]) -> BSResult<()> {
+        // the list is set again as browsers are discovered, keep the item the user moved to
+        let selected_uuid = usize::try_from(self.selected_index.get())
+            .ok()
+            .and_then(|index| self.list.borrow().get(index).map(|item| item.uuid.clone()));
+
+        self.items = list.to_vec();
+        *self.list.borrow_mut() = self.rank_shown_items(&self.filter.borrow());
+        self.select_uuid_or_first(selected_uuid);
+
+        Ok(())
+    }
+
+    fn set_list_is_loading(&self, is_loading: bool) -> BSResult<()> {
+        self.list_is_loading.set(is_loading);
+
+        Ok(())
+    }
+
+    fn set_urls(&self, urls: &[String]) -> BSResult<()> {
+        *self.urls.borrow_mut() = urls.to_vec();
+
+        Ok(())
+    }
+
+    fn get_url(&self) -> String {
+        self.urls.borrow().first().cloned().unwrap_or_default()
+    }
+
+    fn set_url_edit(&self, edit: Option<UrlEdit>) -> BSResult<()> {
+        *self.url_edit.borrow_mut() = edit;
+
+        Ok(())
+    }
+
+    fn get_url_edit(&self) -> Option<UrlEdit> {
+        self.url_edit.borrow().clone()
+    }
+
+    fn on_url_changed(&mut self, event_handler: impl FnMut(&str) + 'static) -> BSResult<()> {
+        *self.url_changed_handler.borrow_mut() = Some(Box::new(event_handler));
+
+        Ok(())
+    }
+
+    fn trigger_url_changed(&self, url: &str) {
+        if let Some(first_url) = self.urls.borrow_mut().first_mut() {
+            *first_url = String::from(url);
+        }
+        if let Some(handler) = self.url_changed_handler.borrow_mut().as_mut() {
+            handler(url);
+        }
+    }
+
+    fn set_warning(&self, warning: Option<&str>) -> BSResult<()> {
+        *self.warning.borrow_mut() = warning.map(String::from);
+
+        Ok(())
+    }
+
+    fn image_from_pixels(_pixels: &IconPixels) -> BSResult<Image> {
+        Ok(Image::default())
+    }
+
+    fn select_list_item_by_index(&self, index: isize) -> BSResult<()> {
+        if index >= 0 && (index as usize) < self.list.borrow().len() {
+            self.selected_index.set(index);
+        }
+
+        Ok(())
+    }
+
+    fn get_selected_list_item_index(&self) -> BSResult<isize> {
+        Ok(self.selected_index.get())
+    }
+
+    fn get_selected_list_item(&self) -> BSResult<Option<ListItem<T>>> {
+        Ok(usize::try_from(self.selected_index.get())
+            .ok()
+            .and_then(|index| self.list.borrow().get(index).cloned()))
+    }
+
+    fn get_list_item(&self, index: usize) -> BSResult<Option<ListItem<T>>> {
+        Ok(self.list.borrow().get(index).cloned())
+    }
+
+    fn get_list_length(&self) -> BSResult<usize> {
+        Ok(self.list.borrow().len())
+    }
+
+    fn set_filter(&self, query: &str) -> BSResult<()> {
+        *self.filter.borrow_mut() = String::from(query);
+        *self.list.borrow_mut() = self.rank_shown_items(query);
+        self.select_uuid_or_first(None);
+
+        Ok(())
+    }
+
+    fn get_filter(&self) -> String {
+        self.filter.borrow().clone()
+    }
+
+    fn set_qr_code(&self, text: Option<&str>) -> BSResult<()> {
+        *self.qr_code.borrow_mut() = match text {
+            Some(text) => Some((String::from(text), super::qr::text_lines(text)?)),
+            None => None,
+        };
+
+        Ok(())
+    }
+
+    fn get_qr_code(&self) -> Option<String> {
+        self.qr_code.borrow().as_ref().map(|(text, _)| text.clone())
+    }
+
+    fn set_history(&self, history: Option<&[ListItem<T>]>) -> BSResult<()> {
+        *self.history.borrow_mut() = history.map(<[ListItem<T>]>::to_vec);
+        // what was typed to find a browser wouldn't find a link
+        self.set_filter("")
+    }
+
+    fn is_history_shown(&self) -> bool {
+        self.history.borrow().is_some()
+    }
+
+    fn prediction_set_is_loading(&self, is_loading: bool) -> BSResult<()> {
+        self.prediction_is_loading.set(is_loading);
+        self.prediction_duration
+            .borrow_mut()
+            .get_or_insert_with(|| String::from("..."));
+
+        Ok(())
+    }
+
+    fn prediction_set_state(&mut self, list: &[ListItem<T>], duration: &str) -> BSResult<()> {
+        self.prediction_set_is_loading(false)?;
+        *self.prediction_duration.borrow_mut() = Some(String::from(duration));
+        self.predictions = list.to_vec();
+
+        Ok(())
+    }
+
+    fn prediction_get_state(&self) -> &[ListItem<T>] {
+        &self.predictions
+    }
+
+    fn on_browser_selected(
+        &mut self,
+        event_handler: impl FnMut(&str, SelectionAction) -> bool + 'static,
+    ) -> BSResult<()> {
+        *self.browser_selected_handler.borrow_mut() = Some(Box::new(event_handler));
+
+        Ok(())
+    }
+
+    fn trigger_browser_selected(&self, uuid: &str, action: SelectionAction) {
+        if let Some(handler) = self.browser_selected_handler.borrow_mut().as_mut() {
+            if handler(uuid, action) {
+                self.close_requested.set(true);
+            }
+        }
+    }
+
+    fn destroy(&self) {
+        if let Some(mut terminal) = self.terminal.borrow_mut().take() {
+            if self.keyboard_enhanced.take() {
+                crossterm::execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags).ok();
+            }
+            terminal::disable_raw_mode().ok();
+            crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
+            terminal.show_cursor().ok();
+        }
+        crate::release_log();
+    }
+}
+
+impl<T: Clone> TerminalUI<T> {
+    /// The history while it is shown, otherwise the items, that match the `query`.
+    fn rank_shown_items(&self, query: &str) -> Vec<ListItem<T>> {
+        match self.history.borrow().as_deref() {
+            Some(history) => super::filter::rank(history, query),
+            None => super::filter::rank(&self.items, query),
+        }
+    }
+
+    /// Selects the listed item with `uuid`, or the first one when it isn't listed.
+    fn select_uuid_or_first(&self, uuid: Option<String>) {
+        let list = self.list.borrow();
+        let index = uuid
+            .and_then(|uuid| list.iter().position(|item| item.uuid == uuid))
+            .unwrap_or(0);
+        self.selected_index.set(match list.is_empty() {
+            true => -1,
+            false => index as isize,
+        });
+    }
+
+    /// Draws the header with the URLs, the list and the predictions, the same sections as the window has.
+    fn draw(&self) -> BSResult<()> {
+        let mut terminal_ref = self.terminal.borrow_mut();
+        let Some(terminal) = terminal_ref.as_mut() else { return Ok(()); };
+        if !self.visible.get() {
+            terminal.draw(|frame| frame.render_widget(Paragraph::new(""), frame.size()))?;
+            return Ok(());
+        }
+
+        let accent = match self.theme.accent_rgb() {
+            Some([red, green, blue]) => Color::Rgb(red, green, blue),
+            None => Color::Cyan,
+        };
+        let mut header = vec![Spans::from(text("open-intro"))];
+        match self.url_edit.borrow().as_ref() {
+            Some(edit) => {
+                let (before, after) = edit.split_at_cursor();
+                let mut after = after.chars();
+                // the character under the caret is drawn inverted, a space past the end
+                let caret = after.next().unwrap_or(' ');
+                header.push(Spans::from(vec![
+                    Span::raw(before.to_string()),
+                    Span::styled(caret.to_string(), Style::default().add_modifier(Modifier::REVERSED)),
+                    Span::raw(after.as_str().to_string()),
+                ]));
+                if let Some(error) = edit.error.as_ref() {
+                    header.push(Spans::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+                }
+            }
+            None => header.push(Spans::from(Span::styled(
+                super::collapsed_urls_text(&self.urls.borrow()),
+                Style::default().fg(accent),
+            ))),
+        }
+        if let Some(warning) = self.warning.borrow().as_ref() {
+            header.push(Spans::from(Span::styled(warning.clone(), Style::default().fg(Color::Red))));
+        }
+
+        let items: Vec<ratatui::widgets::ListItem> = self
+            .list
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let hint = super::keys::shortcut_hint(index, item);
+                let dim = Style::default().fg(Color::DarkGray);
+                let mut lines = vec![Spans::from(vec![
+                    Span::raw(item.title.clone()),
+                    Span::styled(format!("  {}", hint), dim),
+                ])];
+                // compact lists keep each item on a single line
+                match (self.theme.subtitles, self.theme.density) {
+                    (false, _) => (),
+                    (true, ListDensity::Compact) => lines[0].0.push(Span::styled(format!("  {}", item.subtitle), dim)),
+                    (true, ListDensity::Comfortable) => lines.push(Spans::from(Span::styled(format!("  {}", item.subtitle), dim))),
+                }
+                ratatui::widgets::ListItem::new(Text::from(lines))
+            })
+            .collect();
+        let filter = self.filter.borrow();
+        let is_history_shown = self.is_history_shown();
+        let list_title = match (is_history_shown, self.list_is_loading.get(), filter.is_empty()) {
+            (true, _, true) => text("history"),
+            (true, _, false) => text_args("history-matching", &[("filter", filter.as_str().into())]),
+            (false, true, _) => text("browsers-loading"),
+            (false, false, true) => text("browsers"),
+            (false, false, false) => text_args("browsers-matching", &[("filter", filter.as_str().into())]),
+        };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(list_title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut list_state = ListState::default();
+        list_state.select(usize::try_from(self.selected_index.get()).ok());
+
+        let prediction_duration = self.prediction_duration.borrow();
+        let prediction_lines: Vec<Spans> = match self.prediction_is_loading.get() {
+            true => vec![Spans::from("...")],
+            false => [text("key-space"), text("key-backspace")]
+                .iter()
+                .zip(self.predictions.iter())
+                .map(|(key, item)| Spans::from(format!("{} ({})", item.title, key)))
+                .collect(),
+        };
+        let predictions_height = match prediction_duration.as_ref() {
+            Some(_) => prediction_lines.len().max(1) as u16 + 2,
+            None => 0,
+        };
+
+        let qr_code = self.qr_code.borrow();
+        let footer = match (self.url_edit.borrow().is_some(), qr_code.is_some(), is_history_shown) {
+            (true, _, _) => text("footer-url-edit"),
+            (false, true, _) => text("footer-qr-code"),
+            (false, false, true) => text("footer-history"),
+            (false, false, false) => text("footer"),
+        };
+
+        terminal.draw(|frame| {
+            let sections = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [
+                        Constraint::Length(header.len() as u16 + 2),
+                        Constraint::Min(4),
+                        Constraint::Length(predictions_height),
+                        Constraint::Length(1),
+                    ]
+                    .as_ref(),
+                )
+                .split(frame.size());
+
+            frame.render_widget(
+                Paragraph::new(header).block(Block::default().borders(Borders::ALL).title(self.title.as_str())),
+                sections[0],
+            );
+            match qr_code.as_ref() {
+                // scanners expect dark modules on light, whatever the colors of the terminal
+                Some((url, lines)) => frame.render_widget(
+                    Paragraph::new(lines.iter().map(|line| Spans::from(line.as_str())).collect::<Vec<Spans>>())
+                        .style(Style::default().fg(Color::White).bg(Color::Black))
+                        .block(Block::default().borders(Borders::ALL).title(text_args("qr-code-title", &[("url", url.as_str().into())]))),
+                    sections[1],
+                ),
+                None => frame.render_stateful_widget(list, sections[1], &mut list_state),
+            }
+            if let Some(duration) = prediction_duration.as_ref() {
+                frame.render_widget(
+                    Paragraph::new(prediction_lines).block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title(text_args("predictions-title", &[("duration", duration.as_str().into())])),
+                    ),
+                    sections[2],
+                );
+            }
+            frame.render_widget(
+                Paragraph::new(Span::styled(
+                    footer,
+                    Style::default().fg(Color::DarkGray),
+                )),
+                sections[3],
+            );
+        })?;
+
+        Ok(())
+    }
+}
+
+/// The digits open the list items until a filter is typed, then they are part of it.
+fn selector_key(key_event: KeyEvent, is_filtering: bool) -> Option<SelectorKey> {
+    match key_event.code {
+        KeyCode::Down => Some(SelectorKey::Down),
+        KeyCode::Up => Some(SelectorKey::Up),
+        KeyCode::Enter => Some(SelectorKey::Enter(match key_event.modifiers {
+            modifiers if modifiers.contains(KeyModifiers::CONTROL) => SelectionAction::CopyUrls,
+            modifiers if modifiers.contains(KeyModifiers::SHIFT) => SelectionAction::OpenPrivate,
+            _ => SelectionAction::Open,
+        })),
+        KeyCode::Char(' ') => Some(SelectorKey::Space),
+        KeyCode::Backspace => Some(SelectorKey::Backspace),
+        KeyCode::Esc => Some(SelectorKey::Escape),
+        KeyCode::Left => Some(SelectorKey::Left),
+        KeyCode::Right => Some(SelectorKey::Right),
+        KeyCode::Home => Some(SelectorKey::Home),
+        KeyCode::End => Some(SelectorKey::End),
+        KeyCode::Delete => Some(SelectorKey::Delete),
+        KeyCode::Char('l') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(SelectorKey::EditUrl)
+        }
+        // raw mode turns Ctrl+C into a key press, it is expected to cancel like in any terminal app
+        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+            Some(SelectorKey::Escape)
+        }
+        KeyCode::Char(letter)
+            if key_event.modifiers.contains(KeyModifiers::ALT) && letter.is_ascii_alphabetic() =>
+        {
+            Some(SelectorKey::Mnemonic(letter))
+        }
+        KeyCode::Char(digit @ '1'..='9') if !is_filtering => {
+            Some(SelectorKey::Number(digit as isize - '1' as isize))
+        }
+        KeyCode::Char(character)
+            if !key_event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+        {
+            Some(SelectorKey::Char(character))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,140 @@
+/// The URL being edited in the header, changed by the keys in [super::keys::handle_key] so that
+/// editing works the same in every frontend. The text is only taken once it is a valid URL.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrlEdit {
+    pub text: String,
+    /// Position of the caret in characters, 0 is before the first one
+    pub cursor: usize,
+    /// Why the text was not taken when last confirmed
+    pub error: Option<String>,
+}
+
+impl UrlEdit {
+    /// Starts editing `url` with the caret at its end.
+    pub fn new(url: &str) -> Self {
+        UrlEdit {
+            text: String::from(url),
+            cursor: url.chars().count(),
+            error: None,
+        }
+    }
+
+    /// The text on each side of the caret, for drawing it.
+    pub fn split_at_cursor(&self) -> (&str, &str) {
+        self.text.split_at(self.byte_index(self.cursor))
+    }
+
+    pub fn insert(&mut self, character: char) {
+        let index = self.byte_index(self.cursor);
+        self.text.insert(index, character);
+        self.cursor += 1;
+        self.error = None;
+    }
+
+    /// Removes the character before the caret.
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete();
+        }
+    }
+
+    /// Removes the character after the caret.
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            self.text.remove(self.byte_index(self.cursor));
+            self.error = None;
+        }
+    }
+
+    pub fn move_cursor(&mut self, offset: isize) {
+        let length = self.text.chars().count() as isize;
+        self.cursor = (self.cursor as isize + offset).clamp(0, length) as usize;
+    }
+
+    pub fn move_cursor_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_cursor_to_end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    /// The URL to open, the text trimmed, or why it isn't one.
+    pub fn validate(&self) -> Result<String, String> {
+        let text = self.text.trim();
+        match url::Url::parse(text) {
+            Ok(_) => Ok(String::from(text)),
+            Err(err) => Err(crate::i18n::text_args("url-not-valid", &[("error", err.to_string().into())])),
+        }
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UrlEdit;
+
+    #[test]
+    fn starts_with_the_caret_at_the_end() {
+        let edit = UrlEdit::new("https://é.fr");
+        assert_eq!(edit.cursor, 12);
+        assert_eq!(edit.split_at_cursor(), ("https://é.fr", ""));
+    }
+
+    #[test]
+    fn edits_at_the_caret_counting_characters() {
+        let mut edit = UrlEdit::new("https://é.fr");
+        edit.move_cursor(-3);
+        edit.insert('x');
+        assert_eq!(edit.text, "https://éx.fr");
+        assert_eq!(edit.split_at_cursor(), ("https://éx", ".fr"));
+
+        edit.backspace();
+        edit.backspace();
+        assert_eq!(edit.text, "https://.fr");
+        edit.delete();
+        assert_eq!(edit.text, "https://fr");
+        assert_eq!(edit.cursor, 8);
+    }
+
+    #[test]
+    fn caret_stays_in_the_text() {
+        let mut edit = UrlEdit::new("ab");
+        edit.move_cursor(5);
+        assert_eq!(edit.cursor, 2);
+        edit.delete();
+        assert_eq!(edit.text, "ab");
+
+        edit.move_cursor(-5);
+        assert_eq!(edit.cursor, 0);
+        edit.backspace();
+        assert_eq!(edit.text, "ab");
+
+        edit.move_cursor_to_end();
+        assert_eq!(edit.cursor, 2);
+        edit.move_cursor_to_start();
+        assert_eq!(edit.cursor, 0);
+    }
+
+    #[test]
+    fn editing_clears_the_error() {
+        let mut edit = UrlEdit::new("not a url");
+        edit.error = edit.validate().err();
+        assert!(edit.error.is_some());
+        edit.insert('!');
+        assert_eq!(edit.error, None);
+    }
+
+    #[test]
+    fn takes_the_trimmed_text_once_it_is_a_url() {
+        assert_eq!(UrlEdit::new("  https://example.com/a  ").validate(), Ok("https://example.com/a".to_string()));
+        assert!(UrlEdit::new("example.com").validate().is_err());
+    }
+}
@@ -1,6 +1,4 @@
 use std::cell::RefCell;
-use std::convert::TryInto;
-use std::mem::MaybeUninit;
 use std::rc::Rc;
 
 // For clarity purposes keep all WinRT imports under wrt::
@@ -25,33 +23,31 @@ mod wrt {
         IListViewFactory, IRelativePanelFactory, IScrollViewerStatics, IStackPanelFactory, Image,
         ItemClickEventArgs, ItemClickEventHandler, ItemsControl, ListBox, ListView,
         ListViewSelectionMode, Orientation, Panel, ProgressRing, RelativePanel, RowDefinition,
-        ScrollMode, ScrollViewer, StackPanel, TextBlock,
+        ScrollMode, ScrollViewer, StackPanel, TextBlock, ToolTipService,
     };
     pub use bindings::windows::ui::xaml::interop::{TypeKind, TypeName};
     pub use bindings::windows::ui::xaml::media::imaging::{BitmapImage, SoftwareBitmapSource};
     pub use bindings::windows::ui::xaml::media::{ImageSource, SolidColorBrush};
     pub use bindings::windows::ui::xaml::{
-        FrameworkElement, GridLength, GridUnitType, RoutedEventHandler, Thickness, UIElement,
-        VerticalAlignment, Visibility,
+        ElementTheme, FrameworkElement, GridLength, GridUnitType, RoutedEventHandler, Thickness,
+        UIElement, VerticalAlignment, Visibility,
     };
     pub use bindings::windows::ui::Color;
 }
 
 mod winapi {
-    pub use winapi::ctypes::*;
-    pub use winapi::shared::windef::{HGDIOBJ, HICON, HWND, POINT};
-    pub use winapi::um::wingdi::{DeleteObject, GetBitmapBits, GetObjectW, BITMAP, DIBSECTION};
-    pub use winapi::um::winuser::{
-        GetCursorPos, GetIconInfo, SetWindowPos, UpdateWindow, ICONINFO, MONITORINFO,
-    };
+    pub use winapi::shared::windef::HWND;
+    pub use winapi::um::winuser::{SetWindowPos, UpdateWindow};
 }
 
+use crate::conf::{ConfigTheme, ListDensity, ThemeMode, WindowPlacement};
 use crate::error::*;
-use crate::os::{as_u8_slice, get_hwnd};
+use crate::i18n::{text, text_args};
+use crate::os::{get_hwnd, IconPixels};
 use crate::ui::windows_desktop_window_xaml_source::IDesktopWindowXamlSourceNative;
 
 use winit::dpi::PhysicalSize;
-use winit::event_loop::EventLoop;
+use winit::event_loop::{ControlFlow, EventLoop, EventLoopProxy};
 use winit::platform::windows::IconExtWindows;
 use winit::window::{Window, WindowBuilder, WindowId};
 
@@ -59,6 +55,8 @@ use winrt::ComInterface;
 
 use crate::ui::Image;
 use crate::ui::ListItem;
+use crate::ui::SelectionAction;
+use crate::ui::UrlEdit;
 use crate::ui::UserInterface;
 
 use super::ev_loop::UserEvent;
@@ -107,23 +105,51 @@ pub struct Theme {
     light_gray: wrt::Color,
     dark_gray: wrt::Color,
     accent: wrt::Color,
+    warning: wrt::Color,
+    mode: ThemeMode,
+    font_size: f64,
 }
 
 pub struct UIState<T: Clone> {
     pub xaml_isle: XamlIslandWindow,
-    pub list: Vec<crate::ui::ListItem<T>>,
+    /// Every item set, the list shows the ones matching the filter
+    pub items: Vec<crate::ui::ListItem<T>>,
+    /// Listed in place of the items while set, see [UserInterface::set_history]
+    pub history: RefCell<Option<Vec<crate::ui::ListItem<T>>>>,
+    pub list: RefCell<Vec<crate::ui::ListItem<T>>>,
+    pub filter: RefCell<String>,
     pub predictions: Vec<crate::ui::ListItem<T>>,
+    pub urls: RefCell<Vec<String>>,
+    pub url_edit: RefCell<Option<UrlEdit>>,
+    pub url_changed_handler: RefCell<Option<Box<dyn FnMut(&str) -> ()>>>,
     pub container: wrt::Panel,
+    /// The colors and the font of the window, made from [theme_config](Self::theme_config) when it is created
     pub theme: Theme,
+    /// The list is made again with every change, it follows the config as it changes
+    pub theme_config: ConfigTheme,
     pub window: Option<Window>,
-    pub browser_selected_handler: Option<Rc<RefCell<Box<dyn FnMut(&str) -> ()>>>>,
+    pub placement: WindowPlacement,
+    /// The text shown as QR code
+    pub qr_code: RefCell<Option<String>>,
+    pub browser_selected_handler: Option<Rc<RefCell<Box<dyn FnMut(&str, SelectionAction) -> bool>>>>,
+    /// Taken out once [run](UserInterface::run) starts it
+    pub event_loop: Option<EventLoop<UserEvent>>,
+    pub ev_loop_proxy: EventLoopProxy<UserEvent>,
 }
 
 const LIST_CONTROL_NAME: &str = "browserList";
+const LIST_SPINNER_NAME: &str = "browserListSpinner";
 const URL_CONTROL_NAME: &str = "urlControl";
 const HEADER_PANEL_NAME: &str = "headerPanel";
-
-impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<ItemStateType> {
+const INTRO_CONTROL_NAME: &str = "introControl";
+const WARNING_CONTROL_NAME: &str = "warningControl";
+const FILTER_CONTROL_NAME: &str = "filterControl";
+const URL_ERROR_CONTROL_NAME: &str = "urlErrorControl";
+const QR_CODE_CONTROL_NAME: &str = "qrCodeControl";
+/// Pixels per QR code module, the image is about the size it is shown at
+const QR_CODE_MODULE_SIZE: usize = 6;
+
+impl<ItemStateType: Clone + 'static> UserInterface<ItemStateType> for BrowserSelectorUI<ItemStateType> {
     fn new() -> BSResult<Self> {
         // TODO: Correct error handling
         // unsafe { initialize_runtime_com()?; }
@@ -132,29 +158,47 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
         // or winit throws: thread 'main'
         // panicked at 'either event handler is re-entrant (likely), or no event
         // handler is registered (very unlikely)'
+        let xaml_isle = init_win_ui_xaml()?;
+        let event_loop = super::ev_loop::make_ev_loop();
         let state = UIState {
-            xaml_isle: init_win_ui_xaml()?,
-            list: Vec::<ListItem<ItemStateType>>::new(),
+            xaml_isle,
+            items: Vec::<ListItem<ItemStateType>>::new(),
+            history: RefCell::new(None),
+            list: Default::default(),
+            filter: Default::default(),
             predictions: Vec::<ListItem<ItemStateType>>::new(),
+            urls: Default::default(),
+            url_edit: RefCell::new(None),
+            url_changed_handler: RefCell::new(None),
             container: wrt::Panel::default(),
-            theme: create_theme()?,
+            theme: Theme::default(),
+            theme_config: ConfigTheme::default(),
             window: Default::default(),
+            placement: WindowPlacement::default(),
+            qr_code: RefCell::new(None),
             browser_selected_handler: None,
+            ev_loop_proxy: event_loop.create_proxy(),
+            event_loop: Some(event_loop),
         };
 
         Ok(BrowserSelectorUI { state })
     }
 
-    fn create(&mut self, title: &str, event_loop: &EventLoop<UserEvent>) -> BSResult<()> {
+    fn create(&mut self, title: &str) -> BSResult<()> {
+        let event_loop = self
+            .state
+            .event_loop
+            .as_ref()
+            .expect("The event loop is already running, the UI must be created before.");
         let window = WindowBuilder::new()
             .with_title(title)
             .with_decorations(true)
             .with_always_on_top(true)
             .with_inner_size(winit::dpi::LogicalSize {
-                height: 400 as i16,
-                width: 400 as i16,
+                height: self.state.theme_config.max_height,
+                width: self.state.theme_config.width,
             })
-            .with_resizable(false)
+            .with_resizable(self.state.placement == WindowPlacement::Last)
             .with_visible(false)
             .build(&event_loop)
             .expect("Failed to create the main window");
@@ -166,6 +210,7 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
             winapi::UpdateWindow(self.state.xaml_isle.hwnd_parent as winapi::HWND);
         }
 
+        self.state.theme = create_theme(&self.state.theme_config)?;
         let ui_container = create_ui(&self.state, &self.state.theme)?;
 
         self.state
@@ -174,7 +219,6 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
             .set_content(ui_container.to_owned())?;
         self.state.container = ComInterface::query::<wrt::Panel>(&ui_container);
 
-        center_window_on_cursor_monitor(&window)?;
         window.set_window_icon(Some(
             winit::window::Icon::from_resource(
                 1,
@@ -191,12 +235,42 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
         Ok(())
     }
 
-    fn get_window_id(&self) -> WindowId {
-        self.state.window.as_ref().expect("Mising main window.").id()
+    fn set_theme(&mut self, theme: &ConfigTheme) -> BSResult<()> {
+        if let Some(window) = self.state.window.as_ref() {
+            window.set_inner_size(winit::dpi::LogicalSize::new(theme.width, theme.max_height));
+        }
+        self.state.theme_config = theme.clone();
+
+        Ok(())
+    }
+
+    fn set_window_placement(&mut self, placement: WindowPlacement) -> BSResult<()> {
+        if let Some(window) = self.state.window.as_ref() {
+            window.set_resizable(placement == WindowPlacement::Last);
+        }
+        self.state.placement = placement;
+
+        Ok(())
+    }
+
+    fn run(
+        ui_ref: Rc<RefCell<Self>>,
+        keep_running: bool,
+        delegate: impl FnMut(&mut ControlFlow) -> () + 'static,
+    ) -> ! {
+        let event_loop = ui_ref
+            .borrow_mut()
+            .state
+            .event_loop
+            .take()
+            .expect("The event loop is already running.");
+
+        event_loop.run(super::ev_loop::make_runner(ui_ref, keep_running, delegate))
     }
 
     fn set_main_window_visible(&self, visible: bool) {
-        self.state.window.as_ref().expect("No main window. ui::create needs to be called.").set_visible(visible)
+        let window = self.state.window.as_ref().expect("No main window. ui::create needs to be called.");
+        super::placement::set_window_visible(window, visible, self.state.placement);
     }
 
     fn destroy(&self) {
@@ -204,38 +278,86 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
         self.state.xaml_isle.desktop_source.close();
     }
 
-    fn update_layout_size(&self, size: &PhysicalSize<u32>) -> BSResult<()> {
-        update_xaml_island_size(&self.state.xaml_isle, *size)?;
+    fn set_list(&mut self, list: &[ListItem<ItemStateType>]) -> BSResult<()> {
+        // the list is set again as browsers are discovered, keep the item the user moved to
+        let selected_uuid = usize::try_from(self.get_selected_list_item_index()?)
+            .ok()
+            .and_then(|index| self.state.list.borrow().get(index).map(|item| item.uuid.clone()));
 
-        Ok(())
+        self.state.items = list.to_vec();
+        self.show_matching_items(selected_uuid)
     }
 
-    fn set_list(&mut self, list: &[ListItem<ItemStateType>]) -> BSResult<()> {
+    fn set_list_is_loading(&self, is_loading: bool) -> BSResult<()> {
         if let Some(ui_element) =
-            recursive_find_child_by_tag(&self.state.container, LIST_CONTROL_NAME)?
+            recursive_find_child_by_tag(&self.state.container, LIST_SPINNER_NAME)?
         {
-            let list_view: wrt::ListView = ComInterface::query(&ui_element);
-            self.state.list = list.clone().to_vec();
-            set_listview_items(&list_view, list, &self.state.theme)?;
+            let spinner = ComInterface::query::<wrt::ProgressRing>(&ui_element);
+            spinner.set_is_active(is_loading)?;
+            spinner.set_visibility(match is_loading {
+                true => wrt::Visibility::Visible,
+                false => wrt::Visibility::Collapsed,
+            })?;
         }
 
         Ok(())
     }
 
-    fn set_url(&self, new_url: &str) -> BSResult<()> {
+    fn set_urls(&self, urls: &[String]) -> BSResult<()> {
+        *self.state.urls.borrow_mut() = urls.to_vec();
+
+        self.show_urls()
+    }
+
+    fn get_url(&self) -> String {
+        self.state.urls.borrow().first().cloned().unwrap_or_default()
+    }
+
+    fn set_url_edit(&self, edit: Option<UrlEdit>) -> BSResult<()> {
+        *self.state.url_edit.borrow_mut() = edit;
+
+        self.show_urls()
+    }
+
+    fn get_url_edit(&self) -> Option<UrlEdit> {
+        self.state.url_edit.borrow().clone()
+    }
+
+    fn on_url_changed(&mut self, event_handler: impl FnMut(&str) -> () + 'static) -> BSResult<()> {
+        *self.state.url_changed_handler.borrow_mut() = Some(Box::new(event_handler));
+
+        Ok(())
+    }
+
+    fn trigger_url_changed(&self, url: &str) {
+        if let Some(first_url) = self.state.urls.borrow_mut().first_mut() {
+            *first_url = String::from(url);
+        }
+        if let Err(err) = self.show_urls() {
+            crate::log_error("Couldn't show the edited URL", &err);
+        }
+        if let Some(handler) = self.state.url_changed_handler.borrow_mut().as_mut() {
+            handler(url);
+        }
+    }
+
+    fn set_warning(&self, warning: Option<&str>) -> BSResult<()> {
         if let Some(ui_element) =
-            recursive_find_child_by_tag(&self.state.container, URL_CONTROL_NAME)?
+            recursive_find_child_by_tag(&self.state.container, WARNING_CONTROL_NAME)?
         {
             let text_block = ComInterface::query::<wrt::TextBlock>(&ui_element);
-            text_block.set_text(new_url)?;
+            text_block.set_text(warning.unwrap_or_default())?;
+            text_block.set_visibility(match warning {
+                Some(_) => wrt::Visibility::Visible,
+                None => wrt::Visibility::Collapsed,
+            })?;
         }
 
         Ok(())
     }
 
-    fn load_image(path: &str) -> BSResult<Image> {
-        let hicon = crate::os::get_exe_file_icon(path)?;
-        let bmp = hicon_to_software_bitmap(hicon)?;
+    fn image_from_pixels(pixels: &IconPixels) -> BSResult<Image> {
+        let bmp = icon_pixels_to_software_bitmap(pixels)?;
 
         match software_bitmap_to_xaml_image(bmp) {
             Ok(image) => Ok(image),
@@ -270,16 +392,21 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
             return Ok(None);
         }
 
-        let cloned_item = self.state.list[selected_index as usize].clone();
-        Ok(Some(cloned_item))
+        let cloned_item = self.state.list.borrow().get(selected_index as usize).cloned();
+        Ok(cloned_item)
+    }
+
+    fn get_list_item(&self, index: usize) -> BSResult<Option<ListItem<ItemStateType>>> {
+        Ok(self.state.list.borrow().get(index).cloned())
     }
 
     fn on_browser_selected(
         &mut self,
-        event_handler: impl FnMut(&str) -> () + 'static,
+        event_handler: impl FnMut(&str, SelectionAction) -> bool + 'static,
     ) -> BSResult<()> {
         self.state.browser_selected_handler = Some(Rc::new(RefCell::new(Box::new(event_handler))));
         let handler_ptr = self.state.browser_selected_handler.as_ref().unwrap().clone();
+        let ev_loop_proxy = self.state.ev_loop_proxy.clone();
         let list_control: wrt::ListView =
             recursive_find_child_by_tag(&self.state.container, LIST_CONTROL_NAME)
                 .unwrap()
@@ -294,7 +421,9 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
 
                 
                 let mut ev_handler = handler_ptr.as_ref().borrow_mut();
-                (ev_handler)(item_tag.as_str());
+                if (ev_handler)(item_tag.as_str(), SelectionAction::Open) {
+                    ev_loop_proxy.send_event(UserEvent::Close).ok();
+                }
 
                 Ok(())
             },
@@ -303,14 +432,87 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
         Ok(())
     }
 
-    fn trigger_browser_selected(&self, uuid: &str) {
+    fn trigger_browser_selected(&self, uuid: &str, action: SelectionAction) {
         if let Some(handler_ptr) = self.state.browser_selected_handler.as_ref() {
-            handler_ptr.as_ref().borrow_mut()(uuid);
+            if handler_ptr.as_ref().borrow_mut()(uuid, action) {
+                self.state.ev_loop_proxy.send_event(UserEvent::Close).ok();
+            }
         }
     }
 
     fn get_list_length(&self) -> BSResult<usize> {
-        Ok(self.state.list.len())
+        Ok(self.state.list.borrow().len())
+    }
+
+    fn set_filter(&self, query: &str) -> BSResult<()> {
+        *self.state.filter.borrow_mut() = String::from(query);
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, FILTER_CONTROL_NAME)?
+        {
+            let text_block = ComInterface::query::<wrt::TextBlock>(&ui_element);
+            text_block.set_text(format!("{} {}", text("filter-label"), query).as_str())?;
+            text_block.set_visibility(match query.is_empty() {
+                true => wrt::Visibility::Collapsed,
+                false => wrt::Visibility::Visible,
+            })?;
+        }
+
+        self.show_matching_items(None)
+    }
+
+    fn get_filter(&self) -> String {
+        self.state.filter.borrow().clone()
+    }
+
+    fn set_qr_code(&self, text: Option<&str>) -> BSResult<()> {
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, QR_CODE_CONTROL_NAME)?
+        {
+            let qr_image = ComInterface::query::<wrt::Image>(&ui_element);
+            if let Some(text) = text {
+                let pixels = super::qr::pixels(text, QR_CODE_MODULE_SIZE)?;
+                qr_image.set_source(Self::image_from_pixels(&pixels)?.source()?)?;
+            }
+            qr_image.set_visibility(match text {
+                Some(_) => wrt::Visibility::Visible,
+                None => wrt::Visibility::Collapsed,
+            })?;
+        }
+        // the QR code shows in place of the list
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, LIST_CONTROL_NAME)?
+        {
+            ui_element.set_visibility(match text {
+                Some(_) => wrt::Visibility::Collapsed,
+                None => wrt::Visibility::Visible,
+            })?;
+        }
+        *self.state.qr_code.borrow_mut() = text.map(String::from);
+
+        Ok(())
+    }
+
+    fn get_qr_code(&self) -> Option<String> {
+        self.state.qr_code.borrow().clone()
+    }
+
+    fn set_history(&self, history: Option<&[ListItem<ItemStateType>]>) -> BSResult<()> {
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, INTRO_CONTROL_NAME)?
+        {
+            let text_block = ComInterface::query::<wrt::TextBlock>(&ui_element);
+            text_block.set_text(match history {
+                Some(_) => text("history-hint"),
+                None => text("open-intro"),
+            }.as_str())?;
+        }
+        *self.state.history.borrow_mut() = history.map(<[ListItem<ItemStateType>]>::to_vec);
+        // what was typed to find a browser wouldn't find a link
+        self.set_filter("")
+    }
+
+    fn is_history_shown(&self) -> bool {
+        self.state.history.borrow().is_some()
     }
 
     fn prediction_set_is_loading(&self, is_loading: bool) -> BSResult<()> {
@@ -326,7 +528,7 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
             predictions_panel.set_padding(wrt::Thickness { top: 15.0, right: 15.0, bottom: 15.0, left: 15.0 })?;
 
             let title = wrt::TextBlock::new()?;
-            title.set_text("Predictions (...)")?;
+            title.set_text(text_args("predictions-title", &[("duration", "...".into())]).as_str())?;
             ui_element_set_string_tag(&title, "predictions_title")?;
 
             let spinner = wrt::ProgressRing::new()?;
@@ -378,10 +580,10 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
         let predictions_title =
             recursive_find_child_by_tag(&self.state.container, "predictions_title")?.unwrap();
         let predictions_title = predictions_title.query::<wrt::TextBlock>();
-        let predictions_title_str = format!("Predictions ({})", duration);
+        let predictions_title_str = text_args("predictions-title", &[("duration", duration.into())]);
         predictions_title.set_text(predictions_title_str.as_str())?;
 
-        let key_shortcuts = ["space", "backspace"];
+        let key_shortcuts = [text("key-space"), text("key-backspace")];
         let mut index = 0;
         list.iter().try_for_each::<_, BSResult<()>>(| list_item | {
             let text_block = wrt::TextBlock::new()?;
@@ -406,6 +608,85 @@ impl<ItemStateType: Clone> UserInterface<ItemStateType> for BrowserSelectorUI<It
     }
 }
 
+impl<ItemStateType: Clone + 'static> BrowserSelectorUI<ItemStateType> {
+    /// Shows the URLs in the header, or the first one with a caret while it is being edited.
+    fn show_urls(&self) -> BSResult<()> {
+        let urls = self.state.urls.borrow();
+        let url_edit = self.state.url_edit.borrow();
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, URL_CONTROL_NAME)?
+        {
+            let text_block = ComInterface::query::<wrt::TextBlock>(&ui_element);
+            let text = match url_edit.as_ref() {
+                Some(edit) => {
+                    let (before, after) = edit.split_at_cursor();
+                    format!("{}|{}", before, after)
+                }
+                None => super::collapsed_urls_text(&urls),
+            };
+            text_block.set_text(text.as_str())?;
+
+            // the whole list shows when hovering the collapsed one
+            let tool_tip = match urls.len() {
+                0 | 1 => winrt::Object::default(),
+                _ => wrt::PropertyValue::create_string(urls.join("\n").as_str())?,
+            };
+            wrt::ToolTipService::set_tool_tip(text_block.clone(), tool_tip)?;
+        }
+
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, URL_ERROR_CONTROL_NAME)?
+        {
+            let text_block = ComInterface::query::<wrt::TextBlock>(&ui_element);
+            let error = url_edit.as_ref().and_then(|edit| edit.error.as_deref());
+            text_block.set_text(error.unwrap_or_default())?;
+            text_block.set_visibility(match error {
+                Some(_) => wrt::Visibility::Visible,
+                None => wrt::Visibility::Collapsed,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills the list view with the items matching the filter and selects the one with
+    /// `selected_uuid`, or the first one when it isn't listed.
+    fn show_matching_items(&self, selected_uuid: Option<String>) -> BSResult<()> {
+        let matching = match self.state.history.borrow().as_deref() {
+            Some(history) => super::filter::rank(history, &self.state.filter.borrow()),
+            None => super::filter::rank(&self.state.items, &self.state.filter.borrow()),
+        };
+        if let Some(ui_element) =
+            recursive_find_child_by_tag(&self.state.container, LIST_CONTROL_NAME)?
+        {
+            let list_view: wrt::ListView = ComInterface::query(&ui_element);
+            let selected_index = selected_uuid
+                .and_then(|uuid| matching.iter().position(|item| item.uuid == uuid))
+                .unwrap_or(0);
+
+            list_view.items()?.clear()?;
+            set_listview_items(&list_view, &matching, &self.state.theme, &self.state.theme_config)?;
+            list_view.set_selected_index(match matching.is_empty() {
+                true => -1,
+                false => selected_index as i32,
+            })?;
+        }
+        *self.state.list.borrow_mut() = matching;
+
+        Ok(())
+    }
+
+    pub fn get_window_id(&self) -> WindowId {
+        self.state.window.as_ref().expect("Mising main window.").id()
+    }
+
+    pub fn update_layout_size(&self, size: &PhysicalSize<u32>) -> BSResult<()> {
+        update_xaml_island_size(&self.state.xaml_isle, *size)?;
+
+        Ok(())
+    }
+}
+
 pub fn init_win_ui_xaml() -> winrt::Result<XamlIslandWindow> {
     use winrt::Object;
     let mut xaml_isle = XamlIslandWindow::default();
@@ -450,8 +731,8 @@ pub fn update_xaml_island_size(
 }
 
 pub fn create_ui<T: Clone>(ui: &UIState<T>, theme: &Theme) -> winrt::Result<wrt::UIElement> {
-    let header_panel = create_header("You are about to open:", "", &theme)?;
-    let list = create_list(&ui.list, &theme)?;
+    let header_panel = create_header(&text("open-intro"), "", &theme)?;
+    let list = create_list(&ui.list.borrow(), &theme, &ui.theme_config)?;
     let grid = create_main_layout_grid(&theme)?;
 
     wrt::Grid::set_row(
@@ -465,8 +746,23 @@ pub fn create_ui<T: Clone>(ui: &UIState<T>, theme: &Theme) -> winrt::Result<wrt:
     wrt::Grid::set_row(&ComInterface::query::<wrt::FrameworkElement>(&list), 1)?;
     wrt::Grid::set_column(ComInterface::query::<wrt::FrameworkElement>(&list), 0)?;
 
+    // shown over the list while the browsers are being discovered
+    let list_spinner = wrt::ProgressRing::new()?;
+    list_spinner.set_vertical_alignment(wrt::VerticalAlignment::Top)?;
+    list_spinner.set_margin(wrt::Thickness {
+        top: 30.,
+        left: 0.,
+        right: 0.,
+        bottom: 0.,
+    })?;
+    list_spinner.set_visibility(wrt::Visibility::Collapsed)?;
+    list_spinner.set_tag(wrt::PropertyValue::create_string(LIST_SPINNER_NAME)?)?;
+    wrt::Grid::set_row(&list_spinner, 1)?;
+    wrt::Grid::set_column(&list_spinner, 0)?;
+
     grid.children()?.append(header_panel)?;
     grid.children()?.append(list)?;
+    grid.children()?.append(list_spinner)?;
 
     Ok(grid.into())
 }
@@ -496,15 +792,33 @@ pub fn create_main_layout_grid(theme: &Theme) -> winrt::Result<wrt::Grid> {
     grid.row_definitions()?.append(bottom_row_def)?;
     grid.column_definitions()?.append(column_definition)?;
     grid.set_background(create_color_brush(theme.white.clone())?)?;
+    // the controls follow the OS colors unless the config picks them
+    grid.set_requested_theme(match theme.mode {
+        ThemeMode::System => wrt::ElementTheme::Default,
+        ThemeMode::Light => wrt::ElementTheme::Light,
+        ThemeMode::Dark => wrt::ElementTheme::Dark,
+    })?;
 
     Ok(grid)
 }
 
-fn create_theme() -> winrt::Result<Theme> {
+/// The colors of the OS, or the light or dark ones of the `config` mode, and its accent and font size.
+fn create_theme(config: &ConfigTheme) -> winrt::Result<Theme> {
     let ui_settings = wrt::UISettings::new()?;
-    let os_foreground = ui_settings.get_color_value(wrt::UIColorType::Foreground)?;
-    let os_background = ui_settings.get_color_value(wrt::UIColorType::Background)?;
-    let os_accent = ui_settings.get_color_value(wrt::UIColorType::Accent)?;
+    let white = wrt::Color { a: 255, r: 255, g: 255, b: 255 };
+    let black = wrt::Color { a: 255, r: 0, g: 0, b: 0 };
+    let (os_foreground, os_background) = match config.mode {
+        ThemeMode::System => (
+            ui_settings.get_color_value(wrt::UIColorType::Foreground)?,
+            ui_settings.get_color_value(wrt::UIColorType::Background)?,
+        ),
+        ThemeMode::Light => (black, white),
+        ThemeMode::Dark => (white, black),
+    };
+    let os_accent = match config.accent_rgb() {
+        Some([r, g, b]) => wrt::Color { a: 255, r, g, b },
+        None => ui_settings.get_color_value(wrt::UIColorType::Accent)?,
+    };
     let is_os_dark_mode = is_light_color(&os_foreground);
 
     let mut light_gray = os_foreground.clone();
@@ -525,6 +839,10 @@ fn create_theme() -> winrt::Result<Theme> {
         light_gray,
         dark_gray,
         accent: os_accent,
+        warning: wrt::Color { a: 255, r: 0xC4, g: 0x2B, b: 0x1C },
+        mode: config.mode,
+        // the XAML default
+        font_size: config.font_size.map_or(14.0, f64::from),
     })
 }
 
@@ -542,16 +860,23 @@ fn is_light_color(color: &wrt::Color) -> bool {
 pub fn create_list_item(
     title: &str,
     subtext: &str,
+    shortcut_hint: &str,
     image: &wrt::Image,
     tag: &str,
     theme: &Theme,
+    theme_config: &ConfigTheme,
 ) -> winrt::Result<wrt::UIElement> {
+    let vertical_margin = match theme_config.density {
+        ListDensity::Compact => 0.,
+        ListDensity::Comfortable => 5.,
+    };
     let list_item_margins = wrt::Thickness {
-        top: 5.,
+        top: vertical_margin,
         left: 15.,
         right: 15.,
-        bottom: 5.,
+        bottom: vertical_margin,
     };
+    let font_size = theme_config.font_size.map_or(theme.font_size, f64::from);
     let root_stack_panel = create_stack_panel()?;
     root_stack_panel.set_orientation(wrt::Orientation::Horizontal)?;
 
@@ -560,19 +885,39 @@ pub fn create_list_item(
 
     let title_block = wrt::TextBlock::new()?;
     title_block.set_text(title)?;
-
-    let subtitle_block = wrt::TextBlock::new()?;
-    subtitle_block.set_text(subtext)?;
-    subtitle_block.set_foreground(create_color_brush(theme.dark_gray.clone())?)?;
-
+    title_block.set_font_size(font_size)?;
     name_version_stack_panel.children()?.append(title_block)?;
-    name_version_stack_panel
-        .children()?
-        .append(subtitle_block)?;
-    root_stack_panel.children()?.append(image)?;
+
+    if theme_config.subtitles {
+        let subtitle_block = wrt::TextBlock::new()?;
+        subtitle_block.set_text(subtext)?;
+        subtitle_block.set_font_size(font_size * 0.85)?;
+        subtitle_block.set_foreground(create_color_brush(theme.dark_gray.clone())?)?;
+        name_version_stack_panel
+            .children()?
+            .append(subtitle_block)?;
+    }
+    if theme_config.icons {
+        // a XAML element can only have one parent, the list can be rebuilt (ie. on config reload)
+        // while the previous list items still hold the original image
+        let item_image = wrt::Image::new()?;
+        item_image.set_source(image.source()?)?;
+        if theme_config.density == ListDensity::Compact {
+            item_image.set_width(20.0)?;
+            item_image.set_height(20.0)?;
+        }
+        root_stack_panel.children()?.append(item_image)?;
+    }
     root_stack_panel
         .children()?
         .append(name_version_stack_panel)?;
+
+    let shortcut_block = wrt::TextBlock::new()?;
+    shortcut_block.set_text(shortcut_hint)?;
+    shortcut_block.set_font_size(font_size * 0.85)?;
+    shortcut_block.set_foreground(create_color_brush(theme.dark_gray.clone())?)?;
+    shortcut_block.set_vertical_alignment(wrt::VerticalAlignment::Center)?;
+    root_stack_panel.children()?.append(shortcut_block)?;
     ui_element_set_string_tag(&root_stack_panel, tag).unwrap();
 
     Ok(root_stack_panel.into())
@@ -588,6 +933,7 @@ pub fn create_stack_panel() -> winrt::Result<wrt::StackPanel> {
 pub fn create_list<T: Clone>(
     list: &Vec<ListItem<T>>,
     theme: &Theme,
+    theme_config: &ConfigTheme,
 ) -> winrt::Result<wrt::UIElement> {
     let list_control = winrt::factory::<wrt::ListView, wrt::IListViewFactory>()?
         .create_instance(winrt::Object::default(), &mut winrt::Object::default())?;
@@ -601,7 +947,7 @@ pub fn create_list<T: Clone>(
     list_control.set_vertical_alignment(wrt::VerticalAlignment::Stretch)?;
     list_control.set_background(create_color_brush(theme.light_gray.clone())?)?;
 
-    set_listview_items(&list_control, list, theme)?;
+    set_listview_items(&list_control, list, theme, theme_config)?;
     list_control.set_selected_index(0)?;
 
     ui_element_set_string_tag(&list_control, LIST_CONTROL_NAME).unwrap();
@@ -615,16 +961,19 @@ pub fn set_listview_items<T: Clone>(
     list_control: &wrt::ListView,
     list: &[ListItem<T>],
     theme: &Theme,
+    theme_config: &ConfigTheme,
 ) -> winrt::Result<()> {
-    for item in list {
+    for (index, item) in list.iter().enumerate() {
         list_control
             .items()?
             .append(winrt::Object::from(create_list_item(
                 item.title.as_str(),
                 item.subtitle.as_str(),
+                super::keys::shortcut_hint(index, item).as_str(),
                 &item.image,
                 item.uuid.as_str(),
                 &theme,
+                theme_config,
             )?))?;
     }
 
@@ -640,16 +989,45 @@ pub fn create_header(
         .create_instance(winrt::Object::default(), &mut winrt::Object::default())?;
     let call_to_action_top_row = wrt::TextBlock::new()?;
     let call_to_action_bottom_row = wrt::TextBlock::new()?;
+    let url_error_row = wrt::TextBlock::new()?;
+    let warning_row = wrt::TextBlock::new()?;
+    let filter_row = wrt::TextBlock::new()?;
+    let qr_code_row = wrt::Image::new()?;
 
     call_to_action_top_row.set_text(open_action_text)?;
+    call_to_action_top_row.set_tag(wrt::PropertyValue::create_string(INTRO_CONTROL_NAME)?)?;
+    call_to_action_top_row.set_font_size(theme.font_size)?;
+    call_to_action_bottom_row.set_font_size(theme.font_size)?;
     call_to_action_bottom_row.set_foreground(create_color_brush(theme.accent.clone())?)?;
     call_to_action_bottom_row.set_text(url)?;
 
     call_to_action_bottom_row.set_tag(wrt::PropertyValue::create_string(URL_CONTROL_NAME)?)?;
+    url_error_row.set_foreground(create_color_brush(theme.warning.clone())?)?;
+    url_error_row.set_visibility(wrt::Visibility::Collapsed)?;
+    url_error_row.set_tag(wrt::PropertyValue::create_string(URL_ERROR_CONTROL_NAME)?)?;
+    warning_row.set_foreground(create_color_brush(theme.warning.clone())?)?;
+    warning_row.set_visibility(wrt::Visibility::Collapsed)?;
+    warning_row.set_tag(wrt::PropertyValue::create_string(WARNING_CONTROL_NAME)?)?;
+    filter_row.set_visibility(wrt::Visibility::Collapsed)?;
+    filter_row.set_tag(wrt::PropertyValue::create_string(FILTER_CONTROL_NAME)?)?;
+    qr_code_row.set_width(240.0)?;
+    qr_code_row.set_height(240.0)?;
+    qr_code_row.set_margin(wrt::Thickness {
+        left: 0.0,
+        right: 0.0,
+        top: 15.0,
+        bottom: 0.0,
+    })?;
+    qr_code_row.set_visibility(wrt::Visibility::Collapsed)?;
+    qr_code_row.set_tag(wrt::PropertyValue::create_string(QR_CODE_CONTROL_NAME)?)?;
     stack_panel.set_tag(wrt::PropertyValue::create_string(HEADER_PANEL_NAME)?)?;
 
     stack_panel.children()?.append(call_to_action_top_row)?;
     stack_panel.children()?.append(call_to_action_bottom_row)?;
+    stack_panel.children()?.append(url_error_row)?;
+    stack_panel.children()?.append(warning_row)?;
+    stack_panel.children()?.append(filter_row)?;
+    stack_panel.children()?.append(qr_code_row)?;
     stack_panel.set_margin(wrt::Thickness {
         left: 15.0,
         right: 15.0,
@@ -685,178 +1063,27 @@ pub fn software_bitmap_to_xaml_image(bmp: wrt::SoftwareBitmap) -> winrt::Result<
     return Ok(image_control);
 }
 
-/// Converts a HICON to a SoftwareBitmap that can be used with WinUI controls
-///
-/// Notes:
-/// - There probably is a simpler way to achieve this
-/// - The function does not implement all possiblities described in the Windows API doc
-/// thus it is possible that it might not work for certain icon formats
-pub fn hicon_to_software_bitmap(hicon: winapi::HICON) -> BSResult<wrt::SoftwareBitmap> {
-    let mut icon_info: winapi::ICONINFO = unsafe { MaybeUninit::uninit().assume_init() };
-    let icon_result = unsafe { winapi::GetIconInfo(hicon, &mut icon_info) };
-    if icon_result == 0 {
-        bail!("Couldn't get icon info for HICON {:?}", hicon);
-    }
-
-    let dib_struct_size = std::mem::size_of::<winapi::DIBSECTION>()
-        .try_into()
-        .unwrap_or(0);
-    let bitmap_struct_size = std::mem::size_of::<winapi::BITMAP>()
-        .try_into()
-        .unwrap_or(0);
-
-    let mut dib: winapi::DIBSECTION = unsafe { MaybeUninit::uninit().assume_init() };
-    let bytes_read = unsafe {
-        winapi::GetObjectW(
-            icon_info.hbmColor as *mut _ as *mut winapi::c_void,
-            dib_struct_size,
-            &mut dib as *mut _ as *mut winapi::c_void,
-        )
-    };
-
-    if bytes_read == 0 {
-        unsafe {
-            winapi::DeleteObject(icon_info.hbmColor as winapi::HGDIOBJ);
-            winapi::DeleteObject(icon_info.hbmMask as winapi::HGDIOBJ);
-        }
-
-        bail!("Error: winapi::GetObject returned 0 on ICONINFO.hbmColor bitmap.");
-    }
-
-    // BITMAP size is 32 bytes
-    // DIBSECTION is 104 bytes
-    let bmp_size_in_bytes =
-        (dib.dsBm.bmHeight * dib.dsBm.bmWidth) * (dib.dsBm.bmBitsPixel as i32 / 8);
-
-    let pixel_bytes_result = match bytes_read {
-        bytes_read if bytes_read == bitmap_struct_size => {
-            // when GetObject returns the size of the BITMAP structure
-            // then dib.dsBm is a device dependent bitmap we need to use GetBitmapBits
-            let mut img_bytes = Vec::<u8>::new();
-            img_bytes.resize(bmp_size_in_bytes as usize, 0);
-
-            let img_bytes_read = unsafe {
-                winapi::GetBitmapBits(
-                    icon_info.hbmColor,
-                    bmp_size_in_bytes,
-                    img_bytes.as_mut_slice().as_mut_ptr() as *mut winapi::c_void,
-                )
-            };
-            // TODO: Replace GetBitmapBits with GetDibBits because GetBitmapBits is deprecated
-
-            if img_bytes_read == 0 {
-                Err("winapi::GetBitmapBits read 0 bytes from the ICONINFO.hbmColor")
-            } else {
-                Ok(img_bytes)
-            }
-        }
-        bytes_read if bytes_read == dib_struct_size => {
-            if dib.dsBm.bmBits as usize != 0 {
-                Ok(unsafe {
-                    std::slice::from_raw_parts::<u8>(
-                        dib.dsBm.bmBits as *const u8,
-                        bmp_size_in_bytes as usize,
-                    )
-                    .to_vec()
-                })
-            } else {
-                Err("Unexpected NULL pointer for image bits from DIBSECTION.dsBm.bmBits")
-            }
-        }
-        0 => Err("winapi::GetObject returned 0 on ICONINFO.hbmColor bitmap."),
-        _ => Err(
-            "Unexpected response from winapi::GetObject, was expecting read bytes \
-            to match either the BITMAP struct size or the DIBSECTION struct size.",
-        ),
-    };
-
-    let pixel_bytes = match pixel_bytes_result {
-        Ok(bytes) => bytes,
-        Err(error) => unsafe {
-            winapi::DeleteObject(icon_info.hbmColor as winapi::HGDIOBJ);
-            winapi::DeleteObject(icon_info.hbmMask as winapi::HGDIOBJ);
-            bail!(error);
-        },
-    };
-
-    let raw_pixels = pixel_bytes
-        .chunks_exact(4)
-        .map(|chunk| {
-            u32::from_ne_bytes(
-                chunk
-                    .try_into()
-                    .expect("Expected chunk size to be 4 bytes when converting to u32"),
-            )
-        })
-        .collect::<Vec<u32>>();
-
+/// Converts the icon pixels read by [crate::os::get_exe_file_icon_pixels] to a SoftwareBitmap
+/// that can be used with WinUI controls
+pub fn icon_pixels_to_software_bitmap(pixels: &IconPixels) -> BSResult<wrt::SoftwareBitmap> {
     let data_writer = wrt::DataWriter::new()?;
-    data_writer.write_bytes(as_u8_slice(&raw_pixels[..]))?;
+    data_writer.write_bytes(pixels.bgra.as_slice())?;
 
     let i_buffer = data_writer.detach_buffer()?;
     let software_bitmap = wrt::SoftwareBitmap::create_copy_with_alpha_from_buffer(
         i_buffer,
         wrt::BitmapPixelFormat::Bgra8,
-        dib.dsBm.bmWidth,
-        dib.dsBm.bmHeight,
+        pixels.width,
+        pixels.height,
         wrt::BitmapAlphaMode::Straight,
     )?;
     // About the BitmapPixelFormat::Bgra8:
     // Hard coding pixel format to BGRA with 1 byte per color seems to work but it should be
     // detected since there are no guarantees the Windows API will always return this format
 
-    unsafe {
-        winapi::DeleteObject(icon_info.hbmColor as winapi::HGDIOBJ);
-        winapi::DeleteObject(icon_info.hbmMask as winapi::HGDIOBJ);
-    }
-
     return Ok(software_bitmap);
 }
 
-/// Centers the given [`Window`] on the monitor where the mouse cursor is found.
-fn center_window_on_cursor_monitor(window: &Window) -> winrt::Result<()> {
-    let cursor_pos = unsafe {
-        let mut point: winapi::POINT = Default::default();
-        winapi::GetCursorPos(std::ptr::addr_of_mut!(point));
-        point
-    };
-    let current_monitor = window
-        .available_monitors()
-        .find(move |monitor| {
-            let pos = monitor.position();
-            let size = monitor.size();
-
-            let is_below_top = cursor_pos.x >= pos.x && cursor_pos.y >= pos.y;
-            let is_above_bottom = cursor_pos.x <= pos.x + size.width as i32
-                && cursor_pos.y <= pos.y + size.height as i32;
-            let is_within_monitor = is_below_top && is_above_bottom;
-
-            is_within_monitor
-        })
-        .ok_or(winrt::Error::new(
-            winrt::ErrorCode(1),
-            "Could not determine monitor from the current cursor",
-        ))?;
-
-    let monitor_size = current_monitor.size();
-    let monitor_pos = current_monitor.position();
-    let window_size = window.outer_size();
-
-    let window_center_x = (window_size.width / 2) as i32;
-    let window_center_y = (window_size.height / 2) as i32;
-    let monitor_center_x = (monitor_pos.x + (monitor_size.width as i32)) / 2;
-    let monitor_center_y = (monitor_pos.y + (monitor_size.height as i32)) / 2;
-    let window_x = monitor_center_x - window_center_x;
-    let window_y = monitor_center_y - window_center_y;
-
-    window.set_outer_position(winit::dpi::PhysicalPosition {
-        x: window_x,
-        y: window_y,
-    });
-
-    Ok(())
-}
-
 fn recursive_find_child_by_tag(
     parent: &impl winrt::ComInterface,
     needle: &str,